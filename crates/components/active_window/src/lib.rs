@@ -1,27 +1,44 @@
 use std::path::PathBuf;
 use gpui::prelude::FluentBuilder;
 use gpui::*;
-use nwidgets_service_niri::{ActiveWindowChanged, NiriActiveWindowService};
+use nwidgets_service_compositor::{
+    CompositorService, CompositorStateChanged, ConnectionStatus, ConnectionStatusChanged,
+};
 
 pub struct ActiveWindowComponent {
     app_id: SharedString,
     class: SharedString,
     title: SharedString,
+    /// Set while the compositor event stream is being reconnected.
+    reconnecting: bool,
 }
 
 impl ActiveWindowComponent {
     pub fn new(cx: &mut Context<Self>) -> Self {
-        let niri = NiriActiveWindowService::global(cx);
-        let active = niri.read(cx).active_window.clone();
+        let compositor = CompositorService::global(cx);
+        let active = &compositor.read(cx).state.active_window;
 
         let (app_id, class, title) = Self::compute_window_info(&active.app_id, &active.title);
 
-        cx.subscribe(&niri, |this, _service, event: &ActiveWindowChanged, cx| {
-            let (app_id, class, title) = Self::compute_window_info(&event.0.app_id, &event.0.title);
-            this.app_id = app_id;
-            this.class = class;
-            this.title = title;
-            cx.notify();
+        cx.subscribe(&compositor, |this, service, _: &CompositorStateChanged, cx| {
+            let active = &service.read(cx).state.active_window;
+            let (app_id, class, title) = Self::compute_window_info(&active.app_id, &active.title);
+            if (&app_id, &class, &title) != (&this.app_id, &this.class, &this.title) {
+                this.app_id = app_id;
+                this.class = class;
+                this.title = title;
+                cx.notify();
+            }
+        })
+        .detach();
+
+        let reconnecting = matches!(compositor.read(cx).connection, ConnectionStatus::Reconnecting { .. });
+        cx.subscribe(&compositor, |this, _service, event: &ConnectionStatusChanged, cx| {
+            let reconnecting = matches!(event.0, ConnectionStatus::Reconnecting { .. });
+            if this.reconnecting != reconnecting {
+                this.reconnecting = reconnecting;
+                cx.notify();
+            }
         })
         .detach();
 
@@ -29,6 +46,7 @@ impl ActiveWindowComponent {
             app_id,
             class,
             title,
+            reconnecting,
         }
     }
 
@@ -107,6 +125,12 @@ impl Render for ActiveWindowComponent {
         let text_muted = cx.theme().muted_foreground;
         let text_main = cx.theme().foreground;
         let icon_path = self.resolve_icon_path();
+        let (class, title) = if self.reconnecting {
+            let backend = CompositorService::global(cx).read(cx).backend_name().unwrap_or("compositor");
+            (backend.into(), "Reconnecting...".into())
+        } else {
+            (self.class.clone(), self.title.clone())
+        };
 
         div()
             .id("active-window-component")
@@ -140,16 +164,16 @@ impl Render for ActiveWindowComponent {
                             .text_color(text_muted)
                             .overflow_hidden()
                             .whitespace_nowrap()
-                            .child(self.class.clone()),
+                            .child(class),
                     )
-                    .when(!self.title.is_empty(), |this| {
+                    .when(!title.is_empty(), |this| {
                         this.child(
                             div()
                                 .text_sm()
                                 .text_color(text_main)
                                 .overflow_hidden()
                                 .whitespace_nowrap()
-                                .child(self.title.clone()),
+                                .child(title),
                         )
                     }),
             )
@@ -0,0 +1,55 @@
+use gpui::*;
+use gpui_component::tooltip::Tooltip;
+use gpui_component::Icon;
+use nwidgets_service_config::{ConfigChanged, ConfigService};
+use nwidgets_service_power::{format_time, BatteryAlert, BatteryState, PowerService, PowerStateChanged};
+use nwidgets_service_theme::ThemeService;
+
+/// Level of the system battery; nothing on machines without one.
+pub struct BatteryComponent {
+    power: Entity<PowerService>,
+}
+
+impl BatteryComponent {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let power = PowerService::global(cx);
+        cx.subscribe(&power, |_, _, _: &PowerStateChanged, cx| cx.notify()).detach();
+        // Thresholds of the colors
+        cx.subscribe(&ConfigService::global(cx), |_, _, _: &ConfigChanged, cx| cx.notify()).detach();
+        Self { power }
+    }
+}
+
+impl Render for BatteryComponent {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let Some(battery) = self.power.read(cx).state.battery.clone() else {
+            return div().into_any_element();
+        };
+
+        // Same thresholds as the notifications
+        let alert = BatteryAlert::for_level(battery.percentage, &ConfigService::config(cx).power);
+        let color = match (battery.state, alert) {
+            (BatteryState::Charging | BatteryState::FullyCharged, _) => palette.success,
+            (_, Some(BatteryAlert::Critical)) => palette.danger,
+            (_, Some(BatteryAlert::Low)) => palette.warning,
+            _ => palette.foreground_dim,
+        };
+        let tooltip: SharedString = match (battery.state, battery.time_left()) {
+            (BatteryState::Charging, Some(secs)) => format!("Charging, full in {}", format_time(secs)).into(),
+            (state, Some(secs)) if state.is_discharging() => format!("{} remaining", format_time(secs)).into(),
+            (state, _) => state.label().into(),
+        };
+
+        div()
+            .id("battery")
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_2()
+            .tooltip(move |window, cx| Tooltip::new(tooltip.clone()).build(window, cx))
+            .child(Icon::new(battery.icon()).size(px(20.0)).text_color(color))
+            .child(div().text_xs().text_color(palette.foreground_dim).child(format!("{}%", battery.percentage)))
+            .into_any_element()
+    }
+}
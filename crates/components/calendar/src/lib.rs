@@ -1,6 +1,7 @@
 use gpui::*;
 use gpui_component::calendar::{Calendar, CalendarState};
 use gpui_component::Sizable;
+use nwidgets_service_theme::ThemeService;
 
 pub struct CalendarComponent {
     calendar_state: Entity<CalendarState>,
@@ -14,8 +15,8 @@ impl CalendarComponent {
 }
 
 impl Render for CalendarComponent {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        let card_bg = rgb(0x3b4252);
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let card_bg = ThemeService::palette(cx).surface;
 
         div()
             .w_full()
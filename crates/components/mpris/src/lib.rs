@@ -0,0 +1,177 @@
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+use gpui_component::button::{Button, ButtonVariants};
+use gpui_component::{Disableable, Icon, Sizable};
+use nwidgets_service_mpris::{MprisService, MprisStateChanged, PlaybackStatus};
+use nwidgets_service_theme::ThemeService;
+use std::time::Duration;
+
+/// Scroll distance (in pixels) for one seek step.
+const SCROLL_STEP: f32 = 40.0;
+/// Seconds skipped per scroll step.
+const SEEK_SECS: i64 = 5;
+
+pub struct MprisComponent {
+    mpris: Entity<MprisService>,
+    scroll_accumulator: f32,
+    /// Refreshes the elapsed time while a track is playing.
+    ticker: Option<Task<()>>,
+}
+
+impl MprisComponent {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let mpris = MprisService::global(cx);
+
+        cx.subscribe(&mpris, |this, _, _: &MprisStateChanged, cx| {
+            this.update_ticker(cx);
+            cx.notify();
+        })
+        .detach();
+
+        let mut this = Self {
+            mpris,
+            scroll_accumulator: 0.0,
+            ticker: None,
+        };
+        this.update_ticker(cx);
+        this
+    }
+
+    fn is_playing(&self, cx: &App) -> bool {
+        let state = &self.mpris.read(cx).state;
+        state.active_player().is_some_and(|player| player.status == PlaybackStatus::Playing)
+    }
+
+    fn update_ticker(&mut self, cx: &mut Context<Self>) {
+        if !self.is_playing(cx) {
+            self.ticker = None;
+            return;
+        }
+        if self.ticker.is_some() {
+            return;
+        }
+        self.ticker = Some(cx.spawn(async move |this, cx| loop {
+            cx.background_executor().timer(Duration::from_secs(1)).await;
+            if this.update(cx, |_, cx| cx.notify()).is_err() {
+                break;
+            }
+        }));
+    }
+
+    fn on_scroll(&mut self, event: &ScrollWheelEvent, cx: &mut Context<Self>) {
+        let delta = event.delta.pixel_delta(px(SCROLL_STEP));
+        self.scroll_accumulator += f32::from(delta.y);
+
+        let steps = (self.scroll_accumulator / SCROLL_STEP).trunc();
+        if steps == 0.0 {
+            return;
+        }
+        self.scroll_accumulator -= steps * SCROLL_STEP;
+
+        let mpris = self.mpris.read(cx);
+        if let Some(player) = mpris.state.active_player().filter(|player| player.can_seek) {
+            // Scrolling up moves forward
+            mpris.seek(&player.bus_name, steps as i64 * SEEK_SECS, cx);
+        }
+    }
+
+    fn format_time(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl Render for MprisComponent {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let Some(player) = self.mpris.read(cx).state.active_player().cloned() else {
+            return div().id("mpris-component");
+        };
+
+        let playing = player.status == PlaybackStatus::Playing;
+        let title = if player.metadata.title.is_empty() {
+            player.identity.clone()
+        } else {
+            player.metadata.title.clone()
+        };
+        let artists = player.metadata.artists.join(", ");
+        let time = match player.metadata.length {
+            Some(length) => format!(
+                "{} / {}",
+                Self::format_time(player.position_now()),
+                Self::format_time(length)
+            ),
+            None => Self::format_time(player.position_now()),
+        };
+
+        let previous_bus = player.bus_name.clone();
+        let play_bus = player.bus_name.clone();
+        let next_bus = player.bus_name.clone();
+
+        div()
+            .id("mpris-component")
+            .flex()
+            .items_center()
+            .gap_1()
+            .max_w(px(360.0))
+            .on_scroll_wheel(cx.listener(|this, event: &ScrollWheelEvent, _window, cx| this.on_scroll(event, cx)))
+            .child(
+                Button::new("mpris-previous")
+                    .ghost()
+                    .with_size(gpui_component::Size::Small)
+                    .icon(Icon::new("skip_previous").size(px(18.0)))
+                    .disabled(!player.can_go_previous)
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.mpris.read(cx).previous(&previous_bus, cx);
+                    })),
+            )
+            .child(
+                Button::new("mpris-play-pause")
+                    .ghost()
+                    .with_size(gpui_component::Size::Small)
+                    .icon(Icon::new(if playing { "pause" } else { "play_arrow" }).size(px(22.0)))
+                    .disabled(!(player.can_play || player.can_pause))
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.mpris.read(cx).play_pause(&play_bus, cx);
+                    })),
+            )
+            .child(
+                Button::new("mpris-next")
+                    .ghost()
+                    .with_size(gpui_component::Size::Small)
+                    .icon(Icon::new("skip_next").size(px(18.0)))
+                    .disabled(!player.can_go_next)
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.mpris.read(cx).next(&next_bus, cx);
+                    })),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .min_w_0()
+                    .pl_1()
+                    .child(
+                        div()
+                            .text_sm()
+                            .font_weight(FontWeight::SEMIBOLD)
+                            .text_color(palette.foreground)
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(title),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap_2()
+                            .text_xs()
+                            .text_color(palette.foreground_dim)
+                            .whitespace_nowrap()
+                            .when(!artists.is_empty(), |this| {
+                                this.child(div().overflow_hidden().child(artists))
+                            })
+                            .child(div().flex_none().text_color(palette.muted).child(time)),
+                    ),
+            )
+    }
+}
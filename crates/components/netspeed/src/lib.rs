@@ -0,0 +1,47 @@
+use gpui::*;
+use gpui_component::Icon;
+use nwidgets_service_system_monitor::{format_rate, SystemMonitorService, SystemStatsChanged};
+use nwidgets_service_theme::ThemeService;
+
+/// Download and upload speeds of every interface together.
+pub struct NetSpeedComponent {
+    system_monitor: Entity<SystemMonitorService>,
+}
+
+impl NetSpeedComponent {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let system_monitor = SystemMonitorService::global(cx);
+        cx.subscribe(&system_monitor, |_, _, _: &SystemStatsChanged, cx| cx.notify()).detach();
+        Self { system_monitor }
+    }
+}
+
+impl Render for NetSpeedComponent {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let net = self.system_monitor.read(cx).stats.net;
+
+        let rate = |icon: &'static str, bytes_per_sec: u64| {
+            div()
+                .flex()
+                .items_center()
+                .gap_1()
+                .child(Icon::new(icon).size(px(14.0)).text_color(palette.muted))
+                .child(
+                    div()
+                        .min_w(px(56.0))
+                        .text_xs()
+                        .text_color(palette.foreground_dim)
+                        .child(format_rate(bytes_per_sec)),
+                )
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .justify_center()
+            .px_2()
+            .child(rate("arrow_downward", net.rx))
+            .child(rate("arrow_upward", net.tx))
+    }
+}
@@ -1,11 +1,13 @@
 use gpui::prelude::FluentBuilder;
 use gpui::*;
+use gpui_component::tooltip::Tooltip;
 use gpui_component::Icon;
 use nwidgets_component_systray::SystemTrayComponent;
 use nwidgets_service_audio::{AudioService, AudioStateChanged};
 use nwidgets_service_bluetooth::{BluetoothService, BluetoothStateChanged};
-use nwidgets_service_network::{NetworkService, NetworkStateChanged};
+use nwidgets_service_network::{ConnectionType, Connectivity, NetworkService, NetworkStateChanged, VpnState};
 use nwidgets_service_systray::{SystemTrayService, SystemTrayStateChanged};
+use nwidgets_service_theme::ThemeService;
 
 pub struct QuickSettingsComponent {
     system_tray: Entity<SystemTrayComponent>,
@@ -40,14 +42,15 @@ impl QuickSettingsComponent {
 
 impl Render for QuickSettingsComponent {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let text_main = rgb(0xd8dee9);
-        let text_muted = rgb(0x4c566a);
-        let accent = rgb(0x88c0d0);
-        let red = rgb(0xbf616a);
-        let border_subtle = rgb(0x4c566a).opacity(0.6);
+        let palette = *ThemeService::palette(cx);
+        let text_main = palette.foreground_dim;
+        let text_muted = palette.muted;
+        let accent = palette.accent;
+        let red = palette.danger;
+        let border_subtle = palette.muted.opacity(0.6);
 
         let bt_state = self.bluetooth.read(cx).state.clone();
-        let _net_state = self.network.read(cx).state.clone();
+        let net_state = self.network.read(cx).state.clone();
         let audio_state = self.audio.read(cx).state.clone();
         let has_tray_items = !self.system_tray_service.read(cx).state.items.is_empty();
 
@@ -61,8 +64,51 @@ impl Render for QuickSettingsComponent {
             ("bluetooth", text_main)
         };
 
-        // 2. Network Icon: "lan"
-        let (net_icon_name, net_icon_color) = ("lan", accent);
+        // Connected devices, with their battery when known
+        let bt_tooltip: Vec<String> = bt_state
+            .devices
+            .iter()
+            .filter(|d| d.connected)
+            .map(|d| match d.battery {
+                Some(battery) => format!("{} · {battery}%", d.name),
+                None => d.name.clone(),
+            })
+            .collect();
+        let bt_tooltip: SharedString = if bt_tooltip.is_empty() {
+            "No device connected".into()
+        } else {
+            bt_tooltip.join("\n").into()
+        };
+
+        // 2. Network Icon: wired, wifi strength, VPN or offline
+        let net_icon_name = match net_state.primary {
+            Some(ConnectionType::Wired) => "lan",
+            Some(ConnectionType::Vpn) => "vpn_key",
+            Some(ConnectionType::Wifi) => match net_state.wifi_strength.unwrap_or(0) {
+                0..=24 => "network_wifi_1_bar",
+                25..=49 => "network_wifi_2_bar",
+                50..=74 => "network_wifi_3_bar",
+                _ => "signal_wifi_4_bar",
+            },
+            Some(ConnectionType::Other) => "lan",
+            None if net_state.wifi_enabled => "signal_wifi_off",
+            None => "wifi_off",
+        };
+        let net_icon_color = match (net_state.primary, net_state.connectivity) {
+            (None, _) => text_muted,
+            (Some(_), Connectivity::Portal | Connectivity::Limited | Connectivity::None) => palette.warning,
+            (Some(_), _) => text_main,
+        };
+
+        // Connected VPNs, unless the network icon already shows one
+        let vpn_tooltip: Vec<String> = net_state
+            .vpns
+            .iter()
+            .filter(|vpn| vpn.state == VpnState::Connected)
+            .map(|vpn| vpn.id.clone())
+            .collect();
+        let show_vpn = !vpn_tooltip.is_empty() && net_state.primary != Some(ConnectionType::Vpn);
+        let vpn_tooltip: SharedString = vpn_tooltip.join("\n").into();
 
         // 3. Audio Sink Icon
         let (vol_icon_name, vol_icon_color) = if audio_state.sink_muted {
@@ -91,8 +137,21 @@ impl Render for QuickSettingsComponent {
                 this.child(self.system_tray.clone())
                     .child(div().h(px(14.0)).w(px(1.0)).bg(border_subtle))
             })
-            .child(Icon::new(bt_icon_name).size(px(22.0)).text_color(bt_icon_color))
+            .child(
+                div()
+                    .id("quicksettings-bluetooth")
+                    .tooltip(move |window, cx| Tooltip::new(bt_tooltip.clone()).build(window, cx))
+                    .child(Icon::new(bt_icon_name).size(px(22.0)).text_color(bt_icon_color)),
+            )
             .child(Icon::new(net_icon_name).size(px(22.0)).text_color(net_icon_color))
+            .when(show_vpn, |this| {
+                this.child(
+                    div()
+                        .id("quicksettings-vpn")
+                        .tooltip(move |window, cx| Tooltip::new(vpn_tooltip.clone()).build(window, cx))
+                        .child(Icon::new("vpn_lock").size(px(22.0)).text_color(accent)),
+                )
+            })
             .child(Icon::new(mic_icon_name).size(px(22.0)).text_color(mic_icon_color))
             .child(Icon::new(vol_icon_name).size(px(22.0)).text_color(vol_icon_color))
     }
@@ -0,0 +1,47 @@
+use gpui::*;
+use nwidgets_service_system_monitor::{SystemMonitorService, SystemStatsChanged};
+use nwidgets_service_theme::ThemeService;
+
+mod sparkline;
+
+pub use sparkline::Sparkline;
+
+/// CPU load over the last minutes, next to the current value.
+pub struct SysGraphComponent {
+    system_monitor: Entity<SystemMonitorService>,
+}
+
+impl SysGraphComponent {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let system_monitor = SystemMonitorService::global(cx);
+        cx.subscribe(&system_monitor, |_, _, _: &SystemStatsChanged, cx| cx.notify()).detach();
+        Self { system_monitor }
+    }
+}
+
+impl Render for SysGraphComponent {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let monitor = self.system_monitor.read(cx);
+        let cpu = &monitor.history.cpu;
+
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_2()
+            .child(
+                div()
+                    .w(px(48.0))
+                    .h(px(18.0))
+                    .child(Sparkline::new(cpu.iter().map(f32::from), cpu.capacity()).max(100.0).color(palette.accent)),
+            )
+            .child(
+                div()
+                    .min_w(px(32.0))
+                    .text_xs()
+                    .text_color(palette.foreground_dim)
+                    .child(format!("{}%", monitor.stats.cpu)),
+            )
+    }
+}
@@ -0,0 +1,89 @@
+use gpui::*;
+
+/// Area chart of a series, newest value on the right. Fills its parent.
+#[derive(IntoElement)]
+pub struct Sparkline {
+    values: Vec<f32>,
+    /// Samples across the full width, so that a short series starts on the right.
+    capacity: usize,
+    max: Option<f32>,
+    color: Hsla,
+}
+
+impl Sparkline {
+    pub fn new(values: impl IntoIterator<Item = f32>, capacity: usize) -> Self {
+        Self {
+            values: values.into_iter().collect(),
+            capacity,
+            max: None,
+            color: black(),
+        }
+    }
+
+    /// Top of the scale, e.g. 100 for a percentage. Defaults to the highest value.
+    pub fn max(mut self, max: f32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn color(mut self, color: Hsla) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl RenderOnce for Sparkline {
+    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
+        let Sparkline {
+            values,
+            capacity,
+            max,
+            color,
+        } = self;
+        canvas(
+            move |_, _, _| {},
+            move |bounds, _, window: &mut Window, _| {
+                let count = values.len().min(capacity);
+                if count < 2 {
+                    return;
+                }
+                let values = &values[values.len() - count..];
+                // An idle series stays flat at the bottom instead of filling the chart
+                let max = max.unwrap_or_else(|| values.iter().copied().fold(1.0, f32::max));
+                let step = bounds.size.width / (capacity.max(2) - 1) as f32;
+                let points: Vec<Point<Pixels>> = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        let ratio = if max > 0.0 { (value / max).clamp(0.0, 1.0) } else { 0.0 };
+                        point(
+                            bounds.right() - step * (count - 1 - i) as f32,
+                            bounds.bottom() - bounds.size.height * ratio,
+                        )
+                    })
+                    .collect();
+
+                let mut area = PathBuilder::fill();
+                area.move_to(point(points[0].x, bounds.bottom()));
+                for p in &points {
+                    area.line_to(*p);
+                }
+                area.line_to(point(bounds.right(), bounds.bottom()));
+                area.close();
+                if let Ok(area) = area.build() {
+                    window.paint_path(area, color.opacity(0.25));
+                }
+
+                let mut line = PathBuilder::stroke(px(1.5));
+                line.move_to(points[0]);
+                for p in &points[1..] {
+                    line.line_to(*p);
+                }
+                if let Ok(line) = line.build() {
+                    window.paint_path(line, color);
+                }
+            },
+        )
+        .size_full()
+    }
+}
@@ -2,6 +2,7 @@ use gpui::*;
 use gpui_component::menu::{ContextMenuExt, PopupMenuItem};
 use gpui_component::Icon;
 use nwidgets_service_systray::{SystemTrayService, SystemTrayStateChanged};
+use nwidgets_service_theme::ThemeService;
 use std::path::PathBuf;
 
 pub struct SystemTrayComponent {
@@ -22,7 +23,9 @@ impl SystemTrayComponent {
 
 impl Render for SystemTrayComponent {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let text_main = rgb(0xd8dee9);
+        let palette = *ThemeService::palette(cx);
+        let text_main = palette.foreground_dim;
+        let hover_bg = palette.surface;
         let items = self.system_tray.read(cx).state.items.clone();
 
         let icon_elements: Vec<_> = items
@@ -50,7 +53,7 @@ impl Render for SystemTrayComponent {
                     .justify_center()
                     .rounded_md()
                     .cursor_pointer()
-                    .hover(|s| s.bg(rgb(0x3b4252)))
+                    .hover(move |s| s.bg(hover_bg))
                     .on_mouse_down(MouseButton::Left, move |event, _window, cx| {
                         let x = f32::from(event.position.x) as i32;
                         let y = f32::from(event.position.y) as i32;
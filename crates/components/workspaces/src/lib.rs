@@ -0,0 +1,110 @@
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+use nwidgets_service_compositor::{CompositorAction, CompositorService, CompositorStateChanged};
+use nwidgets_service_theme::ThemeService;
+
+/// Scroll distance (in pixels) needed to switch to the next workspace, so a
+/// touchpad swipe does not skip through all of them.
+const SCROLL_STEP: f32 = 40.0;
+
+pub struct WorkspacesComponent {
+    compositor: Entity<CompositorService>,
+    /// Display of the bar; its workspaces are the ones shown.
+    display: Option<DisplayId>,
+    scroll_accumulator: f32,
+}
+
+impl WorkspacesComponent {
+    pub fn new(display: Option<DisplayId>, cx: &mut Context<Self>) -> Self {
+        let compositor = CompositorService::global(cx);
+        cx.subscribe(&compositor, |_, _, _: &CompositorStateChanged, cx| cx.notify()).detach();
+
+        Self {
+            compositor,
+            display,
+            scroll_accumulator: 0.0,
+        }
+    }
+
+    /// Name of the output behind `display`, the focused one without display.
+    /// Every workspace is shown when the output is unknown.
+    fn output_name(&self, cx: &App) -> Option<String> {
+        let state = &self.compositor.read(cx).state;
+        match self.display {
+            Some(display) => state
+                .outputs
+                .iter()
+                .find(|output| output.display_id(cx) == Some(display))
+                .map(|output| output.name.clone()),
+            None => state.focused_output().map(str::to_string),
+        }
+    }
+
+    fn on_scroll(&mut self, event: &ScrollWheelEvent, cx: &mut Context<Self>) {
+        let delta = event.delta.pixel_delta(px(SCROLL_STEP));
+        self.scroll_accumulator += f32::from(delta.y);
+
+        let steps = (self.scroll_accumulator / SCROLL_STEP).trunc();
+        if steps == 0.0 {
+            return;
+        }
+        self.scroll_accumulator -= steps * SCROLL_STEP;
+
+        // Scrolling down moves to the next workspace, like in niri
+        let output = self.output_name(cx);
+        self.compositor.read(cx).cycle_workspace(output.as_deref(), -steps as i32, cx);
+    }
+}
+
+impl Render for WorkspacesComponent {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let output = self.output_name(cx);
+        let compositor = self.compositor.read(cx);
+        let workspaces: Vec<_> = compositor.workspaces_on(output.as_deref()).cloned().collect();
+        let keyboard_layout: Option<SharedString> = compositor.state.keyboard_layout.clone().map(Into::into);
+
+        div()
+            .id("workspaces-component")
+            .flex()
+            .items_center()
+            .gap_1()
+            .on_scroll_wheel(cx.listener(|this, event: &ScrollWheelEvent, _window, cx| this.on_scroll(event, cx)))
+            .children(workspaces.into_iter().map(|ws| {
+                let id = ws.id;
+                let label: SharedString = ws.name.clone().into();
+                let is_current = if output.is_some() { ws.is_active } else { ws.is_focused };
+                let (bg, fg) = if is_current {
+                    (palette.accent, palette.background)
+                } else if ws.is_urgent {
+                    (palette.danger, palette.background)
+                } else {
+                    (palette.surface, if ws.window_count > 0 { palette.foreground } else { palette.muted })
+                };
+
+                div()
+                    .id(SharedString::from(format!("workspace-{id}")))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .h(px(24.0))
+                    .min_w(px(24.0))
+                    .px_2()
+                    .rounded_md()
+                    .bg(bg)
+                    .text_xs()
+                    .font_weight(FontWeight::BOLD)
+                    .text_color(fg)
+                    .cursor_pointer()
+                    .when(!is_current, |this| this.hover(|s| s.bg(palette.surface_alt)))
+                    .when(is_current && ws.is_urgent, |this| this.border_1().border_color(palette.danger))
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.compositor.read(cx).execute(CompositorAction::FocusWorkspace(id), cx);
+                    }))
+                    .child(label)
+            }))
+            .when_some(keyboard_layout, |this, layout| {
+                this.child(div().ml_1().text_xs().text_color(palette.muted).child(layout))
+            })
+    }
+}
@@ -0,0 +1,82 @@
+//! Private D-Bus daemon for the services' tests, so their watchers can run
+//! against fake D-Bus services instead of the session bus.
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use zbus::Connection;
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// Private bus from `dbus-daemon`, stopped on drop.
+pub struct Bus {
+    daemon: Child,
+    dir: PathBuf,
+    address: String,
+}
+
+impl Bus {
+    /// Starts a bus listening in its own directory under the system temp dir.
+    ///
+    /// Panics when `dbus-daemon` cannot be run: the tests using it need it on
+    /// `PATH` and must not pass without it.
+    pub fn start() -> Self {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("nwidgets-dbus-{}-{n}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                r#"<busconfig>
+  <type>session</type>
+  <listen>unix:path={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>"#,
+                dir.join("bus.sock").display()
+            ),
+        )
+        .unwrap();
+
+        let mut daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|err| panic!("cannot run dbus-daemon, needed by the D-Bus tests: {err}"));
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        assert!(!address.trim().is_empty(), "dbus-daemon did not print its address");
+
+        Self {
+            daemon,
+            dir,
+            address: address.trim().to_string(),
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// A new connection to the bus.
+    pub async fn connect(&self) -> Connection {
+        zbus::connection::Builder::address(self.address.as_str()).unwrap().build().await.unwrap()
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
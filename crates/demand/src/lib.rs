@@ -0,0 +1,234 @@
+//! Ref-counted demand for polling services.
+//!
+//! Views hold a [`DemandGuard`] while they show some data; the service's
+//! worker follows the merge of every live request through a watch channel
+//! and sleeps when nothing is requested.
+
+use std::collections::HashMap;
+use std::future::pending;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+/// Sampling rate asked by a view, the fastest request wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Rate {
+    #[default]
+    Off,
+    /// Background checks, e.g. a low space notification.
+    Slow,
+    /// A view showing the data is open.
+    Fast,
+}
+
+/// Combines two requests into one asking for both.
+pub trait Merge {
+    fn merge(&self, other: &Self) -> Self;
+}
+
+impl Merge for Rate {
+    fn merge(&self, other: &Self) -> Self {
+        (*self).max(*other)
+    }
+}
+
+struct Requests<T> {
+    next_id: u64,
+    live: HashMap<u64, T>,
+    tx: watch::Sender<T>,
+}
+
+impl<T: Merge + Default + PartialEq> Requests<T> {
+    fn publish(&self) {
+        let merged = self.live.values().fold(T::default(), |merged, request| merged.merge(request));
+        self.tx.send_if_modified(|current| {
+            let changed = *current != merged;
+            *current = merged;
+            changed
+        });
+    }
+}
+
+/// Live requests of one service. `T::default()` is the idle demand.
+pub struct Demand<T> {
+    requests: Arc<Mutex<Requests<T>>>,
+}
+
+impl<T> Clone for Demand<T> {
+    fn clone(&self) -> Self {
+        Self {
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+impl<T: Merge + Default + PartialEq + Send + Sync + 'static> Demand<T> {
+    /// The demand and the receiver its worker follows; nothing is requested at first.
+    pub fn new() -> (Self, watch::Receiver<T>) {
+        let (tx, rx) = watch::channel(T::default());
+        let requests = Requests {
+            next_id: 0,
+            live: HashMap::new(),
+            tx,
+        };
+        (
+            Self {
+                requests: Arc::new(Mutex::new(requests)),
+            },
+            rx,
+        )
+    }
+
+    /// Adds `request` to the demand until the guard is dropped.
+    pub fn request(&self, request: T) -> DemandGuard {
+        let id = {
+            let mut requests = self.requests.lock().unwrap_or_else(|err| err.into_inner());
+            let id = requests.next_id;
+            requests.next_id += 1;
+            requests.live.insert(id, request);
+            requests.publish();
+            id
+        };
+        let requests = self.requests.clone();
+        DemandGuard {
+            release: Some(Box::new(move || {
+                let mut requests = requests.lock().unwrap_or_else(|err| err.into_inner());
+                requests.live.remove(&id);
+                requests.publish();
+            })),
+        }
+    }
+}
+
+/// Withdraws its request when dropped.
+#[must_use = "the request is withdrawn as soon as the guard is dropped"]
+pub struct DemandGuard {
+    release: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Drop for DemandGuard {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+/// Waits until the next sample is due: after `interval`, or only once the
+/// demand grows when idle (`None`). A demand asking for more than `sampled`
+/// cuts the wait short, so a view that just opened gets fresh data right away;
+/// a view closing does not.
+pub async fn next_sample<T: Merge + PartialEq>(
+    demand: &mut watch::Receiver<T>,
+    sampled: &T,
+    interval: Option<Duration>,
+) {
+    // `false` once the service is gone
+    let grown = async {
+        while demand.changed().await.is_ok() {
+            let grown = sampled.merge(&demand.borrow_and_update()) != *sampled;
+            if grown {
+                return true;
+            }
+        }
+        false
+    };
+    match interval {
+        Some(interval) => {
+            let deadline = tokio::time::Instant::now() + interval;
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {}
+                grown = grown => {
+                    // The service is gone: keep the usual pace
+                    if !grown {
+                        tokio::time::sleep_until(deadline).await;
+                    }
+                }
+            }
+        }
+        None => {
+            if !grown.await {
+                pending::<()>().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Instant;
+
+    const FAST: Duration = Duration::from_secs(2);
+
+    /// Time `next_sample` waits after a sample at the current demand, `change`
+    /// running 500 ms in.
+    async fn wait(
+        rx: &mut watch::Receiver<Rate>,
+        interval: Option<Duration>,
+        change: impl FnOnce() + Send + 'static,
+    ) -> Duration {
+        let sampled = *rx.borrow_and_update();
+        let start = Instant::now();
+        let change = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            change();
+        });
+        next_sample(rx, &sampled, interval).await;
+        change.await.unwrap();
+        start.elapsed()
+    }
+
+    #[test]
+    fn merges_requests() {
+        let (demand, rx) = Demand::<Rate>::new();
+        let slow = demand.request(Rate::Slow);
+        let fast = demand.request(Rate::Fast);
+        assert_eq!(*rx.borrow(), Rate::Fast);
+        drop(fast);
+        assert_eq!(*rx.borrow(), Rate::Slow);
+        drop(slow);
+        assert_eq!(*rx.borrow(), Rate::Off);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn new_view_cuts_the_wait_short() {
+        let (demand, mut rx) = Demand::new();
+        let _slow = demand.request(Rate::Slow);
+        let requests = demand.clone();
+        let waited = wait(&mut rx, Some(Duration::from_secs(30)), move || {
+            std::mem::forget(requests.request(Rate::Fast));
+        })
+        .await;
+        assert_eq!(waited, Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn closed_view_keeps_the_pace() {
+        let (demand, mut rx) = Demand::new();
+        let _slow = demand.request(Rate::Slow);
+        let fast = demand.request(Rate::Fast);
+        assert_eq!(wait(&mut rx, Some(FAST), move || drop(fast)).await, FAST);
+        assert_eq!(*rx.borrow(), Rate::Slow);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_until_requested() {
+        let (demand, mut rx) = Demand::new();
+        let requests = demand.clone();
+        let waited = wait(&mut rx, None, move || {
+            std::mem::forget(requests.request(Rate::Slow));
+        })
+        .await;
+        assert_eq!(waited, Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_the_pace_without_service() {
+        let (demand, mut rx) = Demand::new();
+        let fast = demand.request(Rate::Fast);
+        // The guard holds the sender as well, both go with the service
+        assert_eq!(wait(&mut rx, Some(FAST), move || drop((fast, demand))).await, FAST);
+    }
+}
@@ -1,7 +1,12 @@
 use gpui::*;
 use gpui_component::init as init_components;
 use gpui_platform::application;
+use nwidgets_service_compositor::{CompositorService, FocusedOutputChanged, OutputsChanged};
+use outputs::{root_builder, FollowingSurface, OutputBars};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
+mod outputs;
 mod views;
 
 fn main() {
@@ -16,16 +21,23 @@ fn main() {
         theme.tokens.background = gpui_component::ThemeToken::from(transparent);
         cx.set_global(theme);
 
+        // ── Configuration (first: windows below are sized from it) ──
+        let config_service = nwidgets_service_config::ConfigService::init(cx);
+        let _theme_service = nwidgets_service_theme::ThemeService::init(cx);
+
         // ── Services Initialization ──
-        let _niri_service = nwidgets_service_niri::NiriActiveWindowService::init(cx);
+        let compositor_service = CompositorService::init(cx);
         let _audio_service = nwidgets_service_audio::AudioService::init(cx);
         let _system_monitor_service = nwidgets_service_system_monitor::SystemMonitorService::init(cx);
-        let _bluetooth_service = nwidgets_service_bluetooth::BluetoothService::init(cx);
-        let _network_service = nwidgets_service_network::NetworkService::init(cx);
+        let bluetooth_service = nwidgets_service_bluetooth::BluetoothService::init(cx);
+        let network_service = nwidgets_service_network::NetworkService::init(cx);
         let _applications_service = nwidgets_service_applications::ApplicationsService::init(cx);
         let _clipboard_service = nwidgets_service_clipboard::ClipboardService::init(cx);
         let _lock_service = nwidgets_service_lock::LockMonitor::init(cx);
         let _system_tray_service = nwidgets_service_systray::SystemTrayService::init(cx);
+        let _mpris_service = nwidgets_service_mpris::MprisService::init(cx);
+        let _ssh_service = nwidgets_service_ssh::SshService::init(cx);
+        let _power_service = nwidgets_service_power::PowerService::init(cx);
 
         // ── Launcher Window ──
         cx.bind_keys([
@@ -33,123 +45,160 @@ fn main() {
             KeyBinding::new("escape", views::launcher::CloseLauncher, None),
         ]);
 
+        // Surfaces below open on the focused output, then follow it while hidden
+        let focused_display = CompositorService::focused_display(cx);
+
         // ── Chat ──
         let mut chat_fh = None;
         let mut chat_entity = None;
-        let chat_window = nwidgets_chat::open(cx, |window, cx| {
+        let chat_window = nwidgets_chat::open(cx, focused_display, |window, cx| {
             let view = cx.new(|cx| views::chat::Chat::new(window, cx));
             chat_fh = Some(view.read(cx).focus_handle.clone());
             chat_entity = Some(view.clone());
             cx.new(|cx| gpui_component::Root::new(view, window, cx).bordered(false))
         })
         .expect("Failed to open chat");
-        let chat_visible = std::rc::Rc::new(std::cell::Cell::new(false));
+        let chat = Rc::new(FollowingSurface::new(
+            chat_entity.expect("Chat view not built"),
+            chat_window,
+            focused_display,
+            |cx, display, view| nwidgets_chat::open(cx, display, root_builder(view)).ok(),
+        ));
+        let chat_visible = Rc::new(Cell::new(false));
 
         // Subscribe Escape event for Chat
-        if let Some(chat_entity) = chat_entity {
-            let chat_win_close = chat_window.clone();
+        {
+            let chat_close = chat.clone();
             let chat_vis_close = chat_visible.clone();
-            let _ = chat_window.update(cx, |_, _window, cx| {
-                cx.subscribe(
-                    &chat_entity,
-                    move |_this, _emitter, _event: &views::chat::CloseChat, cx| {
-                        chat_vis_close.set(false);
-                        nwidgets_chat::set_visible(&chat_win_close, false, cx);
-                    },
-                )
-                .detach();
-            });
+            cx.subscribe(&chat.view, move |_emitter, _event: &views::chat::CloseChat, cx| {
+                chat_vis_close.set(false);
+                nwidgets_chat::set_visible(&chat_close.window(), false, cx);
+            })
+            .detach();
         }
 
         // ── OSD ──
-        let mut osd_entity = None;
-        let osd_window = nwidgets_osd::open(cx, |window, cx| {
-            let view = cx.new(views::osd::OsdView::new);
-            osd_entity = Some(view.clone());
-            cx.new(|cx| gpui_component::Root::new(view, window, cx).bordered(false))
+        let osd_view = cx.new(views::osd::OsdView::new);
+        let osd = nwidgets_osd::open(cx, focused_display, root_builder(osd_view.clone())).map(|window| {
+            Rc::new(FollowingSurface::new(osd_view.clone(), window, focused_display, |cx, display, view| {
+                nwidgets_osd::open(cx, display, root_builder(view))
+            }))
         });
-        if let (Some(osd_window), Some(osd_entity)) = (osd_window, osd_entity) {
-            let handle: AnyWindowHandle = osd_window.into();
-            let _ = osd_window.update(cx, |_, _window, cx| {
-                osd_entity.update(cx, |osd, _| {
-                    osd.set_window_handle(handle);
-                });
-            });
+        if let Some(ref osd) = osd {
+            let handle: AnyWindowHandle = osd.window().into();
+            osd_view.update(cx, |osd, _| osd.set_window_handle(handle));
         }
 
         // ── Notifications ──
-        let mut ntf_entity = None;
-        let ntf_window = nwidgets_notification::open(cx, |window, cx| {
-            let view = cx.new(views::notification::NtfView::new);
-            ntf_entity = Some(view.clone());
-            cx.new(|cx| gpui_component::Root::new(view, window, cx).bordered(false))
+        let ntf_view = cx.new(views::notification::NtfView::new);
+        let ntf = nwidgets_notification::open(cx, focused_display, root_builder(ntf_view.clone())).map(|window| {
+            Rc::new(FollowingSurface::new(ntf_view.clone(), window, focused_display, |cx, display, view| {
+                nwidgets_notification::open(cx, display, root_builder(view))
+            }))
         });
-        if let (Some(ntf_window), Some(ntf_entity)) = (ntf_window, ntf_entity) {
-            let handle: AnyWindowHandle = ntf_window.into();
-            let _ = ntf_window.update(cx, |_, _window, cx| {
-                ntf_entity.update(cx, |ntf, _| {
-                    ntf.set_window_handle(handle);
-                });
-            });
+        if let Some(ref ntf) = ntf {
+            let handle: AnyWindowHandle = ntf.window().into();
+            ntf_view.update(cx, |ntf, cx| ntf.set_window_handle(handle, cx));
         }
 
-        // ── Panel ──
-        let cc_window = nwidgets_panel::open(cx, |window, cx| {
-            let view = cx.new(views::panel::Panel::new);
-            cx.new(|cx| gpui_component::Root::new(view, window, cx).bordered(false))
+        // ── Bars & panels, one per output ──
+        let output_bars = Rc::new(RefCell::new(OutputBars::default()));
+        output_bars.borrow_mut().sync(cx);
+
+        // Hotplug and bar size changes
+        let bars = output_bars.clone();
+        cx.subscribe(&compositor_service, move |_, _: &OutputsChanged, cx| bars.borrow_mut().sync(cx))
+            .detach();
+        let bars = output_bars.clone();
+        cx.subscribe(&config_service, move |_, _: &nwidgets_service_config::ConfigChanged, cx| {
+            bars.borrow_mut().sync(cx)
         })
-        .expect("Failed to open panel");
-        let cc_visible = std::rc::Rc::new(std::cell::Cell::new(false));
-
-        // ── Bar (panel) ──
-        let cc_window_for_bar = cc_window.clone();
-        nwidgets_bar::open(cx, move |window, cx| {
-            let cc = cc_window_for_bar.into();
-            let view = cx.new(move |cx| views::bar::Bar::new(cc, cx));
-            cx.new(|cx| gpui_component::Root::new(view, window, cx).bordered(false))
+        .detach();
+
+        // Pairing and network secret requests are answered from the panel
+        let bars = output_bars.clone();
+        cx.subscribe(&bluetooth_service, move |bluetooth, _: &nwidgets_service_bluetooth::BluetoothPromptChanged, cx| {
+            if bluetooth.read(cx).prompt.is_some() {
+                bars.borrow().show_panel(cx);
+            }
+        })
+        .detach();
+        let bars = output_bars.clone();
+        cx.subscribe(&network_service, move |network, _: &nwidgets_service_network::NetworkPromptChanged, cx| {
+            if network.read(cx).secret_prompt.is_some() {
+                bars.borrow().show_panel(cx);
+            }
         })
-        .expect("Failed to open bar");
+        .detach();
 
         // ── Launcher ──
         let mut launcher_fh = None;
         let mut launcher_entity = None;
-        let launcher_window = nwidgets_launcher::open(cx, |window, cx| {
+        let launcher_window = nwidgets_launcher::open(cx, focused_display, |window, cx| {
             let launcher_view = cx.new(|cx| views::launcher::Launcher::new(window, cx));
             launcher_fh = Some(launcher_view.read(cx).focus_handle.clone());
             launcher_entity = Some(launcher_view.clone());
             cx.new(|cx| gpui_component::Root::new(launcher_view, window, cx).bordered(false))
         })
         .expect("Failed to open launcher");
-        let launcher_visible = std::rc::Rc::new(std::cell::Cell::new(false));
+        let launcher = Rc::new(FollowingSurface::new(
+            launcher_entity.expect("Launcher view not built"),
+            launcher_window,
+            focused_display,
+            |cx, display, view| nwidgets_launcher::open(cx, display, root_builder(view)).ok(),
+        ));
+        let launcher_visible = Rc::new(Cell::new(false));
 
         // Subscribe Escape event for Launcher
-        if let Some(ref launcher_entity) = launcher_entity {
-            let launcher_win_close = launcher_window.clone();
+        {
+            let launcher_close = launcher.clone();
             let launcher_vis_close = launcher_visible.clone();
-            let _ = launcher_window.update(cx, |_, _window, cx| {
-                cx.subscribe(
-                    &launcher_entity,
-                    move |_this, _emitter, _event: &views::launcher::CloseLauncher, cx| {
-                        launcher_vis_close.set(false);
-                        nwidgets_launcher::set_visible(&launcher_win_close, false, None, cx);
-                    },
-                )
-                .detach();
-            });
+            cx.subscribe(&launcher.view, move |_emitter, _event: &views::launcher::CloseLauncher, cx| {
+                launcher_vis_close.set(false);
+                nwidgets_launcher::set_visible(&launcher_close.window(), false, 0.0, 0.0, None, cx);
+            })
+            .detach();
+        }
+
+        // ── Focused output ──
+        {
+            let chat = chat.clone();
+            let chat_vis = chat_visible.clone();
+            let launcher = launcher.clone();
+            let launcher_vis = launcher_visible.clone();
+            cx.subscribe(&compositor_service, move |_, _: &FocusedOutputChanged, cx| {
+                let display = CompositorService::focused_display(cx);
+                if !chat_vis.get() {
+                    chat.move_to(display, cx);
+                }
+                if !launcher_vis.get() {
+                    launcher.move_to(display, cx);
+                }
+                if let Some(ref osd) = osd {
+                    if osd.move_to(display, cx) {
+                        let handle: AnyWindowHandle = osd.window().into();
+                        osd.view.update(cx, |osd, _| osd.set_window_handle(handle));
+                    }
+                }
+                if let Some(ref ntf) = ntf {
+                    if ntf.move_to(display, cx) {
+                        let handle: AnyWindowHandle = ntf.window().into();
+                        ntf.view.update(cx, |ntf, cx| ntf.set_window_handle(handle, cx));
+                    }
+                }
+            })
+            .detach();
         }
 
         // ── Shortcut (IPC / D-Bus Service) ──
-        let chat_win = chat_window.clone();
         let chat_vis = chat_visible.clone();
-        let cc_win = cc_window.clone();
-        let cc_vis = cc_visible.clone();
-        let launcher_win = launcher_window.clone();
         let launcher_vis = launcher_visible.clone();
 
         nwidgets_shortcut::ShortcutService::init(cx, move |cmd, cx| match cmd {
             nwidgets_shortcut::ShortcutCommand::ToggleChat => {
                 let v = !chat_vis.get();
                 chat_vis.set(v);
+                let chat_win = chat.window();
                 nwidgets_chat::set_visible(&chat_win, v, cx);
                 if v {
                     if let Some(ref fh) = chat_fh {
@@ -161,23 +210,24 @@ fn main() {
                 }
             }
             nwidgets_shortcut::ShortcutCommand::TogglePanel => {
-                let v = !cc_vis.get();
-                cc_vis.set(v);
-                nwidgets_panel::set_visible(&cc_win, v, cx);
+                output_bars.borrow().toggle_panel(cx);
             }
             nwidgets_shortcut::ShortcutCommand::ToggleLauncher => {
                 let v = !launcher_vis.get();
                 launcher_vis.set(v);
+                let launcher_win = launcher.window();
                 if v {
-                    if let Some(ref entity) = launcher_entity {
-                        let _ = launcher_window.update(cx, |_, window, cx| {
-                            entity.update(cx, |launcher, cx| {
-                                launcher.reset(window, cx);
-                            });
+                    let _ = launcher_win.update(cx, |_, window, cx| {
+                        launcher.view.update(cx, |launcher, cx| {
+                            launcher.reset(window, cx);
                         });
-                    }
+                    });
                 }
-                nwidgets_launcher::set_visible(&launcher_win, v, launcher_fh.as_ref(), cx);
+                let (width, height) = {
+                    let launcher_config = &nwidgets_service_config::ConfigService::config(cx).launcher;
+                    (launcher_config.width, launcher_config.height)
+                };
+                nwidgets_launcher::set_visible(&launcher_win, v, width, height, launcher_fh.as_ref(), cx);
             }
             nwidgets_shortcut::ShortcutCommand::PinChat => {}
         });
@@ -0,0 +1,201 @@
+use gpui::*;
+use gpui_component::Root;
+use nwidgets_service_config::ConfigService;
+use nwidgets_service_compositor::CompositorService;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::views;
+
+/// Wraps an existing view into a new `Root`, for surfaces reopened on another output.
+pub fn root_builder<V: Render>(view: Entity<V>) -> impl FnOnce(&mut Window, &mut App) -> Entity<Root> {
+    move |window, cx| cx.new(|cx| Root::new(view, window, cx).bordered(false))
+}
+
+/// Layer surface following the focused output.
+///
+/// A layer surface is bound to its wl_output for its whole life, so moving it
+/// means opening a new window around the same view entity (its state is kept)
+/// and closing the previous one.
+pub struct FollowingSurface<V: 'static> {
+    pub view: Entity<V>,
+    window: Cell<WindowHandle<Root>>,
+    display: Cell<Option<DisplayId>>,
+    open: fn(&mut App, Option<DisplayId>, Entity<V>) -> Option<WindowHandle<Root>>,
+}
+
+impl<V: Render> FollowingSurface<V> {
+    pub fn new(
+        view: Entity<V>,
+        window: WindowHandle<Root>,
+        display: Option<DisplayId>,
+        open: fn(&mut App, Option<DisplayId>, Entity<V>) -> Option<WindowHandle<Root>>,
+    ) -> Self {
+        Self {
+            view,
+            window: Cell::new(window),
+            display: Cell::new(display),
+            open,
+        }
+    }
+
+    pub fn window(&self) -> WindowHandle<Root> {
+        self.window.get()
+    }
+
+    /// Reopens the surface on `display`. Returns `false` when it already was there.
+    pub fn move_to(&self, display: Option<DisplayId>, cx: &mut App) -> bool {
+        if display.is_none() || self.display.get() == display {
+            return false;
+        }
+        let Some(window) = (self.open)(cx, display, self.view.clone()) else {
+            return false;
+        };
+
+        let previous = self.window.replace(window);
+        let _ = previous.update(cx, |_, window, _| window.remove_window());
+        self.display.set(display);
+        true
+    }
+}
+
+/// Bar and panel opened on one output.
+struct OutputSurfaces {
+    bar: WindowHandle<Root>,
+    bar_size: (f32, f32),
+    panel: WindowHandle<Root>,
+    panel_view: Entity<views::panel::Panel>,
+    panel_visible: Rc<Cell<bool>>,
+}
+
+/// One bar (with its panel) per output, kept in sync with hotplug and the configuration.
+#[derive(Default)]
+pub struct OutputBars {
+    surfaces: HashMap<DisplayId, OutputSurfaces>,
+}
+
+fn open_bar(
+    cx: &mut App,
+    display: Option<DisplayId>,
+    (width, height): (f32, f32),
+    panel: AnyWindowHandle,
+    panel_view: Entity<views::panel::Panel>,
+    panel_visible: Rc<Cell<bool>>,
+) -> Option<WindowHandle<Root>> {
+    nwidgets_bar::open(cx, display, width, height, move |window, cx| {
+        let view = cx.new(move |cx| views::bar::Bar::new(display, panel, panel_view, panel_visible, cx));
+        cx.new(|cx| Root::new(view, window, cx).bordered(false))
+    })
+    .inspect_err(|err| log::error!("Failed to open bar: {err}"))
+    .ok()
+}
+
+fn open_panel(
+    cx: &mut App,
+    display: Option<DisplayId>,
+) -> Option<(WindowHandle<Root>, Entity<views::panel::Panel>)> {
+    let mut panel_view = None;
+    let window = nwidgets_panel::open(cx, display, |window, cx| {
+        let view = cx.new(views::panel::Panel::new);
+        panel_view = Some(view.clone());
+        cx.new(|cx| Root::new(view, window, cx).bordered(false))
+    })
+    .inspect_err(|err| log::error!("Failed to open panel: {err}"))
+    .ok()?;
+    Some((window, panel_view?))
+}
+
+fn close(window: WindowHandle<Root>, cx: &mut App) {
+    let _ = window.update(cx, |_, window, _| window.remove_window());
+}
+
+impl OutputBars {
+    /// Opens the surfaces of new outputs, closes those of removed ones and
+    /// reopens a bar whose size changed (its height is the exclusive zone).
+    pub fn sync(&mut self, cx: &mut App) {
+        let bar_config = &ConfigService::config(cx).bar;
+        let (config_width, height) = (bar_config.width, bar_config.height);
+        let displays: Vec<(DisplayId, f32)> = cx
+            .displays()
+            .iter()
+            .map(|display| (display.id(), f32::from(display.bounds().size.width)))
+            .collect();
+
+        self.surfaces.retain(|display, surfaces| {
+            let keep = displays.iter().any(|(id, _)| id == display);
+            if !keep {
+                close(surfaces.bar, cx);
+                close(surfaces.panel, cx);
+            }
+            keep
+        });
+
+        for (display, display_width) in displays {
+            let bar_size = (config_width.unwrap_or(display_width), height);
+
+            if let Some(surfaces) = self.surfaces.get_mut(&display) {
+                if surfaces.bar_size != bar_size {
+                    let panel = surfaces.panel.into();
+                    let (panel_view, panel_visible) = (surfaces.panel_view.clone(), surfaces.panel_visible.clone());
+                    if let Some(bar) = open_bar(cx, Some(display), bar_size, panel, panel_view, panel_visible) {
+                        close(std::mem::replace(&mut surfaces.bar, bar), cx);
+                        surfaces.bar_size = bar_size;
+                    }
+                }
+                continue;
+            }
+
+            let Some((panel, panel_view)) = open_panel(cx, Some(display)) else {
+                continue;
+            };
+            let panel_visible = Rc::new(Cell::new(false));
+            let Some(bar) =
+                open_bar(cx, Some(display), bar_size, panel.into(), panel_view.clone(), panel_visible.clone())
+            else {
+                close(panel, cx);
+                continue;
+            };
+
+            self.surfaces.insert(
+                display,
+                OutputSurfaces {
+                    bar,
+                    bar_size,
+                    panel,
+                    panel_view,
+                    panel_visible,
+                },
+            );
+        }
+    }
+
+    /// Surfaces of the focused output, or of any output when it is unknown.
+    fn focused(&self, cx: &App) -> Option<&OutputSurfaces> {
+        CompositorService::focused_display(cx)
+            .and_then(|display| self.surfaces.get(&display))
+            .or_else(|| self.surfaces.values().next())
+    }
+
+    /// Toggles the panel of the focused output.
+    pub fn toggle_panel(&self, cx: &mut App) {
+        if let Some(surfaces) = self.focused(cx) {
+            let visible = !surfaces.panel_visible.get();
+            surfaces.panel_visible.set(visible);
+            nwidgets_panel::set_visible(&surfaces.panel, visible, cx);
+            surfaces.panel_view.update(cx, |panel, cx| panel.set_visible(visible, cx));
+        }
+    }
+
+    /// Shows the panel of the focused output unless a panel is already visible.
+    pub fn show_panel(&self, cx: &mut App) {
+        if self.surfaces.values().any(|surfaces| surfaces.panel_visible.get()) {
+            return;
+        }
+        if let Some(surfaces) = self.focused(cx) {
+            surfaces.panel_visible.set(true);
+            nwidgets_panel::set_visible(&surfaces.panel, true, cx);
+            surfaces.panel_view.update(cx, |panel, cx| panel.set_visible(true, cx));
+        }
+    }
+}
@@ -1,47 +1,133 @@
 use gpui::*;
 use gpui_component::corner::{Corner, CornerPosition};
 use nwidgets_component_active_window::ActiveWindowComponent;
+use nwidgets_component_battery::BatteryComponent;
 use nwidgets_component_datetime::DateTimeComponent;
+use nwidgets_component_mpris::MprisComponent;
+use nwidgets_component_netspeed::NetSpeedComponent;
 use nwidgets_component_pomodoro::PomodoroComponent;
 use nwidgets_component_quicksettings::QuickSettingsComponent;
+use nwidgets_component_sysgraph::SysGraphComponent;
+use nwidgets_component_workspaces::WorkspacesComponent;
+use nwidgets_service_config::{BarComponent, ConfigChanged, ConfigService};
+use nwidgets_service_system_monitor::{DemandGuard, MonitorDemand, Rate, SystemMonitorService};
+use nwidgets_service_theme::ThemeService;
 
 const CORNER_RADIUS: f32 = 12.0;
-const BAR_HEIGHT: f32 = 50.0;
 
 pub struct Bar {
+    workspaces: Entity<WorkspacesComponent>,
     active_window: Entity<ActiveWindowComponent>,
     pomodoro: Entity<PomodoroComponent>,
+    media: Entity<MprisComponent>,
     quicksettings: Entity<QuickSettingsComponent>,
     datetime: Entity<DateTimeComponent>,
+    netspeed: Entity<NetSpeedComponent>,
+    sysgraph: Entity<SysGraphComponent>,
+    battery: Entity<BatteryComponent>,
     cc_window: AnyWindowHandle,
+    cc_view: Entity<super::panel::Panel>,
     cc_visible: std::rc::Rc<std::cell::Cell<bool>>,
+    /// System monitor sampling while `netspeed` or `sysgraph` is shown.
+    monitor_demand: Option<DemandGuard>,
 }
 
 impl Bar {
-    pub fn new(cc_window: AnyWindowHandle, cx: &mut Context<Self>) -> Self {
+    /// `cc_window` is the panel of the bar's output, `cc_view` its view and
+    /// `cc_visible` its visibility shared with the shortcuts.
+    pub fn new(
+        display: Option<DisplayId>,
+        cc_window: AnyWindowHandle,
+        cc_view: Entity<super::panel::Panel>,
+        cc_visible: std::rc::Rc<std::cell::Cell<bool>>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let workspaces = cx.new(|cx| WorkspacesComponent::new(display, cx));
         let active_window = cx.new(ActiveWindowComponent::new);
         let pomodoro = cx.new(PomodoroComponent::new);
+        let media = cx.new(MprisComponent::new);
         let quicksettings = cx.new(QuickSettingsComponent::new);
         let datetime = cx.new(DateTimeComponent::new);
-        let cc_visible = std::rc::Rc::new(std::cell::Cell::new(false));
+        let netspeed = cx.new(NetSpeedComponent::new);
+        let sysgraph = cx.new(SysGraphComponent::new);
+        let battery = cx.new(BatteryComponent::new);
 
-        Self {
+        let config = ConfigService::global(cx);
+        cx.subscribe(&config, |this, _, _: &ConfigChanged, cx| {
+            this.update_monitor_demand(cx);
+            cx.notify();
+        })
+        .detach();
+
+        let mut bar = Self {
+            workspaces,
             active_window,
             pomodoro,
+            media,
             quicksettings,
             datetime,
+            netspeed,
+            sysgraph,
+            battery,
             cc_window,
+            cc_view,
             cc_visible,
+            monitor_demand: None,
+        };
+        bar.update_monitor_demand(cx);
+        bar
+    }
+
+    fn update_monitor_demand(&mut self, cx: &mut Context<Self>) {
+        let bar_config = &ConfigService::config(cx).bar;
+        let needed = bar_config
+            .left
+            .iter()
+            .chain(&bar_config.center)
+            .chain(&bar_config.right)
+            .any(|component| matches!(component, BarComponent::NetSpeed | BarComponent::SysGraph));
+        if needed != self.monitor_demand.is_some() {
+            self.monitor_demand = needed.then(|| {
+                SystemMonitorService::global(cx).read(cx).request(MonitorDemand {
+                    rate: Rate::Fast,
+                    ..Default::default()
+                })
+            });
         }
     }
+
+    fn render_components(&self, components: &[BarComponent]) -> Vec<AnyElement> {
+        components
+            .iter()
+            .map(|component| match component {
+                BarComponent::Workspaces => self.workspaces.clone().into_any_element(),
+                BarComponent::ActiveWindow => self.active_window.clone().into_any_element(),
+                BarComponent::Pomodoro => self.pomodoro.clone().into_any_element(),
+                BarComponent::Media => self.media.clone().into_any_element(),
+                BarComponent::QuickSettings => self.quicksettings.clone().into_any_element(),
+                BarComponent::DateTime => self.datetime.clone().into_any_element(),
+                BarComponent::NetSpeed => self.netspeed.clone().into_any_element(),
+                BarComponent::SysGraph => self.sysgraph.clone().into_any_element(),
+                BarComponent::Battery => self.battery.clone().into_any_element(),
+            })
+            .collect()
+    }
 }
 
 impl Render for Bar {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        let bg = rgb(0x2e3440);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let bg = palette.background;
+        let frost_border = palette.border;
         let cc_win = self.cc_window;
         let cc_vis = self.cc_visible.clone();
+        let cc_view = self.cc_view.clone();
+
+        let bar_config = &ConfigService::config(cx).bar;
+        let bar_height = bar_config.height;
+        let left = self.render_components(&bar_config.left);
+        let center = self.render_components(&bar_config.center);
+        let right = self.render_components(&bar_config.right);
 
         div()
             .size_full()
@@ -51,33 +137,34 @@ impl Render for Bar {
                 // ── Bar content ──
                 div()
                     .w_full()
-                    .h(px(BAR_HEIGHT))
+                    .h(px(bar_height))
                     .bg(bg)
                     .relative()
                     .flex()
                     .items_center()
                     .justify_between()
                     .px_4()
-                    // ── Left: Active window ──
+                    // ── Left (workspaces & active window by default) ──
                     .child(
                         div()
                             .flex()
+                            .gap_3()
                             .items_center()
-                            .max_w(px(320.0))
                             .overflow_hidden()
-                            .child(self.active_window.clone()),
+                            .children(left),
                     )
-                    // ── Center: Pomodoro ──
+                    // ── Center (pomodoro & media by default) ──
                     .child(
                         div()
                             .absolute()
                             .inset_0()
                             .flex()
+                            .gap_3()
                             .items_center()
                             .justify_center()
-                            .child(self.pomodoro.clone()),
+                            .children(center),
                     )
-                    // ── Right: QuickSettings & DateTime ──
+                    // ── Right (quicksettings & datetime by default), opens the panel ──
                     .child(
                         div()
                             .id("quicksettings-trigger")
@@ -89,9 +176,9 @@ impl Render for Bar {
                                 let v = !cc_vis.get();
                                 cc_vis.set(v);
                                 nwidgets_panel::toggle(&cc_win, v, cx);
+                                cc_view.update(cx, |panel, cx| panel.set_visible(v, cx));
                             })
-                            .child(self.quicksettings.clone())
-                            .child(self.datetime.clone()),
+                            .children(right),
                     ),
             )
             // ── Corners & bottom border row ──
@@ -4,6 +4,7 @@ use gpui_component::button::{Button, ButtonVariants};
 use gpui_component::corner::{Corner, CornerPosition};
 use gpui_component::input::{Input, InputState};
 use gpui_component::Icon;
+use nwidgets_service_theme::ThemeService;
 
 const CHAT_WIDTH: f32 = 600.0;
 const CORNER_RADIUS: f32 = 12.0;
@@ -91,11 +92,12 @@ impl Chat {
     }
 
     fn render_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
-        let text_main = rgb(0xe5e9f0);
-        let text_muted = rgb(0x4c566a);
-        let accent = rgb(0x88c0d0);
-        let green = rgb(0xa3be8c);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let palette = *ThemeService::palette(cx);
+        let text_main = palette.foreground;
+        let text_muted = palette.muted;
+        let accent = palette.accent;
+        let green = palette.success;
+        let frost_border = palette.border;
 
         div()
             .w_full()
@@ -114,7 +116,7 @@ impl Chat {
                     .child(
                         div()
                             .p_2()
-                            .bg(rgb(0x3b4252))
+                            .bg(palette.surface)
                             .rounded_lg()
                             .child(Icon::new("smart_toy").size(px(20.0)).text_color(accent)),
                     )
@@ -154,11 +156,12 @@ impl Chat {
             )
     }
 
-    fn render_empty_state(&self) -> impl IntoElement {
-        let text_main = rgb(0xe5e9f0);
-        let text_muted = rgb(0x4c566a);
-        let accent = rgb(0x88c0d0);
-        let card_bg = rgb(0x3b4252);
+    fn render_empty_state(&self, cx: &App) -> impl IntoElement {
+        let palette = ThemeService::palette(cx);
+        let text_main = palette.foreground;
+        let text_muted = palette.muted;
+        let accent = palette.accent;
+        let card_bg = palette.surface;
 
         div()
             .flex_1()
@@ -226,9 +229,10 @@ impl Chat {
     }
 
     fn render_message(&self, msg: &ChatMessage, ix: usize, cx: &mut Context<Self>) -> Div {
-        let text_main = rgb(0xe5e9f0);
-        let text_muted = rgb(0xd8dee9);
-        let purple = rgb(0xb48ead);
+        let palette = *ThemeService::palette(cx);
+        let text_main = palette.foreground;
+        let text_muted = palette.foreground_dim;
+        let purple = palette.highlight;
         let is_user = msg.role == "user";
         let thinking_expanded = self.thinking_expanded;
 
@@ -242,9 +246,9 @@ impl Chat {
                     div()
                         .w_full()
                         .p_3()
-                        .bg(rgb(0x3b4252))
+                        .bg(palette.surface)
                         .border_1()
-                        .border_color(rgb(0x4c566a))
+                        .border_color(palette.muted)
                         .rounded_xl()
                         .text_sm()
                         .text_color(text_main)
@@ -319,10 +323,12 @@ impl Chat {
     }
 
     fn render_input_bar(&self, cx: &mut Context<Self>) -> impl IntoElement {
-        let bg = rgb(0x2e3440);
-        let text_muted = rgb(0xd8dee9);
-        let accent = rgb(0x88c0d0);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let palette = *ThemeService::palette(cx);
+        let bg = palette.background;
+        let text_muted = palette.foreground_dim;
+        let accent = palette.accent;
+        let hover_bg = palette.muted;
+        let frost_border = palette.border;
 
         div()
             .w_full()
@@ -360,7 +366,7 @@ impl Chat {
                                     .cursor_pointer()
                                     .p_1()
                                     .rounded_md()
-                                    .hover(|s| s.bg(rgb(0x4c566a)))
+                                    .hover(move |s| s.bg(hover_bg))
                                     .child(Icon::new("add").size(px(16.0)).text_color(text_muted)),
                             )
                             .child(
@@ -371,7 +377,7 @@ impl Chat {
                                     .px_2()
                                     .py_1()
                                     .rounded_md()
-                                    .bg(rgb(0x3b4252))
+                                    .bg(palette.surface)
                                     .child(Icon::new("psychology").size(px(14.0)).text_color(accent))
                                     .child(
                                         div()
@@ -395,8 +401,9 @@ impl Chat {
 
 impl Render for Chat {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let bg = rgb(0x2e3440);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let palette = *ThemeService::palette(cx);
+        let bg = palette.background;
+        let frost_border = palette.border;
 
         // Force focus sur la barre de recherche / saisie
         let input_fh = self.input_state.read(cx).focus_handle(cx);
@@ -432,7 +439,7 @@ impl Render for Chat {
                     .child(self.render_header(cx))
                     .child(
                         div().flex_1().flex().flex_col().when(is_empty, |this| {
-                            this.child(self.render_empty_state())
+                            this.child(self.render_empty_state(cx))
                         }).when(!is_empty, |this| {
                             this.child(
                                 div()
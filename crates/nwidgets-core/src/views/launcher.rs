@@ -6,6 +6,7 @@ use gpui_component::{Icon, IndexPath, Selectable, Sizable};
 use nwidgets_service_applications::{AppInfo, ApplicationsService, ApplicationsStateChanged};
 use nwidgets_service_clipboard::{ClipboardChanged, ClipboardEntry, ClipboardService};
 use nwidgets_service_process::{ProcessInfo, kill_process, search_processes};
+use nwidgets_service_theme::ThemeService;
 use std::process::Command;
 
 actions!(launcher, [CloseLauncher]);
@@ -45,12 +46,13 @@ impl Selectable for LauncherListItem {
 }
 
 impl RenderOnce for LauncherListItem {
-    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
-        let frost0: Hsla  = rgb(0xd8dee9).into();
-        let muted: Hsla   = rgb(0x4c566a).into();
-        let accent: Hsla  = rgb(0x88c0d0).into();
-        let red: Hsla     = rgb(0xbf616a).into();
-        let selected_bg: Hsla = rgb(0x434c5e).into();
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let frost0: Hsla  = palette.foreground_dim;
+        let muted: Hsla   = palette.muted;
+        let accent: Hsla  = palette.accent;
+        let red: Hsla     = palette.danger;
+        let selected_bg: Hsla = palette.surface_alt;
         let hover_bg: Hsla    = Hsla { h: frost0.h, s: frost0.s, l: frost0.l, a: 0.08 };
         let border: Hsla = if self.selected { accent } else { rgb(0x000000).opacity(0.0).into() };
 
@@ -444,12 +446,13 @@ impl RenderOnce for RoundedBottomRightCorner {
 
 impl Render for Launcher {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let bg = rgb(0x2e3440);
+        let palette = *ThemeService::palette(cx);
+        let bg = palette.background;
 
         // Force focus vers la barre de recherche
         let search_fh = self.list_state.focus_handle(cx);
         window.focus(&search_fh, cx);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let frost_border = palette.border;
 
         div()
             .id("launcher-main")
@@ -505,7 +508,7 @@ impl Render for Launcher {
                             .left_0()
                             .child(RoundedBottomLeftCorner {
                                 radius: px(CORNER_RADIUS),
-                                border_color: frost_border.into(),
+                                border_color: frost_border,
                             }),
                     )
                     // Bottom horizontal border line (x=24 to x=700)
@@ -526,7 +529,7 @@ impl Render for Launcher {
                             .right_0()
                             .child(RoundedBottomRightCorner {
                                 radius: px(CORNER_RADIUS),
-                                border_color: frost_border.into(),
+                                border_color: frost_border,
                             }),
                     )
                     // Right vertical border line (x=712, y=12 down to y=H-12)
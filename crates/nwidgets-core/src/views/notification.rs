@@ -3,10 +3,10 @@ use gpui::*;
 use gpui::prelude::FluentBuilder;
 use gpui_component::corner::{Corner, CornerPosition};
 use gpui_component::Icon;
+use nwidgets_service_config::ConfigService;
 use nwidgets_service_notification::{Notification, NotificationAdded, NotificationService};
+use nwidgets_service_theme::ThemeService;
 
-const TOAST_TIMEOUT_SECS: u64 = 5;
-const MAX_ACTIVE_TOASTS: usize = 5;
 const CORNER_RADIUS: f32 = 12.0;
 
 #[derive(IntoElement)]
@@ -67,8 +67,10 @@ impl NtfView {
         view
     }
 
-    pub fn set_window_handle(&mut self, handle: AnyWindowHandle) {
+    /// Also re-applies the toast state, the window may have been reopened on another output.
+    pub fn set_window_handle(&mut self, handle: AnyWindowHandle, cx: &mut Context<Self>) {
         self.window_handle = Some(handle);
+        self.update_window_state(cx);
     }
 
     pub fn add_toast(&mut self, notification: Notification, cx: &mut Context<Self>) {
@@ -84,7 +86,8 @@ impl NtfView {
                 created_at: now,
             },
         );
-        self.active_toasts.truncate(MAX_ACTIVE_TOASTS);
+        self.active_toasts
+            .truncate(ConfigService::config(cx).notifications.max_toasts);
 
         self.update_window_state(cx);
         cx.notify();
@@ -111,9 +114,10 @@ impl NtfView {
                         .as_secs();
 
                     let _ = this.update(&mut cx, |this, cx| {
+                        let timeout_secs = ConfigService::config(cx).notifications.timeout_secs;
                         let old_len = this.active_toasts.len();
                         this.active_toasts
-                            .retain(|t| now - t.created_at < TOAST_TIMEOUT_SECS);
+                            .retain(|t| now - t.created_at < timeout_secs);
 
                         if this.active_toasts.len() != old_len {
                             this.update_window_state(cx);
@@ -153,14 +157,15 @@ impl NtfView {
 
 impl Render for NtfView {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let panel_bg = rgb(0x2e3440);
-        let card_bg = rgb(0x3b4252);
-        let frost0 = rgb(0xd8dee9);
-        let text_muted = rgb(0xd8dee9);
-        let text_bright = rgb(0xe5e9f0);
-        let accent = rgb(0x88c0d0);
-        let red = rgb(0xbf616a);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let palette = *ThemeService::palette(cx);
+        let panel_bg = palette.background;
+        let card_bg = palette.surface;
+        let frost0 = palette.foreground_dim;
+        let text_muted = palette.foreground_dim;
+        let text_bright = palette.foreground;
+        let accent = palette.accent;
+        let red = palette.danger;
+        let frost_border = palette.border;
 
         if self.active_toasts.is_empty() {
             return div().into_any_element();
@@ -180,7 +185,7 @@ impl Render for NtfView {
                 let notif_id = notif.id;
                 let border_color = match notif.urgency {
                     2 => red,
-                    _ => rgb(0x4c566a),
+                    _ => palette.muted,
                 };
 
                 let body_chars = notif.body.chars().count();
@@ -277,7 +282,7 @@ impl Render for NtfView {
                     .left_0()
                     .child(RoundedBottomLeftCorner {
                         radius: px(CORNER_RADIUS),
-                        border_color: frost_border.into(),
+                        border_color: frost_border,
                     }),
             )
             // Bottom border line (x=12 to x=368)
@@ -4,10 +4,14 @@ use gpui_component::corner::{Corner, CornerPosition};
 use gpui_component::Icon;
 use nwidgets_service_audio::{AudioService, AudioStateChanged};
 use nwidgets_service_clipboard::{ClipboardChanged, ClipboardService};
+use nwidgets_service_config::ConfigService;
 use nwidgets_service_lock::{LockMonitor, LockStateChanged};
+use nwidgets_service_power::{
+    battery_icon, BatteryAlert, BatteryState, LowBattery, PowerService, PowerState, PowerStateChanged,
+};
+use nwidgets_service_theme::ThemeService;
 
 const CORNER_RADIUS: f32 = 12.0;
-const DISPLAY_DURATION_MS: u64 = 2000;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum OsdEvent {
@@ -15,6 +19,13 @@ pub enum OsdEvent {
     Microphone { muted: bool },
     CapsLock { enabled: bool },
     Clipboard { content: String },
+    Power { plugged: bool, percentage: u8 },
+    LowBattery { percentage: u8, alert: BatteryAlert },
+}
+
+/// Mains power as shown by the OSD: only known with UPower running and a battery to charge.
+fn plugged(state: &PowerState) -> Option<bool> {
+    (state.available && state.battery.is_some()).then(|| state.ac_online())
 }
 
 pub struct OsdView {
@@ -25,6 +36,7 @@ pub struct OsdView {
     last_sink_vol: u8,
     last_sink_muted: bool,
     last_source_muted: bool,
+    last_plugged: Option<bool>,
 }
 
 impl OsdView {
@@ -32,6 +44,7 @@ impl OsdView {
         let audio = AudioService::global(cx);
         let lock_monitor = LockMonitor::init(cx);
         let clipboard = ClipboardService::global(cx);
+        let power = PowerService::global(cx);
 
         let initial_audio = audio.read(cx).state.clone();
 
@@ -43,6 +56,7 @@ impl OsdView {
             last_sink_vol: initial_audio.sink_volume,
             last_sink_muted: initial_audio.sink_muted,
             last_source_muted: initial_audio.source_muted,
+            last_plugged: plugged(&power.read(cx).state),
         };
 
         // Subscribe AudioService
@@ -88,6 +102,29 @@ impl OsdView {
         })
         .detach();
 
+        // Subscribe PowerService (charger plugged/unplugged, low battery)
+        cx.subscribe(&power, |this, service, _: &PowerStateChanged, cx| {
+            let state = &service.read(cx).state;
+            let plugged = plugged(state);
+            let changed = this.last_plugged.is_some() && plugged.is_some() && plugged != this.last_plugged;
+            this.last_plugged = plugged;
+            if let (true, Some(plugged), Some(battery)) = (changed, plugged, state.battery.as_ref()) {
+                let percentage = battery.percentage;
+                this.show_event(OsdEvent::Power { plugged, percentage }, cx);
+            }
+        })
+        .detach();
+        cx.subscribe(&power, |this, _, event: &LowBattery, cx| {
+            this.show_event(
+                OsdEvent::LowBattery {
+                    percentage: event.percentage,
+                    alert: event.alert,
+                },
+                cx,
+            );
+        })
+        .detach();
+
         view
     }
 
@@ -110,11 +147,12 @@ impl OsdView {
         }
 
         // Timer de masquage auto
-        self._hide_task = Some(cx.spawn(|this: WeakEntity<Self>, cx: &mut AsyncApp| {
+        let display_duration = Duration::from_millis(ConfigService::config(cx).osd.timeout_ms);
+        self._hide_task = Some(cx.spawn(move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
             let mut cx = cx.clone();
             async move {
                 cx.background_executor()
-                    .timer(Duration::from_millis(DISPLAY_DURATION_MS))
+                    .timer(display_duration)
                     .await;
                 let _ = this.update(&mut cx, |this, cx| {
                     this.hide(cx);
@@ -139,15 +177,16 @@ impl OsdView {
 }
 
 impl Render for OsdView {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        let bg = rgb(0x2e3440);
-        let card_bg = rgb(0x3b4252);
-        let frost0 = rgb(0xd8dee9);
-        let muted_text = rgb(0x4c566a);
-        let accent = rgb(0x88c0d0);
-        let red = rgb(0xbf616a);
-        let green = rgb(0xa3be8c);
-        let yellow = rgb(0xebcb8b);
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let bg = palette.background;
+        let card_bg = palette.surface;
+        let frost0 = palette.foreground_dim;
+        let muted_text = palette.muted;
+        let accent = palette.accent;
+        let red = palette.danger;
+        let green = palette.success;
+        let yellow = palette.warning;
 
         let content = match &self.event {
             Some(OsdEvent::Volume { volume, muted }) => {
@@ -268,10 +307,54 @@ impl Render for OsdView {
                     )
             }
 
+            Some(OsdEvent::Power { plugged, percentage }) => {
+                let (state, label) = if *plugged {
+                    (BatteryState::Charging, "Charger connected")
+                } else {
+                    (BatteryState::Discharging, "On battery")
+                };
+                let icon_color = if *plugged { green } else { frost0 };
+
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_3()
+                    .w_full()
+                    .child(Icon::new(battery_icon(*percentage, state)).size(px(22.0)).text_color(icon_color))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(frost0).child(label))
+                            .child(div().text_xs().text_color(muted_text).child(format!("{percentage}%"))),
+                    )
+            }
+
+            Some(OsdEvent::LowBattery { percentage, alert }) => {
+                let (label, icon_color) = match alert {
+                    BatteryAlert::Low => ("Low Battery", yellow),
+                    BatteryAlert::Critical => ("Battery Critically Low", red),
+                };
+
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_3()
+                    .w_full()
+                    .child(Icon::new("battery_alert").size(px(22.0)).text_color(icon_color))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(frost0).child(label))
+                            .child(div().text_xs().text_color(icon_color).child(format!("{percentage}% remaining"))),
+                    )
+            }
+
             None => div().child(div().text_xs().text_color(muted_text).child("OSD")),
         };
 
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let frost_border = palette.border;
 
         div()
             .size_full()
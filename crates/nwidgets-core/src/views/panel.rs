@@ -2,26 +2,67 @@ use gpui::prelude::FluentBuilder;
 use gpui::*;
 use gpui_component::button::{Button, ButtonVariants};
 use gpui_component::corner::{Corner, CornerPosition};
+use gpui_component::input::{Input, InputEvent, InputState};
 use gpui_component::scroll::ScrollableElement;
 use gpui_component::slider::{Slider, SliderState};
 use gpui_component::switch::Switch;
-use gpui_component::{Icon, Selectable, Sizable};
+use gpui_component::{Disableable, Icon, Selectable, Sizable};
 use nwidgets_component_calendar::CalendarComponent;
-use nwidgets_service_audio::{AudioService, AudioStateChanged};
-use nwidgets_service_bluetooth::{BluetoothService, BluetoothStateChanged};
-use nwidgets_service_network::{NetworkService, NetworkStateChanged};
+use nwidgets_component_sysgraph::Sparkline;
+use nwidgets_service_audio::{AudioDevice, AudioService, AudioState, AudioStateChanged, StreamKind};
+use nwidgets_service_bluetooth::{AgentPromptKind, AgentReply, BluetoothPromptChanged, BluetoothService, BluetoothStateChanged};
+use nwidgets_service_config::ConfigService;
+use nwidgets_service_network::{
+    NetworkPromptChanged, NetworkService, NetworkStateChanged, VpnState, WifiNetwork, WifiSecurity,
+};
 use nwidgets_service_notification::{NotificationAdded, NotificationService, NotificationsCleared};
-use nwidgets_service_system_monitor::{SystemMonitorService, SystemStatsChanged};
+use nwidgets_service_power::{format_time, BatteryAlert, DeviceKind, PowerDevice, PowerService, PowerStateChanged};
+use nwidgets_service_ssh::{SshService, SshStateChanged, UnitState};
+use nwidgets_service_system_monitor::{
+    format_bytes, format_rate, DemandGuard, GpuVendor, History, MonitorDemand, Rate, StatsHistory, SystemMonitorService,
+    SystemStats, SystemStatsChanged,
+};
+use nwidgets_service_theme::ThemeService;
+use std::collections::HashMap;
 
 const CORNER_RADIUS: f32 = 12.0;
+/// Longest legacy PIN code accepted by BlueZ.
+const MAX_PIN_LEN: usize = 16;
+/// Passkeys are 6 digits.
+const PASSKEY_LEN: usize = 6;
+
+/// Maps the freedesktop icon name reported by BlueZ to one of ours.
+fn bluetooth_icon(icon: Option<&str>) -> &'static str {
+    match icon {
+        Some("audio-headset" | "audio-headphones") => "headphones",
+        Some("audio-card") => "speaker",
+        Some("input-keyboard") => "keyboard",
+        Some("input-mouse" | "input-tablet") => "mouse",
+        Some("input-gaming") => "sports_esports",
+        Some("phone") => "smartphone",
+        Some("computer") => "computer",
+        _ => "bluetooth",
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PanelSection {
     Monitor,
     Bluetooth,
     Network,
+    Vpn,
+    Ssh,
+    Power,
     AudioSink,
     AudioSource,
+    Mixer,
+}
+
+/// Volume slider of one application stream.
+struct StreamSlider {
+    volume: u8,
+    slider: Entity<SliderState>,
+    _subscription: Subscription,
 }
 
 pub struct Panel {
@@ -30,10 +71,21 @@ pub struct Panel {
     system_monitor: Entity<SystemMonitorService>,
     bluetooth: Entity<BluetoothService>,
     network: Entity<NetworkService>,
+    ssh: Entity<SshService>,
+    power: Entity<PowerService>,
     notifications: Entity<NotificationService>,
     volume_slider: Entity<SliderState>,
     mic_slider: Entity<SliderState>,
+    stream_sliders: HashMap<(StreamKind, u32), StreamSlider>,
     expanded_section: Option<PanelSection>,
+    /// Digits typed for a PIN code or passkey request.
+    pin_entry: String,
+    /// Shared by the wifi password row and the secret request card; built on first render.
+    password_input: Option<Entity<InputState>>,
+    /// Secured network whose password row is open.
+    password_ssid: Option<String>,
+    /// Full rate system monitor sampling while the panel is shown.
+    monitor_demand: Option<DemandGuard>,
 }
 
 impl Panel {
@@ -42,6 +94,8 @@ impl Panel {
         let system_monitor = SystemMonitorService::global(cx);
         let bluetooth = BluetoothService::global(cx);
         let network = NetworkService::global(cx);
+        let ssh = SshService::global(cx);
+        let power = PowerService::global(cx);
         let notifications = NotificationService::init(cx);
 
         let sink_vol = audio.read(cx).state.sink_volume as f32;
@@ -82,6 +136,7 @@ impl Panel {
                     .step(1.0)
                     .default_value(source_vol);
             });
+            this.sync_stream_sliders(cx);
             cx.notify();
         })
         .detach();
@@ -118,20 +173,97 @@ impl Panel {
 
         cx.subscribe(&system_monitor, |_, _, _: &SystemStatsChanged, cx| cx.notify()).detach();
         cx.subscribe(&bluetooth, |_, _, _: &BluetoothStateChanged, cx| cx.notify()).detach();
+        cx.subscribe(&bluetooth, |this, _, _: &BluetoothPromptChanged, cx| {
+            this.pin_entry.clear();
+            cx.notify();
+        })
+        .detach();
         cx.subscribe(&network, |_, _, _: &NetworkStateChanged, cx| cx.notify()).detach();
+        cx.subscribe(&network, |_, _, _: &NetworkPromptChanged, cx| cx.notify()).detach();
+        cx.subscribe(&ssh, |_, _, _: &SshStateChanged, cx| cx.notify()).detach();
+        cx.subscribe(&power, |_, _, _: &PowerStateChanged, cx| cx.notify()).detach();
         cx.subscribe(&notifications, |_, _, _: &NotificationAdded, cx| cx.notify()).detach();
         cx.subscribe(&notifications, |_, _, _: &NotificationsCleared, cx| cx.notify()).detach();
 
-        Self {
+        let mut panel = Self {
             calendar: None,
             audio,
             system_monitor,
             bluetooth,
             network,
+            ssh,
+            power,
             notifications,
             volume_slider,
             mic_slider,
+            stream_sliders: HashMap::new(),
+            pin_entry: String::new(),
+            password_input: None,
+            password_ssid: None,
             expanded_section: None,
+            monitor_demand: None,
+        };
+        panel.sync_stream_sliders(cx);
+        panel
+    }
+
+    /// Called when the panel window is shown or hidden.
+    pub fn set_visible(&mut self, visible: bool, cx: &mut Context<Self>) {
+        self.monitor_demand = visible.then(|| {
+            self.system_monitor.read(cx).request(MonitorDemand {
+                rate: Rate::Fast,
+                gpu: true,
+                disks: true,
+            })
+        });
+    }
+
+    /// Creates sliders for new streams, drops those of closed ones and
+    /// follows volume changes made elsewhere.
+    fn sync_stream_sliders(&mut self, cx: &mut Context<Self>) {
+        let streams = self.audio.read(cx).state.streams.clone();
+        self.stream_sliders
+            .retain(|key, _| streams.iter().any(|stream| (stream.kind, stream.id) == *key));
+
+        for stream in streams {
+            let key = (stream.kind, stream.id);
+            let volume = stream.volume as f32;
+            match self.stream_sliders.get_mut(&key) {
+                Some(entry) if entry.volume != stream.volume => {
+                    entry.volume = stream.volume;
+                    entry.slider.update(cx, |slider, _cx| {
+                        *slider = SliderState::new().min(0.0).max(100.0).step(1.0).default_value(volume);
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    let slider = cx.new(|_| SliderState::new().min(0.0).max(100.0).step(1.0).default_value(volume));
+                    let subscription = cx.subscribe(&slider, move |this, _, ev: &gpui_component::slider::SliderEvent, cx| {
+                        let val = match ev {
+                            gpui_component::slider::SliderEvent::Change(v) | gpui_component::slider::SliderEvent::Release(v) => {
+                                match v {
+                                    gpui_component::slider::SliderValue::Single(f) => *f,
+                                    _ => 0.0,
+                                }
+                            }
+                        };
+                        if let Some(entry) = this.stream_sliders.get_mut(&key) {
+                            entry.volume = val as u8;
+                        }
+                        this.audio.update(cx, |audio, cx| {
+                            audio.set_stream_volume(key.0, key.1, val as u8, cx);
+                        });
+                    });
+                    self.stream_sliders.insert(
+                        key,
+                        StreamSlider {
+                            volume: stream.volume,
+                            slider,
+                            _subscription: subscription,
+                        },
+                    );
+                }
+            }
         }
     }
 
@@ -141,30 +273,1118 @@ impl Panel {
         } else {
             self.expanded_section = Some(section);
         }
+        // Scanning drains the battery, it only lasts while the list is shown
+        if self.expanded_section != Some(PanelSection::Bluetooth) && self.bluetooth.read(cx).state.discovering {
+            self.bluetooth.update(cx, |bt, cx| bt.stop_discovery(cx));
+        }
+        cx.notify();
+    }
+
+    // ── 1. Audio Section with Dropdown Device Details ──
+    fn render_audio_section(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let card_bg = palette.surface;
+        let text_main = palette.foreground;
+        let text_muted = palette.muted;
+        let accent = palette.accent;
+
+        let sink_expanded = self.expanded_section == Some(PanelSection::AudioSink);
+        let source_expanded = self.expanded_section == Some(PanelSection::AudioSource);
+        let mixer_expanded = self.expanded_section == Some(PanelSection::Mixer);
+
+        let audio_state = self.audio.read(cx).state.clone();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .p_3()
+            .bg(card_bg)
+            .rounded_md()
+            // Speaker / Output Volume
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .child(
+                                div()
+                                    .id("sink-mute-trigger")
+                                    .flex()
+                                    .items_center()
+                                    .gap_2()
+                                    .cursor_pointer()
+                                    .on_click(cx.listener(|this, _, _window, cx| {
+                                        this.audio.update(cx, |audio, cx| audio.toggle_sink_mute(cx));
+                                    }))
+                                    .child(Icon::new(if audio_state.sink_muted { "volume_off" } else { "volume_up" }).size(px(20.0)).text_color(if audio_state.sink_muted { palette.danger } else { accent }))
+                                    .child(
+                                        div()
+                                            .text_sm()
+                                            .font_weight(FontWeight::SEMIBOLD)
+                                            .text_color(text_main)
+                                            .child(if audio_state.sink_muted { "Output Muted" } else { "Output Volume" }),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .items_center()
+                                    .gap_2()
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .text_color(text_muted)
+                                            .child(format!("{}%", audio_state.sink_volume)),
+                                    )
+                                    .child(
+                                        Button::new("sink-details-btn")
+                                            .ghost()
+                                            .with_size(gpui_component::Size::Small)
+                                            .icon(Icon::new(if sink_expanded { "keyboard_arrow_up" } else { "keyboard_arrow_down" }).size(px(16.0)))
+                                            .on_click(cx.listener(|this, _, _window, cx| {
+                                                this.toggle_section(PanelSection::AudioSink, cx);
+                                            })),
+                                    ),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .h(px(24.0))
+                            .flex()
+                            .items_center()
+                            .child(Slider::new(&self.volume_slider)),
+                    ),
+            )
+            .when(sink_expanded, |this| {
+                this.child(Self::render_device_list(audio_state.sinks.clone(), PanelSection::AudioSink, cx))
+            })
+            // Microphone Input
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .child(
+                                div()
+                                    .id("source-mute-trigger")
+                                    .flex()
+                                    .items_center()
+                                    .gap_2()
+                                    .cursor_pointer()
+                                    .on_click(cx.listener(|this, _, _window, cx| {
+                                        this.audio.update(cx, |audio, cx| audio.toggle_source_mute(cx));
+                                    }))
+                                    .child(Icon::new(if audio_state.source_muted { "mic_off" } else { "mic" }).size(px(20.0)).text_color(if audio_state.source_muted { palette.danger } else { accent }))
+                                    .child(
+                                        div()
+                                            .text_sm()
+                                            .font_weight(FontWeight::SEMIBOLD)
+                                            .text_color(text_main)
+                                            .child(if audio_state.source_muted { "Microphone Muted" } else { "Microphone" }),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .items_center()
+                                    .gap_2()
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .text_color(text_muted)
+                                            .child(format!("{}%", audio_state.source_volume)),
+                                    )
+                                    .child(
+                                        Button::new("source-details-btn")
+                                            .ghost()
+                                            .with_size(gpui_component::Size::Small)
+                                            .icon(Icon::new(if source_expanded { "keyboard_arrow_up" } else { "keyboard_arrow_down" }).size(px(16.0)))
+                                            .on_click(cx.listener(|this, _, _window, cx| {
+                                                this.toggle_section(PanelSection::AudioSource, cx);
+                                            })),
+                                    ),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .h(px(24.0))
+                            .flex()
+                            .items_center()
+                            .child(Slider::new(&self.mic_slider)),
+                    ),
+            )
+            .when(source_expanded, |this| {
+                this.child(Self::render_device_list(audio_state.sources.clone(), PanelSection::AudioSource, cx))
+            })
+            // Per-application streams
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .child(Icon::new("tune").size(px(20.0)).text_color(accent))
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .text_color(text_main)
+                                    .child("Applications"),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(text_muted)
+                                    .child(format!("{}", audio_state.streams.len())),
+                            )
+                            .child(
+                                Button::new("mixer-details-btn")
+                                    .ghost()
+                                    .with_size(gpui_component::Size::Small)
+                                    .icon(Icon::new(if mixer_expanded { "keyboard_arrow_up" } else { "keyboard_arrow_down" }).size(px(16.0)))
+                                    .on_click(cx.listener(|this, _, _window, cx| {
+                                        this.toggle_section(PanelSection::Mixer, cx);
+                                    })),
+                            ),
+                    ),
+            )
+            .when(mixer_expanded, |this| this.child(self.render_mixer(&audio_state, cx)))
+    }
+
+    /// One row per application stream: mute toggle, volume slider and target device.
+    fn render_mixer(&self, audio_state: &AudioState, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_2()
+            .bg(palette.background)
+            .rounded_md()
+            .when(audio_state.streams.is_empty(), |this| {
+                this.child(div().text_xs().text_color(palette.muted).child("No application is playing or recording"))
+            })
+            .children(audio_state.streams.iter().filter_map(|stream| {
+                let slider = self.stream_sliders.get(&(stream.kind, stream.id))?.slider.clone();
+                let (kind, id) = (stream.kind, stream.id);
+                let devices = match kind {
+                    StreamKind::Playback => audio_state.sinks.clone(),
+                    StreamKind::Recording => audio_state.sources.clone(),
+                };
+                let current = devices.iter().position(|device| device.id == stream.device);
+                let device_label = current
+                    .map(|ix| devices[ix].description.clone())
+                    .unwrap_or_default();
+                let icon = match (kind, stream.muted) {
+                    (StreamKind::Playback, false) => "volume_up",
+                    (StreamKind::Playback, true) => "volume_off",
+                    (StreamKind::Recording, false) => "mic",
+                    (StreamKind::Recording, true) => "mic_off",
+                };
+                let title = if stream.media_name.is_empty() || stream.media_name == stream.app_name {
+                    stream.app_name.clone()
+                } else {
+                    format!("{} · {}", stream.app_name, stream.media_name)
+                };
+                let row_id = match kind {
+                    StreamKind::Playback => format!("playback-{id}"),
+                    StreamKind::Recording => format!("recording-{id}"),
+                };
+
+                Some(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap_1()
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_between()
+                                .gap_2()
+                                .child(
+                                    div()
+                                        .id(SharedString::from(format!("{row_id}-mute")))
+                                        .flex()
+                                        .flex_1()
+                                        .min_w_0()
+                                        .items_center()
+                                        .gap_2()
+                                        .cursor_pointer()
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            this.audio.update(cx, |audio, cx| audio.toggle_stream_mute(kind, id, cx));
+                                        }))
+                                        .child(
+                                            Icon::new(icon)
+                                                .size(px(16.0))
+                                                .text_color(if stream.muted { palette.danger } else { palette.accent }),
+                                        )
+                                        .child(
+                                            div()
+                                                .text_xs()
+                                                .overflow_hidden()
+                                                .whitespace_nowrap()
+                                                .text_color(palette.foreground)
+                                                .child(title),
+                                        ),
+                                )
+                                .child(div().text_xs().text_color(palette.muted).child(format!("{}%", stream.volume))),
+                        )
+                        .child(div().h(px(24.0)).flex().items_center().child(Slider::new(&slider)))
+                        // Clicking the device sends the stream to the next one
+                        .when(devices.len() > 1, |this| {
+                            this.child(
+                                div()
+                                    .id(SharedString::from(format!("{row_id}-device")))
+                                    .text_xs()
+                                    .text_color(palette.foreground_dim)
+                                    .cursor_pointer()
+                                    .hover(|s| s.text_color(palette.accent))
+                                    .on_click(cx.listener(move |this, _, _window, cx| {
+                                        let next = current.map_or(0, |ix| (ix + 1) % devices.len());
+                                        let device = devices[next].clone();
+                                        this.audio.update(cx, |audio, cx| audio.move_stream(kind, id, &device, cx));
+                                    }))
+                                    .child(device_label),
+                            )
+                        }),
+                )
+            }))
+    }
+
+    /// Selectable outputs (`AudioSink`) or inputs (`AudioSource`), the default one checked.
+    fn render_device_list(devices: Vec<AudioDevice>, section: PanelSection, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let is_sink = section == PanelSection::AudioSink;
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .p_2()
+            .bg(palette.background)
+            .rounded_md()
+            .when(devices.is_empty(), |this| {
+                this.child(div().text_xs().text_color(palette.muted).child(if is_sink { "No output device" } else { "No input device" }))
+            })
+            .children(devices.into_iter().map(|device| {
+                let name = device.name.clone();
+                div()
+                    .id(SharedString::from(format!("audio-device-{}", device.id)))
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap_2()
+                    .px_2()
+                    .py_1()
+                    .rounded_md()
+                    .cursor_pointer()
+                    .hover(|s| s.bg(palette.surface))
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.audio.update(cx, |audio, cx| {
+                            if is_sink {
+                                audio.set_default_sink(&name, cx);
+                            } else {
+                                audio.set_default_source(&name, cx);
+                            }
+                        });
+                    }))
+                    .child(
+                        div()
+                            .text_xs()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .text_color(if device.is_default { palette.foreground } else { palette.foreground_dim })
+                            .child(device.description),
+                    )
+                    .when(device.is_default, |this| {
+                        this.child(Icon::new("check").size(px(16.0)).text_color(palette.accent))
+                    })
+            }))
+    }
+
+    /// Known devices with their actions; while scanning, discovered devices too.
+    fn render_bluetooth_devices(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let bluetooth = self.bluetooth.read(cx);
+        let discovering = bluetooth.state.discovering;
+        let low_battery = ConfigService::config(cx).bluetooth.low_battery;
+        let adapter_error = bluetooth
+            .state
+            .adapter
+            .as_ref()
+            .and_then(|adapter| bluetooth.errors.get(adapter))
+            .cloned();
+        let devices: Vec<_> = bluetooth
+            .state
+            .devices
+            .iter()
+            .filter(|dev| discovering || dev.paired || dev.connected)
+            .map(|dev| {
+                let pending = bluetooth.pending.get(&dev.path).copied();
+                let error = bluetooth.errors.get(&dev.path).cloned();
+                (dev.clone(), pending, error)
+            })
+            .collect();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .when_some(adapter_error, |this, error| {
+                this.child(div().text_xs().text_color(palette.danger).child(error))
+            })
+            .when(devices.is_empty(), |this| {
+                this.child(div().text_xs().text_color(palette.muted).child(if discovering {
+                    "Searching for devices..."
+                } else {
+                    "No paired device"
+                }))
+            })
+            .children(devices.into_iter().map(|(dev, pending, error)| {
+                let id = dev.address.clone();
+                let busy = pending.is_some();
+                let (status, status_color) = match (pending, &error) {
+                    (Some(action), _) => (action.label().to_string(), palette.muted),
+                    (None, Some(error)) => (error.clone(), palette.danger),
+                    (None, None) if dev.connected => ("Connected".to_string(), palette.success),
+                    (None, None) if dev.paired => ("Paired".to_string(), palette.foreground_dim),
+                    (None, None) => (
+                        dev.rssi.map(|rssi| format!("{rssi} dBm")).unwrap_or_default(),
+                        palette.muted,
+                    ),
+                };
+                let connect_path = dev.path.clone();
+                let trust_path = dev.path.clone();
+                let remove_path = dev.path.clone();
+                let (connected, paired, trusted) = (dev.connected, dev.paired, dev.trusted);
+                let battery = dev.battery;
+
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap_2()
+                    .p_2()
+                    .bg(palette.background)
+                    .rounded_md()
+                    .child(
+                        div()
+                            .flex()
+                            .flex_1()
+                            .min_w_0()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                Icon::new(bluetooth_icon(dev.icon.as_deref()))
+                                    .size(px(16.0))
+                                    .text_color(if connected { palette.accent } else { palette.foreground_dim }),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_col()
+                                    .min_w_0()
+                                    .child(
+                                        div()
+                                            .text_xs()
+                                            .overflow_hidden()
+                                            .whitespace_nowrap()
+                                            .text_color(palette.foreground)
+                                            .child(dev.name.clone()),
+                                    )
+                                    .when(!status.is_empty() || battery.is_some(), |this| {
+                                        this.child(
+                                            div()
+                                                .text_xs()
+                                                .overflow_hidden()
+                                                .whitespace_nowrap()
+                                                .flex()
+                                                .gap_2()
+                                                .child(div().text_color(status_color).child(status))
+                                                .when_some(battery, |this, battery| {
+                                                    this.child(
+                                                        div()
+                                                            .text_color(if battery < low_battery { palette.danger } else { palette.foreground_dim })
+                                                            .child(format!("{battery}%")),
+                                                    )
+                                                }),
+                                        )
+                                    }),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_1()
+                            // Pairs a new device, otherwise connects or disconnects it
+                            .child(
+                                Button::new(SharedString::from(format!("bt-connect-{id}")))
+                                    .ghost()
+                                    .with_size(gpui_component::Size::Small)
+                                    .icon(Icon::new(if connected { "link_off" } else { "link" }).size(px(16.0)))
+                                    .disabled(busy)
+                                    .on_click(cx.listener(move |this, _, _window, cx| {
+                                        this.bluetooth.update(cx, |bt, cx| {
+                                            if connected {
+                                                bt.disconnect(&connect_path, cx);
+                                            } else if paired {
+                                                bt.connect(&connect_path, cx);
+                                            } else {
+                                                bt.pair(&connect_path, cx);
+                                            }
+                                        });
+                                    })),
+                            )
+                            .when(paired, |this| {
+                                this.child(
+                                    Button::new(SharedString::from(format!("bt-trust-{id}")))
+                                        .ghost()
+                                        .with_size(gpui_component::Size::Small)
+                                        .icon(Icon::new("verified_user").size(px(16.0)))
+                                        .selected(trusted)
+                                        .disabled(busy)
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            this.bluetooth.update(cx, |bt, cx| bt.trust(&trust_path, !trusted, cx));
+                                        })),
+                                )
+                                .child(
+                                    Button::new(SharedString::from(format!("bt-remove-{id}")))
+                                        .ghost()
+                                        .with_size(gpui_component::Size::Small)
+                                        .icon(Icon::new("delete").size(px(16.0)))
+                                        .disabled(busy)
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            this.bluetooth.update(cx, |bt, cx| bt.remove(&remove_path, cx));
+                                        })),
+                                )
+                            }),
+                    )
+            }))
+    }
+
+    /// Pairing agent request: code display, confirmation or a keypad for PIN/passkey entry.
+    fn render_pairing_prompt(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let palette = *ThemeService::palette(cx);
+        let bluetooth = self.bluetooth.read(cx);
+        let prompt = bluetooth.prompt.clone()?;
+        let device = bluetooth
+            .state
+            .devices
+            .iter()
+            .find(|dev| dev.path == prompt.device)
+            .map(|dev| dev.name.clone())
+            .unwrap_or_else(|| "device".to_string());
+
+        let max_len = match prompt.kind {
+            AgentPromptKind::PinCode => MAX_PIN_LEN,
+            AgentPromptKind::Passkey => PASSKEY_LEN,
+            _ => 0,
+        };
+        let message = match &prompt.kind {
+            AgentPromptKind::PinCode | AgentPromptKind::Passkey => "Enter the code shown on the device".to_string(),
+            AgentPromptKind::DisplayPinCode(pin) => format!("Type {pin} on the device, then press Enter"),
+            AgentPromptKind::DisplayPasskey { passkey, entered } => {
+                format!("Type {passkey:06} on the device, then press Enter ({entered}/{PASSKEY_LEN})")
+            }
+            AgentPromptKind::Confirmation(passkey) => format!("Does the device show {passkey:06}?"),
+            AgentPromptKind::Authorization => "Allow this device to pair?".to_string(),
+            AgentPromptKind::Service(uuid) => format!("Allow this device to use service {uuid}?"),
+        };
+
+        let button = |id: &'static str, label: &'static str| {
+            Button::new(id).with_size(gpui_component::Size::Small).label(label)
+        };
+
+        let actions = if prompt.kind.is_display() {
+            div().child(button("bt-prompt-dismiss", "Dismiss").ghost().on_click(cx.listener(|this, _, _window, cx| {
+                this.bluetooth.update(cx, |bt, cx| bt.answer_prompt(AgentReply::Accept, cx));
+            })))
+        } else if max_len > 0 {
+            let is_passkey = prompt.kind == AgentPromptKind::Passkey;
+            let complete = if is_passkey {
+                self.pin_entry.len() == PASSKEY_LEN
+            } else {
+                !self.pin_entry.is_empty()
+            };
+            div()
+                .child(button("bt-prompt-cancel", "Cancel").ghost().on_click(cx.listener(|this, _, _window, cx| {
+                    this.bluetooth.update(cx, |bt, cx| bt.answer_prompt(AgentReply::Reject, cx));
+                })))
+                .child(
+                    button("bt-prompt-ok", "Pair")
+                        .primary()
+                        .disabled(!complete)
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            let entry = std::mem::take(&mut this.pin_entry);
+                            let reply = if is_passkey {
+                                entry.parse().map(AgentReply::Passkey).unwrap_or(AgentReply::Reject)
+                            } else {
+                                AgentReply::PinCode(entry)
+                            };
+                            this.bluetooth.update(cx, |bt, cx| bt.answer_prompt(reply, cx));
+                        })),
+                )
+        } else {
+            div()
+                .child(button("bt-prompt-reject", "Reject").ghost().on_click(cx.listener(|this, _, _window, cx| {
+                    this.bluetooth.update(cx, |bt, cx| bt.answer_prompt(AgentReply::Reject, cx));
+                })))
+                .child(button("bt-prompt-accept", "Accept").primary().on_click(cx.listener(|this, _, _window, cx| {
+                    this.bluetooth.update(cx, |bt, cx| bt.answer_prompt(AgentReply::Accept, cx));
+                })))
+        };
+
+        // Digits only: a keypad is quicker than focusing an input
+        let keypad = (max_len > 0).then(|| {
+            let keys = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "", "0", "backspace"];
+            div()
+                .flex()
+                .flex_wrap()
+                .gap_1()
+                .w(px(156.0))
+                .children(keys.into_iter().map(|key| {
+                    let cell = div().id(SharedString::from(format!("bt-key-{key}"))).w(px(48.0)).h(px(32.0));
+                    if key.is_empty() {
+                        return cell;
+                    }
+                    cell.flex()
+                        .items_center()
+                        .justify_center()
+                        .rounded_md()
+                        .bg(palette.background)
+                        .cursor_pointer()
+                        .hover(|s| s.bg(palette.surface_alt))
+                        .text_sm()
+                        .text_color(palette.foreground)
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            if key == "backspace" {
+                                this.pin_entry.pop();
+                            } else if this.pin_entry.len() < max_len {
+                                this.pin_entry.push_str(key);
+                            }
+                            cx.notify();
+                        }))
+                        .map(|cell| {
+                            if key == "backspace" {
+                                cell.child(Icon::new("backspace").size(px(16.0)))
+                            } else {
+                                cell.child(key)
+                            }
+                        })
+                }))
+        });
+
+        Some(
+            div()
+                .flex()
+                .flex_col()
+                .gap_2()
+                .p_3()
+                .bg(palette.surface)
+                .border_1()
+                .border_color(palette.accent)
+                .rounded_md()
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .child(Icon::new("bluetooth").size(px(16.0)).text_color(palette.accent))
+                        .child(
+                            div()
+                                .text_xs()
+                                .font_weight(FontWeight::BOLD)
+                                .text_color(palette.accent)
+                                .child(format!("Pairing with {device}")),
+                        ),
+                )
+                .child(div().text_xs().text_color(palette.foreground).child(message))
+                .when(max_len > 0, |this| {
+                    this.child(
+                        div()
+                            .h(px(28.0))
+                            .px_2()
+                            .flex()
+                            .items_center()
+                            .rounded_md()
+                            .bg(palette.background)
+                            .text_sm()
+                            .text_color(palette.foreground)
+                            .child(self.pin_entry.clone()),
+                    )
+                })
+                .children(keypad)
+                .child(actions.flex().justify_end().gap_2()),
+        )
+    }
+
+    /// Sends the typed password to the secret request, or connects the open password row.
+    fn submit_password(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(input) = self.password_input.clone() else {
+            return;
+        };
+        let password = input.read(cx).value().to_string();
+        if password.is_empty() {
+            return;
+        }
+        input.update(cx, |input, cx| input.set_value("", window, cx));
+
+        if self.network.read(cx).secret_prompt.is_some() {
+            self.network.update(cx, |net, cx| net.answer_secret(Some(password), cx));
+        } else if let Some(ssid) = self.password_ssid.take() {
+            self.network.update(cx, |net, cx| net.connect(&ssid, Some(password), cx));
+        }
+        cx.notify();
+    }
+
+    fn focus_password(&self, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(input) = &self.password_input {
+            let fh = input.read(cx).focus_handle(cx);
+            window.focus(&fh, cx);
+        }
+    }
+
+    /// Opens the password row of unknown secured networks, connects the others.
+    fn select_network(&mut self, network: &WifiNetwork, window: &mut Window, cx: &mut Context<Self>) {
+        if network.active {
+            return;
+        }
+        let needs_password =
+            !network.known && matches!(network.security, WifiSecurity::Wep | WifiSecurity::Wpa | WifiSecurity::Wpa3);
+        if needs_password {
+            self.password_ssid = Some(network.ssid.clone());
+            self.focus_password(window, cx);
+        } else {
+            self.password_ssid = None;
+            self.network.update(cx, |net, cx| net.connect(&network.ssid, None, cx));
+        }
         cx.notify();
     }
 
-    // ── 1. Audio Section with Dropdown Device Details ──
-    fn render_audio_section(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
-        let card_bg = rgb(0x3b4252);
-        let text_main = rgb(0xe5e9f0);
-        let text_muted = rgb(0x4c566a);
-        let accent = rgb(0x88c0d0);
+    /// Password input with its Cancel/Connect buttons.
+    fn render_password_row(
+        &self,
+        id: &'static str,
+        on_cancel: impl Fn(&mut Self, &mut Window, &mut Context<Self>) + 'static,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .children(self.password_input.as_ref().map(|input| {
+                div()
+                    .flex_1()
+                    .h(px(28.0))
+                    .rounded_md()
+                    .bg(palette.background)
+                    .child(Input::new(input).size_full())
+            }))
+            .child(
+                Button::new(SharedString::from(format!("{id}-cancel")))
+                    .ghost()
+                    .with_size(gpui_component::Size::Small)
+                    .label("Cancel")
+                    .on_click(cx.listener(move |this, _, window, cx| on_cancel(this, window, cx))),
+            )
+            .child(
+                Button::new(SharedString::from(format!("{id}-connect")))
+                    .primary()
+                    .with_size(gpui_component::Size::Small)
+                    .label("Connect")
+                    .on_click(cx.listener(|this, _, window, cx| this.submit_password(window, cx))),
+            )
+    }
+
+    /// NetworkManager secret request (e.g. a changed wifi password).
+    fn render_secret_prompt(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let palette = *ThemeService::palette(cx);
+        let prompt = self.network.read(cx).secret_prompt.clone()?;
+        let message = if prompt.retry {
+            format!("The password for {} was rejected, try again", prompt.connection)
+        } else {
+            format!("{} needs a password", prompt.connection)
+        };
+
+        Some(
+            div()
+                .flex()
+                .flex_col()
+                .gap_2()
+                .p_3()
+                .bg(palette.surface)
+                .border_1()
+                .border_color(palette.accent)
+                .rounded_md()
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .child(Icon::new("lock").size(px(16.0)).text_color(palette.accent))
+                        .child(
+                            div()
+                                .text_xs()
+                                .font_weight(FontWeight::BOLD)
+                                .text_color(palette.accent)
+                                .child("Authentication required"),
+                        ),
+                )
+                .child(div().text_xs().text_color(palette.foreground).child(message))
+                .child(self.render_password_row(
+                    "secret",
+                    |this, _window, cx| this.network.update(cx, |net, cx| net.answer_secret(None, cx)),
+                    cx,
+                )),
+        )
+    }
+
+    /// Visible networks with their security; the password row opens under unknown secured ones.
+    fn render_wifi_networks(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let network = self.network.read(cx);
+        let networks = network.state.networks.clone();
+        let connecting = network.connecting.clone();
+        let errors = network.errors.clone();
+        // The secret request card owns the input while it is shown
+        let password_ssid = self.password_ssid.clone().filter(|_| network.secret_prompt.is_none());
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .when(networks.is_empty(), |this| {
+                this.child(div().text_xs().text_color(palette.muted).child("No network found"))
+            })
+            .children(networks.into_iter().map(|net| {
+                let ssid = net.ssid.clone();
+                let (status, status_color) = if connecting.as_deref() == Some(ssid.as_str()) {
+                    ("Connecting...".to_string(), palette.muted)
+                } else if let Some(error) = errors.get(&ssid) {
+                    (error.clone(), palette.danger)
+                } else {
+                    (net.security.label().to_string(), palette.foreground_dim)
+                };
+                let signal_icon = match net.signal {
+                    0..=24 => "network_wifi_1_bar",
+                    25..=49 => "network_wifi_2_bar",
+                    50..=74 => "network_wifi_3_bar",
+                    _ => "signal_wifi_4_bar",
+                };
+                let show_password = password_ssid.as_deref() == Some(ssid.as_str());
+                let forget_ssid = ssid.clone();
+                let (active, known, secure) = (net.active, net.known, net.security.is_secure());
+
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .p_2()
+                    .bg(palette.background)
+                    .rounded_md()
+                    .child(
+                        div()
+                            .id(SharedString::from(format!("wifi-{ssid}")))
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .gap_2()
+                            .when(!active, |this| this.cursor_pointer())
+                            .on_click(cx.listener(move |this, _, window, cx| this.select_network(&net, window, cx)))
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_1()
+                                    .min_w_0()
+                                    .items_center()
+                                    .gap_2()
+                                    .child(
+                                        Icon::new(signal_icon)
+                                            .size(px(16.0))
+                                            .text_color(if active { palette.accent } else { palette.foreground_dim }),
+                                    )
+                                    .child(
+                                        div()
+                                            .flex()
+                                            .flex_col()
+                                            .min_w_0()
+                                            .child(
+                                                div()
+                                                    .text_xs()
+                                                    .overflow_hidden()
+                                                    .whitespace_nowrap()
+                                                    .text_color(palette.foreground)
+                                                    .child(ssid.clone()),
+                                            )
+                                            .child(
+                                                div()
+                                                    .text_xs()
+                                                    .overflow_hidden()
+                                                    .whitespace_nowrap()
+                                                    .text_color(status_color)
+                                                    .child(status),
+                                            ),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .items_center()
+                                    .gap_1()
+                                    .when(secure, |this| {
+                                        this.child(Icon::new("lock").size(px(14.0)).text_color(palette.foreground_dim))
+                                    })
+                                    .when(active, |this| {
+                                        this.child(Icon::new("check").size(px(16.0)).text_color(palette.accent))
+                                    })
+                                    .when(known, |this| {
+                                        this.child(
+                                            Button::new(SharedString::from(format!("wifi-forget-{ssid}")))
+                                                .ghost()
+                                                .with_size(gpui_component::Size::Small)
+                                                .icon(Icon::new("delete").size(px(16.0)))
+                                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                                    this.network.update(cx, |net, cx| net.forget(&forget_ssid, cx));
+                                                })),
+                                        )
+                                    }),
+                            ),
+                    )
+                    .when(show_password, |this| {
+                        this.child(self.render_password_row(
+                            "wifi-password",
+                            |this, _window, cx| {
+                                this.password_ssid = None;
+                                cx.notify();
+                            },
+                            cx,
+                        ))
+                    })
+            }))
+    }
+
+    /// Graphs of the last samples; sensors the machine lacks are left out.
+    fn render_history(history: &StatsHistory, cx: &mut Context<Self>) -> impl IntoElement {
+        const GRAPH_HEIGHT: f32 = 28.0;
+        let palette = *ThemeService::palette(cx);
+        let row = |label: &'static str, graph: Sparkline, value: String| {
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(div().w(px(44.0)).text_xs().text_color(palette.muted).child(label))
+                .child(div().flex_1().h(px(GRAPH_HEIGHT)).child(graph))
+                .child(div().min_w(px(64.0)).text_xs().text_color(palette.foreground_dim).child(value))
+        };
+        let percent = |series: &History<u8>, color: Hsla| {
+            Sparkline::new(series.iter().map(f32::from), series.capacity()).max(100.0).color(color)
+        };
+        let optional = |series: &History<Option<u8>>, color: Hsla| {
+            Sparkline::new(series.iter().map(|value| value.unwrap_or(0) as f32), series.capacity())
+                .max(100.0)
+                .color(color)
+        };
+        let cpu = history.cpu.latest().unwrap_or(0);
+        let ram = history.ram.latest().unwrap_or(0);
+        let net = history.net.latest().unwrap_or_default();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(row("CPU", percent(&history.cpu, palette.accent), format!("{cpu}%")))
+            .when_some(history.gpu.latest().flatten(), |div_elem, gpu| {
+                div_elem.child(row("GPU", optional(&history.gpu, palette.accent), format!("{gpu}%")))
+            })
+            .child(row("RAM", percent(&history.ram, palette.highlight), format!("{ram}%")))
+            .when_some(history.cpu_temp.latest().flatten(), |div_elem, temp| {
+                div_elem.child(row("CPU °C", optional(&history.cpu_temp, palette.warning), format!("{temp}°C")))
+            })
+            .when_some(history.gpu_temp.latest().flatten(), |div_elem, temp| {
+                div_elem.child(row("GPU °C", optional(&history.gpu_temp, palette.warning), format!("{temp}°C")))
+            })
+            .child(row(
+                "Down",
+                Sparkline::new(history.net.iter().map(|rate| rate.rx as f32), history.net.capacity())
+                    .color(palette.accent),
+                format_rate(net.rx),
+            ))
+            .child(row(
+                "Up",
+                Sparkline::new(history.net.iter().map(|rate| rate.tx as f32), history.net.capacity())
+                    .color(palette.highlight),
+                format_rate(net.tx),
+            ))
+    }
+
+    /// Load average, frequency and one bar per core.
+    fn render_cpu_details(stats: &SystemStats, cx: &mut Context<Self>) -> impl IntoElement {
+        const CORE_BAR_HEIGHT: f32 = 24.0;
+        let palette = *ThemeService::palette(cx);
+
+        let mut summary = format!("Load {:.2} {:.2} {:.2}", stats.load.one, stats.load.five, stats.load.fifteen);
+        if let Some(mhz) = stats.cpu_mhz {
+            summary.push_str(&format!(" · {:.1} GHz", mhz as f32 / 1000.0));
+        }
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.foreground).child("CPU"))
+                    .child(div().text_xs().text_color(palette.foreground_dim).child(summary)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .items_end()
+                    .gap(px(2.0))
+                    .h(px(CORE_BAR_HEIGHT))
+                    .children(stats.cores.iter().map(|core| {
+                        div()
+                            .flex_1()
+                            .h(px((CORE_BAR_HEIGHT * core.usage as f32 / 100.0).max(1.0)))
+                            .rounded_sm()
+                            .bg(if core.usage >= 90 { palette.warning } else { palette.accent })
+                    })),
+            )
+    }
+
+    /// Usage, memory and temperature of each GPU, as far as its driver tells.
+    fn render_gpu_details(stats: &SystemStats, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .children(stats.gpus.iter().map(|gpu| {
+                let name = match gpu.vendor {
+                    GpuVendor::Amd => "AMD",
+                    GpuVendor::Intel => "Intel",
+                    GpuVendor::Nvidia => "NVIDIA",
+                };
+                let mut details = Vec::new();
+                if let Some(usage) = gpu.usage {
+                    details.push(format!("{usage}%"));
+                }
+                if let (Some(used), Some(total)) = (gpu.vram_used, gpu.vram_total) {
+                    details.push(format!("VRAM {} / {}", format_bytes(used), format_bytes(total)));
+                }
+                if let Some(temp) = gpu.temp {
+                    details.push(format!("{temp}°C"));
+                }
+
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.foreground).child(name))
+                    .child(div().text_xs().text_color(palette.foreground_dim).child(details.join(" · ")))
+            }))
+    }
+
+    /// Download/upload speeds in total and per interface.
+    fn render_network_stats(stats: &SystemStats, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let rates = |rx: u64, tx: u64, color: Hsla| {
+            div()
+                .flex()
+                .items_center()
+                .gap_3()
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_1()
+                        .child(Icon::new("arrow_downward").size(px(14.0)).text_color(palette.accent))
+                        .child(div().min_w(px(64.0)).text_xs().text_color(color).child(format_rate(rx))),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_1()
+                        .child(Icon::new("arrow_upward").size(px(14.0)).text_color(palette.highlight))
+                        .child(div().min_w(px(64.0)).text_xs().text_color(color).child(format_rate(tx))),
+                )
+        };
 
-        let sink_expanded = self.expanded_section == Some(PanelSection::AudioSink);
-        let source_expanded = self.expanded_section == Some(PanelSection::AudioSource);
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.foreground).child("Network"))
+                    .child(rates(stats.net.rx, stats.net.tx, palette.foreground)),
+            )
+            // Interfaces that never carried traffic are left out
+            .children(
+                stats
+                    .interfaces
+                    .iter()
+                    .filter(|interface| interface.rx_total + interface.tx_total > 0)
+                    .map(|interface| {
+                        div()
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .child(div().text_xs().text_color(palette.muted).child(interface.name.clone()))
+                            .child(rates(interface.rate.rx, interface.rate.tx, palette.foreground_dim))
+                    }),
+            )
+    }
 
-        let audio_state = self.audio.read(cx).state.clone();
+    /// Usage bar, free space and read/write speeds of each configured mount.
+    fn render_disk_stats(stats: &SystemStats, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let threshold = ConfigService::config(cx).system_monitor.low_disk_space;
 
         div()
             .flex()
             .flex_col()
-            .gap_3()
-            .p_3()
-            .bg(card_bg)
-            .rounded_md()
-            // Speaker / Output Volume
-            .child(
+            .gap_2()
+            .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.foreground).child("Disks"))
+            .children(stats.disks.iter().map(|disk| {
+                let usage = disk.usage();
+                let low = 100 - usage < threshold;
+                let free = format!("{} free of {}", format_bytes(disk.available), format_bytes(disk.total));
+                let io = format!("R {} · W {}", format_rate(disk.read), format_rate(disk.write));
+
                 div()
                     .flex()
                     .flex_col()
@@ -174,167 +1394,362 @@ impl Panel {
                             .flex()
                             .items_center()
                             .justify_between()
+                            .child(div().text_xs().text_color(palette.foreground).child(disk.mount.clone()))
                             .child(
                                 div()
-                                    .id("sink-mute-trigger")
-                                    .flex()
-                                    .items_center()
-                                    .gap_2()
-                                    .cursor_pointer()
-                                    .on_click(cx.listener(|this, _, _window, cx| {
-                                        this.audio.update(cx, |audio, cx| audio.toggle_sink_mute(cx));
-                                    }))
-                                    .child(Icon::new(if audio_state.sink_muted { "volume_off" } else { "volume_up" }).size(px(20.0)).text_color(if audio_state.sink_muted { rgb(0xbf616a) } else { accent }))
-                                    .child(
-                                        div()
-                                            .text_sm()
-                                            .font_weight(FontWeight::SEMIBOLD)
-                                            .text_color(text_main)
-                                            .child(if audio_state.sink_muted { "Output Muted" } else { "Output Volume" }),
-                                    ),
+                                    .text_xs()
+                                    .text_color(if low { palette.danger } else { palette.foreground_dim })
+                                    .child(free),
+                            ),
+                    )
+                    .child(
+                        div().h(px(6.0)).w_full().rounded_sm().bg(palette.border).child(
+                            div()
+                                .h_full()
+                                .w(relative(usage as f32 / 100.0))
+                                .rounded_sm()
+                                .bg(if low { palette.danger } else { palette.accent }),
+                        ),
+                    )
+                    .child(div().text_xs().text_color(palette.muted).child(io))
+            }))
+    }
+
+    /// VPN and WireGuard profiles saved in NetworkManager, each with its switch.
+    fn render_vpn_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let network = self.network.read(cx);
+        let vpns = network.state.vpns.clone();
+        let errors = network.errors.clone();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_3()
+            .bg(palette.surface)
+            .rounded_md()
+            .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.accent).child("VPN"))
+            .when(vpns.is_empty(), |this| {
+                this.child(div().text_xs().text_color(palette.muted).child("No VPN connection configured"))
+            })
+            .children(vpns.into_iter().map(|vpn| {
+                let (status, status_color) = match (errors.get(&vpn.id), vpn.state) {
+                    (Some(error), VpnState::Disconnected) => (error.clone(), palette.danger),
+                    (_, VpnState::Connected) => ("Connected".to_string(), palette.accent),
+                    (_, VpnState::Connecting) => ("Connecting...".to_string(), palette.muted),
+                    (_, VpnState::Disconnected) => (vpn.kind.label().to_string(), palette.foreground_dim),
+                };
+                let path = vpn.path.clone();
+
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .gap_2()
+                    .p_2()
+                    .bg(palette.background)
+                    .rounded_md()
+                    .child(
+                        div()
+                            .flex()
+                            .flex_1()
+                            .min_w_0()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                Icon::new(if vpn.state == VpnState::Connected { "vpn_lock" } else { "vpn_key" })
+                                    .size(px(16.0))
+                                    .text_color(if vpn.state == VpnState::Connected {
+                                        palette.accent
+                                    } else {
+                                        palette.foreground_dim
+                                    }),
                             )
                             .child(
                                 div()
                                     .flex()
-                                    .items_center()
-                                    .gap_2()
+                                    .flex_col()
+                                    .min_w_0()
                                     .child(
                                         div()
                                             .text_xs()
-                                            .text_color(text_muted)
-                                            .child(format!("{}%", audio_state.sink_volume)),
+                                            .overflow_hidden()
+                                            .whitespace_nowrap()
+                                            .text_color(palette.foreground)
+                                            .child(vpn.id.clone()),
                                     )
                                     .child(
-                                        Button::new("sink-details-btn")
-                                            .ghost()
-                                            .with_size(gpui_component::Size::Small)
-                                            .icon(Icon::new(if sink_expanded { "keyboard_arrow_up" } else { "keyboard_arrow_down" }).size(px(16.0)))
-                                            .on_click(cx.listener(|this, _, _window, cx| {
-                                                this.toggle_section(PanelSection::AudioSink, cx);
-                                            })),
+                                        div()
+                                            .text_xs()
+                                            .overflow_hidden()
+                                            .whitespace_nowrap()
+                                            .text_color(status_color)
+                                            .child(status),
                                     ),
                             ),
                     )
                     .child(
-                        div()
-                            .h(px(24.0))
-                            .flex()
-                            .items_center()
-                            .child(Slider::new(&self.volume_slider)),
-                    ),
-            )
-            .when(sink_expanded, |this| {
-                this.child(
-                    div()
-                        .flex()
-                        .flex_col()
-                        .gap_2()
-                        .p_2()
-                        .bg(rgb(0x2e3440))
-                        .rounded_md()
-                        .child(
-                            div()
-                                .flex()
-                                .items_center()
-                                .justify_between()
-                                .child(div().text_xs().text_color(text_main).child("Default Speaker (PulseAudio / PipeWire)"))
-                                .child(Icon::new("check").size(px(16.0)).text_color(accent)),
-                        ),
-                )
-            })
-            // Microphone Input
+                        Switch::new(SharedString::from(format!("vpn-{}", vpn.uuid)))
+                            .checked(vpn.state != VpnState::Disconnected)
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.network.update(cx, |net, cx| net.toggle_vpn(&path, cx));
+                            })),
+                    )
+            }))
+    }
+
+    /// Start/stop of the configured SSH unit (`[ssh]` in the configuration).
+    fn render_ssh_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let ssh = self.ssh.read(cx);
+        let unit = ssh.state.unit.clone();
+        let state = ssh.state.state;
+        let available = !matches!(state, UnitState::Unavailable | UnitState::NotFound);
+        let (status, status_color) = match &ssh.error {
+            Some(error) => (error.clone(), palette.danger),
+            None if state == UnitState::Active => (state.label().to_string(), palette.accent),
+            None if state == UnitState::Failed => (state.label().to_string(), palette.danger),
+            None => (state.label().to_string(), palette.muted),
+        };
+        let pending = ssh.pending;
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_3()
+            .bg(palette.surface)
+            .rounded_md()
+            .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.accent).child("SSH"))
             .child(
                 div()
                     .flex()
-                    .flex_col()
-                    .gap_1()
+                    .items_center()
+                    .justify_between()
+                    .gap_2()
+                    .p_2()
+                    .bg(palette.background)
+                    .rounded_md()
                     .child(
                         div()
                             .flex()
+                            .flex_1()
+                            .min_w_0()
                             .items_center()
-                            .justify_between()
+                            .gap_2()
                             .child(
-                                div()
-                                    .id("source-mute-trigger")
-                                    .flex()
-                                    .items_center()
-                                    .gap_2()
-                                    .cursor_pointer()
-                                    .on_click(cx.listener(|this, _, _window, cx| {
-                                        this.audio.update(cx, |audio, cx| audio.toggle_source_mute(cx));
-                                    }))
-                                    .child(Icon::new(if audio_state.source_muted { "mic_off" } else { "mic" }).size(px(20.0)).text_color(if audio_state.source_muted { rgb(0xbf616a) } else { accent }))
-                                    .child(
-                                        div()
-                                            .text_sm()
-                                            .font_weight(FontWeight::SEMIBOLD)
-                                            .text_color(text_main)
-                                            .child(if audio_state.source_muted { "Microphone Muted" } else { "Microphone" }),
-                                    ),
+                                Icon::new("terminal")
+                                    .size(px(16.0))
+                                    .text_color(if state.is_on() { palette.accent } else { palette.foreground_dim }),
                             )
                             .child(
                                 div()
                                     .flex()
-                                    .items_center()
-                                    .gap_2()
+                                    .flex_col()
+                                    .min_w_0()
                                     .child(
                                         div()
                                             .text_xs()
-                                            .text_color(text_muted)
-                                            .child(format!("{}%", audio_state.source_volume)),
+                                            .overflow_hidden()
+                                            .whitespace_nowrap()
+                                            .text_color(palette.foreground)
+                                            .child(unit),
                                     )
                                     .child(
-                                        Button::new("source-details-btn")
-                                            .ghost()
-                                            .with_size(gpui_component::Size::Small)
-                                            .icon(Icon::new(if source_expanded { "keyboard_arrow_up" } else { "keyboard_arrow_down" }).size(px(16.0)))
-                                            .on_click(cx.listener(|this, _, _window, cx| {
-                                                this.toggle_section(PanelSection::AudioSource, cx);
-                                            })),
+                                        div()
+                                            .text_xs()
+                                            .overflow_hidden()
+                                            .whitespace_nowrap()
+                                            .text_color(status_color)
+                                            .child(status),
                                     ),
                             ),
                     )
                     .child(
-                        div()
-                            .h(px(24.0))
-                            .flex()
-                            .items_center()
-                            .child(Slider::new(&self.mic_slider)),
+                        Switch::new("ssh-switch")
+                            .checked(state.is_on())
+                            .disabled(pending || !available)
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.ssh.update(cx, |ssh, cx| ssh.toggle(cx));
+                            })),
                     ),
             )
-            .when(source_expanded, |this| {
-                this.child(
+    }
+
+    /// Battery charge and estimate, AC adapter and the peripherals UPower reports.
+    fn render_power_section(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
+        let state = self.power.read(cx).state.clone();
+        let config = ConfigService::config(cx).power.clone();
+        let ac_online = state.ac_online();
+
+        let device_row = |device: &PowerDevice| {
+            let low = device.kind != DeviceKind::LinePower
+                && device.state.is_discharging()
+                && BatteryAlert::for_level(device.percentage, &config).is_some();
+            let detail = match device.kind {
+                DeviceKind::LinePower if device.online => "Connected".to_string(),
+                DeviceKind::LinePower => "Disconnected".to_string(),
+                _ => format!("{}% · {}", device.percentage, device.state.label()),
+            };
+
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .p_2()
+                .bg(palette.background)
+                .rounded_md()
+                .child(
+                    Icon::new(device.icon())
+                        .size(px(16.0))
+                        .text_color(if low { palette.danger } else { palette.foreground_dim }),
+                )
+                .child(
                     div()
                         .flex()
                         .flex_col()
-                        .gap_2()
-                        .p_2()
-                        .bg(rgb(0x2e3440))
-                        .rounded_md()
+                        .flex_1()
+                        .min_w_0()
+                        .child(
+                            div()
+                                .text_xs()
+                                .overflow_hidden()
+                                .whitespace_nowrap()
+                                .text_color(palette.foreground)
+                                .child(device.name.clone()),
+                        )
+                        .child(div().text_xs().text_color(palette.muted).child(device.kind.label())),
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(if low { palette.danger } else { palette.foreground_dim })
+                        .child(detail),
+                )
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .p_3()
+            .bg(palette.surface)
+            .rounded_md()
+            .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.accent).child("Power"))
+            .when_some(state.battery.clone(), |div_elem, battery| {
+                let low = battery.state.is_discharging() && BatteryAlert::for_level(battery.percentage, &config).is_some();
+                let bar_color = if low {
+                    palette.danger
+                } else if ac_online {
+                    palette.success
+                } else {
+                    palette.accent
+                };
+                let status = match battery.time_left() {
+                    Some(secs) if battery.state.is_discharging() => format!("{} left", format_time(secs)),
+                    Some(secs) => format!("Full in {}", format_time(secs)),
+                    None => battery.state.label().to_string(),
+                };
+
+                div_elem.child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .gap_1()
                         .child(
                             div()
                                 .flex()
                                 .items_center()
                                 .justify_between()
-                                .child(div().text_xs().text_color(text_main).child("Default Internal Microphone"))
-                                .child(Icon::new("check").size(px(16.0)).text_color(accent)),
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .child(Icon::new(battery.icon()).size(px(16.0)).text_color(bar_color))
+                                        .child(
+                                            div()
+                                                .text_xs()
+                                                .text_color(palette.foreground)
+                                                .child(format!("{}%", battery.percentage)),
+                                        ),
+                                )
+                                .child(div().text_xs().text_color(palette.foreground_dim).child(status)),
+                        )
+                        .child(
+                            div().h(px(6.0)).w_full().rounded_sm().bg(palette.border).child(
+                                div()
+                                    .h_full()
+                                    .w(relative(battery.percentage as f32 / 100.0))
+                                    .rounded_sm()
+                                    .bg(bar_color),
+                            ),
+                        )
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(palette.muted)
+                                .child(if ac_online { "On AC power" } else { "On battery" }),
                         ),
                 )
             })
+            .when(!state.available, |div_elem| {
+                div_elem.child(div().text_xs().text_color(palette.muted).child("UPower is not running"))
+            })
+            .children(state.devices.iter().map(device_row))
+    }
+
+    /// Percentage gauge; a secondary reading the system does not provide is left out rather than drawn as 0.
+    fn render_gauge(
+        id: &'static str,
+        label: &'static str,
+        value: u8,
+        secondary: Option<(&'static str, u8, &'static str, Hsla)>,
+        color: Hsla,
+    ) -> impl IntoElement {
+        let gauge = gpui_component::progress::CircularGauge::new(id)
+            .primary_value(value as f32)
+            .primary_label(label)
+            .primary_unit("%")
+            .primary_color(color)
+            .with_size(gpui_component::Size::Medium);
+        match secondary {
+            Some((label, value, unit, color)) => gauge
+                .secondary_value(value as f32)
+                .secondary_label(label)
+                .secondary_unit(unit)
+                .secondary_color(color),
+            None => gauge,
+        }
     }
 
     // ── 2. Quick Actions & Connectivity Section ──
     fn render_quick_actions(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = *ThemeService::palette(cx);
         let monitor_expanded = self.expanded_section == Some(PanelSection::Monitor);
         let bt_expanded = self.expanded_section == Some(PanelSection::Bluetooth);
         let net_expanded = self.expanded_section == Some(PanelSection::Network);
+        let vpn_expanded = self.expanded_section == Some(PanelSection::Vpn);
+        let ssh_expanded = self.expanded_section == Some(PanelSection::Ssh);
+        let power_expanded = self.expanded_section == Some(PanelSection::Power);
 
         let stats = self.system_monitor.read(cx).stats.clone();
+        let history = self.system_monitor.read(cx).history.clone();
         let bt_state = self.bluetooth.read(cx).state.clone();
-        let net_state = self.network.read(cx).state.clone();
 
         let bt_active = bt_state.powered;
-        let wifi_active = net_state.wifi_enabled;
+        let wifi_active = self.network.read(cx).state.wifi_enabled;
+        let vpn_active = self.network.read(cx).state.vpn_connected();
+        let gauge = |id, label, value, secondary| Self::render_gauge(id, label, value, secondary, palette.accent);
+        let power_state = &self.power.read(cx).state;
+        // Desktops without battery nor peripherals have nothing to show
+        let power_icon = match &power_state.battery {
+            Some(battery) => Some(battery.icon()),
+            None if !power_state.devices.is_empty() => Some("power"),
+            None => None,
+        };
 
         div()
             .flex()
@@ -383,26 +1798,47 @@ impl Panel {
                                 })),
                         ),
                     )
-                    // Proxy Toggle
+                    // VPN Toggle & Details
                     .child(
                         div().flex_1().child(
-                            Button::new("proxy-toggle")
+                            Button::new("vpn-toggle")
                                 .secondary()
                                 .with_size(gpui_component::Size::Medium)
-                                .icon(Icon::new("vpn_key").size(px(20.0)))
-                                .on_click(|_, _, _| {}),
+                                .icon(Icon::new(if vpn_active { "vpn_lock" } else { "vpn_key" }).size(px(20.0)))
+                                .selected(vpn_expanded)
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.toggle_section(PanelSection::Vpn, cx);
+                                })),
                         ),
                     )
-                    // SSH Toggle
+                    // SSH Toggle & Details
                     .child(
                         div().flex_1().child(
                             Button::new("ssh-toggle")
                                 .secondary()
                                 .with_size(gpui_component::Size::Medium)
                                 .icon(Icon::new("terminal").size(px(20.0)))
-                                .on_click(|_, _, _| {}),
+                                .selected(ssh_expanded)
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.toggle_section(PanelSection::Ssh, cx);
+                                })),
                         ),
-                    ),
+                    )
+                    // Power Toggle & Details
+                    .when_some(power_icon, |div_elem, icon| {
+                        div_elem.child(
+                            div().flex_1().child(
+                                Button::new("power-toggle")
+                                    .secondary()
+                                    .with_size(gpui_component::Size::Medium)
+                                    .icon(Icon::new(icon).size(px(20.0)))
+                                    .selected(power_expanded)
+                                    .on_click(cx.listener(|this, _, _window, cx| {
+                                        this.toggle_section(PanelSection::Power, cx);
+                                    })),
+                            ),
+                        )
+                    }),
             )
             // ── Expandable Details Panels ──
             .when(monitor_expanded, |div_elem| {
@@ -412,13 +1848,13 @@ impl Panel {
                         .flex_col()
                         .gap_3()
                         .p_3()
-                        .bg(rgb(0x3b4252))
+                        .bg(palette.surface)
                         .rounded_md()
                         .child(
                             div()
                                 .text_xs()
                                 .font_weight(FontWeight::BOLD)
-                                .text_color(rgb(0x88c0d0))
+                                .text_color(palette.accent)
                                 .child("System Monitor Stats"),
                         )
                         .child(
@@ -427,129 +1863,134 @@ impl Panel {
                                 .items_center()
                                 .justify_around()
                                 .p_2()
-                                .child(
-                                    gpui_component::progress::CircularGauge::new("cpu-gauge")
-                                        .primary_value(stats.cpu as f32)
-                                        .primary_label("CPU")
-                                        .primary_unit("%")
-                                        .primary_color(rgb(0x88c0d0))
-                                        .secondary_value(stats.cpu_temp.unwrap_or(45) as f32)
-                                        .secondary_label("Temp")
-                                        .secondary_unit("°C")
-                                        .secondary_color(rgb(0xebcb8b))
-                                        .with_size(gpui_component::Size::Medium),
-                                )
-                                .child(
-                                    gpui_component::progress::CircularGauge::new("gpu-gauge")
-                                        .primary_value(stats.gpu as f32)
-                                        .primary_label("GPU")
-                                        .primary_unit("%")
-                                        .primary_color(rgb(0x88c0d0))
-                                        .secondary_value(stats.gpu_temp.unwrap_or(50) as f32)
-                                        .secondary_label("Temp")
-                                        .secondary_unit("°C")
-                                        .secondary_color(rgb(0xebcb8b))
-                                        .with_size(gpui_component::Size::Medium),
-                                )
-                                .child(
-                                    gpui_component::progress::CircularGauge::new("ram-gauge")
-                                        .primary_value(stats.ram as f32)
-                                        .primary_label("RAM")
-                                        .primary_unit("%")
-                                        .primary_color(rgb(0x88c0d0))
-                                        .secondary_value(65.0)
-                                        .secondary_label("Disk")
-                                        .secondary_unit("%")
-                                        .secondary_color(rgb(0xb48ead))
-                                        .with_size(gpui_component::Size::Medium),
-                                ),
-                        ),
+                                .child(gauge(
+                                    "cpu-gauge",
+                                    "CPU",
+                                    stats.cpu,
+                                    stats.cpu_temp.map(|temp| ("Temp", temp, "°C", palette.warning)),
+                                ))
+                                // No GPU, or a driver without load reporting
+                                .when_some(stats.gpu, |div_elem, gpu| {
+                                    let temp = stats.gpu_temp.map(|temp| ("Temp", temp, "°C", palette.warning));
+                                    div_elem.child(gauge("gpu-gauge", "GPU", gpu, temp))
+                                })
+                                .child(gauge(
+                                    "ram-gauge",
+                                    "RAM",
+                                    stats.ram,
+                                    stats.disks.first().map(|disk| ("Disk", disk.usage(), "%", palette.highlight)),
+                                )),
+                        )
+                        .child(Self::render_history(&history, cx))
+                        .child(Self::render_cpu_details(&stats, cx))
+                        .child(Self::render_gpu_details(&stats, cx))
+                        .child(Self::render_network_stats(&stats, cx))
+                        .when(!stats.disks.is_empty(), |div_elem| div_elem.child(Self::render_disk_stats(&stats, cx))),
                 )
             })
             .when(bt_expanded, |div_elem| {
-                let devices = bt_state.devices.clone();
                 div_elem.child(
                     div()
                         .flex()
                         .flex_col()
                         .gap_2()
                         .p_3()
-                        .bg(rgb(0x3b4252))
+                        .bg(palette.surface)
                         .rounded_md()
                         .child(
                             div()
                                 .flex()
                                 .items_center()
                                 .justify_between()
-                                .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(rgb(0x88c0d0)).child("Bluetooth Devices"))
+                                .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.accent).child("Bluetooth Devices"))
                                 .child(
-                                    Switch::new("bt-switch")
-                                        .checked(bt_active)
-                                        .on_click(cx.listener(|this, _, _window, cx| {
-                                            this.bluetooth.update(cx, |bt, cx| bt.toggle_power(cx));
-                                        })),
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .child(
+                                            Button::new("bt-scan")
+                                                .ghost()
+                                                .with_size(gpui_component::Size::Small)
+                                                .icon(Icon::new(if bt_state.discovering { "bluetooth_searching" } else { "search" }).size(px(16.0)))
+                                                .selected(bt_state.discovering)
+                                                .disabled(!bt_active)
+                                                .on_click(cx.listener(|this, _, _window, cx| {
+                                                    this.bluetooth.update(cx, |bt, cx| {
+                                                        if bt.state.discovering {
+                                                            bt.stop_discovery(cx);
+                                                        } else {
+                                                            bt.start_discovery(cx);
+                                                        }
+                                                    });
+                                                })),
+                                        )
+                                        .child(
+                                            Switch::new("bt-switch")
+                                                .checked(bt_active)
+                                                .on_click(cx.listener(|this, _, _window, cx| {
+                                                    this.bluetooth.update(cx, |bt, cx| bt.toggle_power(cx));
+                                                })),
+                                        ),
                                 ),
                         )
-                        .children(devices.into_iter().map(|dev| {
-                            div()
-                                .flex()
-                                .items_center()
-                                .justify_between()
-                                .p_2()
-                                .bg(rgb(0x2e3440))
-                                .rounded_md()
-                                .child(div().text_xs().text_color(rgb(0xe5e9f0)).child(dev.name))
-                                .child(div().text_xs().text_color(rgb(0xa3be8c)).child(if dev.connected { "Connected" } else { "Paired" }))
-                        })),
+                        .child(self.render_bluetooth_devices(cx)),
                 )
             })
             .when(net_expanded, |div_elem| {
-                let networks = net_state.networks.clone();
                 div_elem.child(
                     div()
                         .flex()
                         .flex_col()
                         .gap_2()
                         .p_3()
-                        .bg(rgb(0x3b4252))
+                        .bg(palette.surface)
                         .rounded_md()
                         .child(
                             div()
                                 .flex()
                                 .items_center()
                                 .justify_between()
-                                .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(rgb(0x88c0d0)).child("Wi-Fi Networks"))
+                                .child(div().text_xs().font_weight(FontWeight::BOLD).text_color(palette.accent).child("Wi-Fi Networks"))
                                 .child(
-                                    Switch::new("wifi-switch")
-                                        .checked(wifi_active)
-                                        .on_click(cx.listener(|this, _, _window, cx| {
-                                            this.network.update(cx, |net, cx| net.toggle_wifi(cx));
-                                        })),
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .child(
+                                            Button::new("wifi-rescan")
+                                                .ghost()
+                                                .with_size(gpui_component::Size::Small)
+                                                .icon(Icon::new("refresh").size(px(16.0)))
+                                                .disabled(!wifi_active)
+                                                .on_click(cx.listener(|this, _, _window, cx| {
+                                                    this.network.read(cx).rescan(cx);
+                                                })),
+                                        )
+                                        .child(
+                                            Switch::new("wifi-switch")
+                                                .checked(wifi_active)
+                                                .on_click(cx.listener(|this, _, _window, cx| {
+                                                    this.network.update(cx, |net, cx| net.toggle_wifi(cx));
+                                                })),
+                                        ),
                                 ),
                         )
-                        .children(networks.into_iter().map(|net| {
-                            div()
-                                .flex()
-                                .items_center()
-                                .justify_between()
-                                .p_2()
-                                .bg(rgb(0x2e3440))
-                                .rounded_md()
-                                .child(div().text_xs().text_color(rgb(0xe5e9f0)).child(net.ssid))
-                                .when(net.active, |this| {
-                                    this.child(Icon::new("check").size(px(16.0)).text_color(rgb(0x88c0d0)))
-                                })
-                        })),
+                        .child(self.render_wifi_networks(cx)),
                 )
             })
+            .when(vpn_expanded, |div_elem| div_elem.child(self.render_vpn_section(cx)))
+            .when(ssh_expanded, |div_elem| div_elem.child(self.render_ssh_section(cx)))
+            .when(power_expanded, |div_elem| div_elem.child(self.render_power_section(cx)))
     }
 
     // ── 3. Notifications Section (matching notifications.rs) ──
     fn render_notifications_section(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
-        let card_bg = rgb(0x3b4252);
-        let text_main = rgb(0xe5e9f0);
-        let text_muted = rgb(0xd8dee9);
-        let accent = rgb(0x88c0d0);
+        let palette = *ThemeService::palette(cx);
+        let card_bg = palette.surface;
+        let text_main = palette.foreground;
+        let text_muted = palette.foreground_dim;
+        let accent = palette.accent;
 
         let notifs = self.notifications.read(cx).history.clone();
         let notif_count = notifs.len();
@@ -588,7 +2029,7 @@ impl Panel {
                                         .rounded_full()
                                         .text_xs()
                                         .font_weight(FontWeight::BOLD)
-                                        .text_color(rgb(0x2e3440))
+                                        .text_color(palette.background)
                                         .child(format!("{}", notif_count)),
                                 )
                             }),
@@ -630,7 +2071,7 @@ impl Panel {
                             .flex_col()
                             .gap_1()
                             .p_2()
-                            .bg(rgb(0x2e3440))
+                            .bg(palette.background)
                             .rounded_md()
                             .child(
                                 div()
@@ -675,15 +2116,31 @@ impl Panel {
 
 impl Render for Panel {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let bg = rgb(0x2e3440);
-        let hover_line = rgb(0x3b4252);
-        let frost_border = rgb(0x88c0d0).opacity(0.3);
+        let palette = *ThemeService::palette(cx);
+        let bg = palette.background;
+        let hover_line = palette.surface;
+        let frost_border = palette.border;
 
         let calendar = self
             .calendar
             .get_or_insert_with(|| cx.new(|cx| CalendarComponent::new(window, cx)))
             .clone();
 
+        if self.password_input.is_none() {
+            let input = cx.new(|cx| {
+                let mut state = InputState::new(window, cx).masked(true);
+                state.set_placeholder("Password", window, cx);
+                state
+            });
+            cx.subscribe_in(&input, window, |this, _, event: &InputEvent, window, cx| {
+                if let InputEvent::PressEnter { .. } = event {
+                    this.submit_password(window, cx);
+                }
+            })
+            .detach();
+            self.password_input = Some(input);
+        }
+
         div()
             .size_full()
             .flex()
@@ -724,6 +2181,9 @@ impl Render for Panel {
                     .flex_col()
                     .p_4()
                     .gap_4()
+                    // Bluetooth pairing and network secret requests, on top of everything else
+                    .children(self.render_pairing_prompt(cx))
+                    .children(self.render_secret_prompt(cx))
                     // 0. Calendar Component (above Audio Section)
                     .child(calendar)
                     .child(div().h(px(1.0)).bg(hover_line))
@@ -1,9 +1,19 @@
 use futures::channel::mpsc;
 use futures::StreamExt;
 use gpui::{App, AppContext, AsyncApp, Context, Entity, EventEmitter, Global};
+use std::process::Stdio;
 use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
+mod pactl;
+
+use pactl::Facility;
+
+/// Events arriving within this window are handled with a single refresh.
+const COALESCE: Duration = Duration::from_millis(30);
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AudioDevice {
     pub id: u32,
@@ -12,6 +22,29 @@ pub struct AudioDevice {
     pub is_default: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StreamKind {
+    /// Sink input: an application playing sound.
+    Playback,
+    /// Source output: an application recording.
+    Recording,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioStream {
+    pub id: u32,
+    pub kind: StreamKind,
+    pub app_name: String,
+    /// Freedesktop icon name, when the application sets one.
+    pub icon_name: Option<String>,
+    /// What is playing, e.g. the tab or track title.
+    pub media_name: String,
+    pub volume: u8,
+    pub muted: bool,
+    /// Id of the sink (playback) or source (recording) it is routed to.
+    pub device: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct AudioState {
     pub sink_volume: u8,
@@ -20,8 +53,12 @@ pub struct AudioState {
     pub source_volume: u8,
     pub source_muted: bool,
     pub source_name: String,
+    /// Every output, in server order.
     pub sinks: Vec<AudioDevice>,
+    /// Every input, monitors excluded.
     pub sources: Vec<AudioDevice>,
+    /// Application streams, playback first.
+    pub streams: Vec<AudioStream>,
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +66,8 @@ pub struct AudioStateChanged;
 
 pub struct AudioService {
     pub state: AudioState,
+    /// To the UI listener, for the state read back after a failed command.
+    tx: mpsc::UnboundedSender<AudioState>,
 }
 
 impl EventEmitter<AudioStateChanged> for AudioService {}
@@ -36,64 +75,77 @@ impl EventEmitter<AudioStateChanged> for AudioService {}
 struct GlobalAudioService(Entity<AudioService>);
 impl Global for GlobalAudioService {}
 
+/// Sends the current state, then a new one after every relevant event,
+/// until `pactl subscribe` exits.
+async fn watch(tx: &mpsc::UnboundedSender<AudioState>, last_state: &mut Option<AudioState>) -> anyhow::Result<()> {
+    let mut child = Command::new("pactl")
+        .arg("subscribe")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("pactl subscribe has no stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+
+    // Subscribed before the first read, so no change can slip in between
+    let mut refresh = true;
+    loop {
+        if refresh {
+            let state = pactl::fetch_state().await?;
+            if last_state.as_ref() != Some(&state) {
+                *last_state = Some(state.clone());
+                if tx.unbounded_send(state).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        let Some(line) = lines.next_line().await? else {
+            anyhow::bail!("pactl subscribe exited");
+        };
+        refresh = is_relevant(&line);
+
+        // A volume drag emits a burst of events
+        while let Ok(line) = tokio::time::timeout(COALESCE, lines.next_line()).await {
+            let Some(line) = line? else {
+                anyhow::bail!("pactl subscribe exited");
+            };
+            refresh |= is_relevant(&line);
+        }
+    }
+}
+
+fn is_relevant(line: &str) -> bool {
+    pactl::parse_event(line).is_some_and(|facility| facility != Facility::Other)
+}
+
 impl AudioService {
     pub fn global(cx: &App) -> Entity<Self> {
         cx.global::<GlobalAudioService>().0.clone()
     }
 
     pub fn init(cx: &mut App) -> Entity<Self> {
+        let (tx, mut rx) = mpsc::unbounded::<AudioState>();
+
         let service = cx.new(|_cx| Self {
             state: AudioState::default(),
+            tx: tx.clone(),
         });
 
         cx.set_global(GlobalAudioService(service.clone()));
 
-        let (tx, mut rx) = mpsc::unbounded::<AudioState>();
-
-        // Background worker to monitor Pipewire / wpctl / pactl events
+        // Background worker following `pactl subscribe`: the state is only
+        // re-read when PipeWire reports a device, stream or server change.
         gpui_tokio::Tokio::spawn(cx, async move {
-            let fetch_state = || async {
-                let mut state = AudioState::default();
-
-                // Get Sink Volume
-                if let Ok(out) = Command::new("wpctl").args(["get-volume", "@DEFAULT_AUDIO_SINK@"]).output().await {
-                    let s = String::from_utf8_lossy(&out.stdout);
-                    if let Some(vol_str) = s.split_whitespace().nth(1) {
-                        if let Ok(vol) = vol_str.parse::<f32>() {
-                            state.sink_volume = (vol * 100.0).round() as u8;
-                        }
-                    }
-                    state.sink_muted = s.contains("[MUTED]");
-                }
-
-                // Get Source Volume
-                if let Ok(out) = Command::new("wpctl").args(["get-volume", "@DEFAULT_AUDIO_SOURCE@"]).output().await {
-                    let s = String::from_utf8_lossy(&out.stdout);
-                    if let Some(vol_str) = s.split_whitespace().nth(1) {
-                        if let Ok(vol) = vol_str.parse::<f32>() {
-                            state.source_volume = (vol * 100.0).round() as u8;
-                        }
-                    }
-                    state.source_muted = s.contains("[MUTED]");
-                }
-
-                state
-            };
-
-            // Send initial audio state
-            let mut last_state = fetch_state().await;
-            let _ = tx.unbounded_send(last_state.clone());
-
-            // Polling loop (150ms) to guarantee volume updates across Pipewire/ALSA/NixOS
+            let mut last_state: Option<AudioState> = None;
             loop {
-                tokio::time::sleep(Duration::from_millis(150)).await;
-                let current_state = fetch_state().await;
-                if current_state != last_state {
-                    last_state = current_state.clone();
-                    if tx.unbounded_send(current_state).is_err() {
-                        break;
-                    }
+                if let Err(err) = watch(&tx, &mut last_state).await {
+                    log::warn!("Audio events: {err}");
                 }
+                if tx.is_closed() {
+                    break;
+                }
+                // pipewire-pulse restarted or is not running yet
+                tokio::time::sleep(RESTART_DELAY).await;
             }
         })
         .detach();
@@ -121,20 +173,33 @@ impl AudioService {
         service
     }
 
+    /// Runs `pactl` after the state was updated ahead of the server. When it
+    /// fails, no event will undo the update, so the state is read back.
+    fn run_pactl(&self, args: &[&str], cx: &mut Context<Self>) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let tx = self.tx.clone();
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            if pactl::run(&args).await {
+                return;
+            }
+            match pactl::fetch_state().await {
+                Ok(state) => {
+                    let _ = tx.unbounded_send(state);
+                }
+                Err(err) => log::warn!("Cannot read the audio state back: {err}"),
+            }
+        })
+        .detach();
+    }
+
     pub fn set_sink_volume(&mut self, volume_percent: u8, cx: &mut Context<Self>) {
         if self.state.sink_volume != volume_percent {
             self.state.sink_volume = volume_percent;
             cx.emit(AudioStateChanged);
             cx.notify();
         }
-        let vol_str = format!("{}%", volume_percent);
-        gpui_tokio::Tokio::spawn(cx, async move {
-            let _ = Command::new("wpctl")
-                .args(["set-volume", "@DEFAULT_AUDIO_SINK@", &vol_str])
-                .status()
-                .await;
-        })
-        .detach();
+        self.run_pactl(&["set-sink-volume", "@DEFAULT_SINK@", &format!("{volume_percent}%")], cx);
     }
 
     pub fn set_source_volume(&mut self, volume_percent: u8, cx: &mut Context<Self>) {
@@ -143,39 +208,95 @@ impl AudioService {
             cx.emit(AudioStateChanged);
             cx.notify();
         }
-        let vol_str = format!("{}%", volume_percent);
-        gpui_tokio::Tokio::spawn(cx, async move {
-            let _ = Command::new("wpctl")
-                .args(["set-volume", "@DEFAULT_AUDIO_SOURCE@", &vol_str])
-                .status()
-                .await;
-        })
-        .detach();
+        self.run_pactl(&["set-source-volume", "@DEFAULT_SOURCE@", &format!("{volume_percent}%")], cx);
     }
 
     pub fn toggle_sink_mute(&mut self, cx: &mut Context<Self>) {
         self.state.sink_muted = !self.state.sink_muted;
         cx.emit(AudioStateChanged);
         cx.notify();
-        gpui_tokio::Tokio::spawn(cx, async move {
-            let _ = Command::new("wpctl")
-                .args(["set-mute", "@DEFAULT_AUDIO_SINK@", "toggle"])
-                .status()
-                .await;
-        })
-        .detach();
+        self.run_pactl(&["set-sink-mute", "@DEFAULT_SINK@", "toggle"], cx);
     }
 
     pub fn toggle_source_mute(&mut self, cx: &mut Context<Self>) {
         self.state.source_muted = !self.state.source_muted;
         cx.emit(AudioStateChanged);
         cx.notify();
-        gpui_tokio::Tokio::spawn(cx, async move {
-            let _ = Command::new("wpctl")
-                .args(["set-mute", "@DEFAULT_AUDIO_SOURCE@", "toggle"])
-                .status()
-                .await;
-        })
-        .detach();
+        self.run_pactl(&["set-source-mute", "@DEFAULT_SOURCE@", "toggle"], cx);
+    }
+
+    /// Makes `name` the default output; the server moves the streams over.
+    pub fn set_default_sink(&mut self, name: &str, cx: &mut Context<Self>) {
+        if mark_default(&mut self.state.sinks, name) {
+            cx.emit(AudioStateChanged);
+            cx.notify();
+        }
+        self.run_pactl(&["set-default-sink", name], cx);
+    }
+
+    pub fn set_default_source(&mut self, name: &str, cx: &mut Context<Self>) {
+        if mark_default(&mut self.state.sources, name) {
+            cx.emit(AudioStateChanged);
+            cx.notify();
+        }
+        self.run_pactl(&["set-default-source", name], cx);
+    }
+
+    fn stream_mut(&mut self, kind: StreamKind, id: u32) -> Option<&mut AudioStream> {
+        self.state.streams.iter_mut().find(|stream| stream.kind == kind && stream.id == id)
+    }
+
+    pub fn set_stream_volume(&mut self, kind: StreamKind, id: u32, volume_percent: u8, cx: &mut Context<Self>) {
+        if let Some(stream) = self.stream_mut(kind, id) {
+            if stream.volume != volume_percent {
+                stream.volume = volume_percent;
+                cx.emit(AudioStateChanged);
+                cx.notify();
+            }
+        }
+        let command = format!("set-{}-volume", stream_object(kind));
+        self.run_pactl(&[&command, &id.to_string(), &format!("{volume_percent}%")], cx);
+    }
+
+    pub fn toggle_stream_mute(&mut self, kind: StreamKind, id: u32, cx: &mut Context<Self>) {
+        if let Some(stream) = self.stream_mut(kind, id) {
+            stream.muted = !stream.muted;
+            cx.emit(AudioStateChanged);
+            cx.notify();
+        }
+        let command = format!("set-{}-mute", stream_object(kind));
+        self.run_pactl(&[&command, &id.to_string(), "toggle"], cx);
+    }
+
+    /// Routes a stream to another sink (playback) or source (recording).
+    pub fn move_stream(&mut self, kind: StreamKind, id: u32, device: &AudioDevice, cx: &mut Context<Self>) {
+        if let Some(stream) = self.stream_mut(kind, id) {
+            if stream.device != device.id {
+                stream.device = device.id;
+                cx.emit(AudioStateChanged);
+                cx.notify();
+            }
+        }
+        let command = format!("move-{}", stream_object(kind));
+        self.run_pactl(&[&command, &id.to_string(), &device.name], cx);
+    }
+}
+
+/// `pactl` object type of a stream, as in `set-sink-input-volume`.
+fn stream_object(kind: StreamKind) -> &'static str {
+    match kind {
+        StreamKind::Playback => "sink-input",
+        StreamKind::Recording => "source-output",
+    }
+}
+
+/// Flags `name` as the default device. Returns whether anything changed.
+fn mark_default(devices: &mut [AudioDevice], name: &str) -> bool {
+    let mut changed = false;
+    for device in devices {
+        let is_default = device.name == name;
+        changed |= device.is_default != is_default;
+        device.is_default = is_default;
     }
+    changed
 }
@@ -0,0 +1,391 @@
+//! PulseAudio protocol client through `pactl`, served by pipewire-pulse on
+//! PipeWire systems. Parsing is kept apart from the processes so it can be
+//! fed recorded output.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+use crate::{AudioDevice, AudioState, AudioStream, StreamKind};
+
+/// Object kinds reported by `pactl subscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facility {
+    Sink,
+    Source,
+    SinkInput,
+    SourceOutput,
+    Server,
+    Card,
+    Other,
+}
+
+/// Parses a `pactl subscribe` line: `Event 'change' on sink #56`.
+pub fn parse_event(line: &str) -> Option<Facility> {
+    let rest = line.strip_prefix("Event '")?;
+    let (_kind, rest) = rest.split_once("' on ")?;
+    let facility = rest.rsplit_once(" #").map_or(rest, |(facility, _)| facility);
+    Some(match facility.trim() {
+        "sink" => Facility::Sink,
+        "source" => Facility::Source,
+        "sink-input" => Facility::SinkInput,
+        "source-output" => Facility::SourceOutput,
+        "server" => Facility::Server,
+        "card" => Facility::Card,
+        _ => Facility::Other,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServerInfo {
+    pub default_sink_name: Option<String>,
+    pub default_source_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChannelVolume {
+    pub value: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Device {
+    pub index: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub mute: bool,
+    pub volume: HashMap<String, ChannelVolume>,
+    #[serde(default)]
+    pub properties: HashMap<String, serde_json::Value>,
+}
+
+/// `PA_VOLUME_NORM`, i.e. 100%.
+const VOLUME_NORM: f64 = 65536.0;
+
+/// Average of the channel volumes, in percent (can exceed 100).
+pub fn volume_percent(volume: &HashMap<String, ChannelVolume>) -> u8 {
+    if volume.is_empty() {
+        return 0;
+    }
+    let sum: f64 = volume.values().map(|channel| channel.value as f64).sum();
+    let average = sum / volume.len() as f64;
+    (average * 100.0 / VOLUME_NORM).round().min(u8::MAX as f64) as u8
+}
+
+/// A sink input (playback) or source output (recording).
+#[derive(Debug, Deserialize)]
+pub struct Stream {
+    pub index: u32,
+    /// Target device index, `sink` for inputs, `source` for outputs.
+    #[serde(alias = "source")]
+    pub sink: u32,
+    pub mute: bool,
+    pub volume: HashMap<String, ChannelVolume>,
+    #[serde(default)]
+    pub properties: HashMap<String, serde_json::Value>,
+}
+
+impl Stream {
+    fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).and_then(|value| value.as_str())
+    }
+
+    /// Peak meters (pavucontrol, our own level monitors) are not user streams.
+    fn is_peak_detect(&self) -> bool {
+        self.property("stream.monitor") == Some("true") || self.property("media.name") == Some("Peak detect")
+    }
+
+    fn to_audio_stream(&self, kind: StreamKind) -> AudioStream {
+        let app_name = self
+            .property("application.name")
+            .or_else(|| self.property("application.process.binary"))
+            .or_else(|| self.property("media.name"))
+            .unwrap_or("Unknown");
+        AudioStream {
+            id: self.index,
+            kind,
+            app_name: app_name.to_string(),
+            icon_name: self.property("application.icon_name").map(str::to_string),
+            media_name: self.property("media.name").unwrap_or_default().to_string(),
+            volume: volume_percent(&self.volume),
+            muted: self.mute,
+            device: self.sink,
+        }
+    }
+}
+
+impl Device {
+    /// Monitor sources mirror a sink and are not real inputs.
+    pub fn is_monitor(&self) -> bool {
+        self.properties.get("device.class").and_then(|class| class.as_str()) == Some("monitor")
+            || self.name.ends_with(".monitor")
+    }
+
+    fn to_audio_device(&self, default: Option<&String>) -> AudioDevice {
+        AudioDevice {
+            id: self.index,
+            name: self.name.clone(),
+            description: if self.description.is_empty() {
+                self.name.clone()
+            } else {
+                self.description.clone()
+            },
+            is_default: Some(&self.name) == default,
+        }
+    }
+}
+
+/// Builds the state from `pactl --format=json info`, `list sinks`, `list sources`,
+/// `list sink-inputs` and `list source-outputs`.
+pub fn build_state(
+    info: &ServerInfo,
+    sinks: &[Device],
+    sources: &[Device],
+    sink_inputs: &[Stream],
+    source_outputs: &[Stream],
+) -> AudioState {
+    let mut state = AudioState::default();
+
+    let default_sink = sinks
+        .iter()
+        .find(|sink| Some(&sink.name) == info.default_sink_name.as_ref());
+    if let Some(sink) = default_sink {
+        state.sink_volume = volume_percent(&sink.volume);
+        state.sink_muted = sink.mute;
+        state.sink_name = sink.description.clone();
+    }
+
+    let default_source = sources
+        .iter()
+        .find(|source| Some(&source.name) == info.default_source_name.as_ref());
+    if let Some(source) = default_source {
+        state.source_volume = volume_percent(&source.volume);
+        state.source_muted = source.mute;
+        state.source_name = source.description.clone();
+    }
+
+    state.sinks = sinks
+        .iter()
+        .map(|sink| sink.to_audio_device(info.default_sink_name.as_ref()))
+        .collect();
+    state.sources = sources
+        .iter()
+        .filter(|source| !source.is_monitor())
+        .map(|source| source.to_audio_device(info.default_source_name.as_ref()))
+        .collect();
+
+    let playback = sink_inputs.iter().map(|stream| (stream, StreamKind::Playback));
+    let recording = source_outputs.iter().map(|stream| (stream, StreamKind::Recording));
+    state.streams = playback
+        .chain(recording)
+        .filter(|(stream, _)| !stream.is_peak_detect())
+        .map(|(stream, kind)| stream.to_audio_stream(kind))
+        .collect();
+
+    state
+}
+
+async fn query<T: serde::de::DeserializeOwned>(args: &[&str]) -> anyhow::Result<T> {
+    let output = Command::new("pactl").arg("--format=json").args(args).output().await?;
+    if !output.status.success() {
+        anyhow::bail!("pactl {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+pub async fn fetch_state() -> anyhow::Result<AudioState> {
+    let info: ServerInfo = query(&["info"]).await?;
+    let sinks: Vec<Device> = query(&["list", "sinks"]).await?;
+    let sources: Vec<Device> = query(&["list", "sources"]).await?;
+    let sink_inputs: Vec<Stream> = query(&["list", "sink-inputs"]).await?;
+    let source_outputs: Vec<Stream> = query(&["list", "source-outputs"]).await?;
+    Ok(build_state(&info, &sinks, &sources, &sink_inputs, &source_outputs))
+}
+
+/// Runs a `pactl` command, logging failures. Returns whether it succeeded.
+pub async fn run(args: &[&str]) -> bool {
+    match Command::new("pactl").args(args).output().await {
+        Ok(output) if !output.status.success() => {
+            log::warn!("pactl {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+            false
+        }
+        Ok(_) => true,
+        Err(err) => {
+            log::warn!("Cannot run pactl: {err}");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO: &str = r#"{"server_string":"/run/user/1000/pulse/native","library_protocol_version":35,
+        "server_protocol_version":35,"is_local":true,"client_index":87,"tile_size":65472,"user_name":"nia",
+        "host_name":"laptop","server_name":"PulseAudio (on PipeWire 1.2.7)","server_version":"15.0.0",
+        "default_sample_specification":"float32le 2ch 48000Hz","default_channel_map":"front-left,front-right",
+        "default_sink_name":"alsa_output.pci-0000_00_1f.3.analog-stereo",
+        "default_source_name":"alsa_input.pci-0000_00_1f.3.analog-stereo","cookie":"40a8:2bc4"}"#;
+
+    /// Speakers at 40 % and a muted HDMI output at 100 %.
+    const SINKS: &str = r#"[
+        {"index":56,"state":"RUNNING","name":"alsa_output.pci-0000_00_1f.3.analog-stereo",
+            "description":"Built-in Audio Analog Stereo","driver":"PipeWire","mute":false,
+            "volume":{"front-left":{"value":26214,"value_percent":"40%","db":"-23.88 dB"},
+                "front-right":{"value":26214,"value_percent":"40%","db":"-23.88 dB"}},
+            "balance":0,"base_volume":{"value":65536,"value_percent":"100%","db":"0.00 dB"},
+            "monitor_source":"alsa_output.pci-0000_00_1f.3.analog-stereo.monitor",
+            "properties":{"device.class":"sound","media.class":"Audio/Sink"}},
+        {"index":61,"state":"SUSPENDED","name":"alsa_output.pci-0000_01_00.1.hdmi-stereo",
+            "description":"HDMI / DisplayPort","driver":"PipeWire","mute":true,
+            "volume":{"front-left":{"value":65536,"value_percent":"100%","db":"0.00 dB"},
+                "front-right":{"value":65536,"value_percent":"100%","db":"0.00 dB"}},
+            "properties":{"device.class":"sound","media.class":"Audio/Sink"}}]"#;
+
+    /// The speakers' monitor and a muted microphone at 75 %.
+    const SOURCES: &str = r#"[
+        {"index":57,"state":"RUNNING","name":"alsa_output.pci-0000_00_1f.3.analog-stereo.monitor",
+            "description":"Monitor of Built-in Audio Analog Stereo","mute":false,
+            "volume":{"front-left":{"value":65536,"value_percent":"100%","db":"0.00 dB"}},
+            "properties":{"device.class":"monitor"}},
+        {"index":58,"state":"SUSPENDED","name":"alsa_input.pci-0000_00_1f.3.analog-stereo",
+            "description":"Built-in Audio Analog Stereo","mute":true,
+            "volume":{"front-left":{"value":49152,"value_percent":"75%","db":"-7.50 dB"},
+                "front-right":{"value":49152,"value_percent":"75%","db":"-7.50 dB"}},
+            "properties":{"device.class":"sound","media.class":"Audio/Source"}}]"#;
+
+    const SINK_INPUTS: &str = r#"[
+        {"index":112,"driver":"PipeWire","owner_module":"4294967295","client":"98","sink":56,
+            "corked":false,"mute":false,
+            "volume":{"front-left":{"value":65536,"value_percent":"100%","db":"0.00 dB"},
+                "front-right":{"value":32768,"value_percent":"50%","db":"-18.06 dB"}},
+            "properties":{"application.name":"Firefox","application.icon_name":"firefox",
+                "media.name":"Lofi Girl - YouTube","application.process.binary":"firefox"}},
+        {"index":115,"driver":"PipeWire","sink":61,"corked":true,"mute":true,
+            "volume":{"mono":{"value":98304,"value_percent":"150%","db":"10.57 dB"}},
+            "properties":{"application.process.binary":"mpv","media.name":"video.mkv"}}]"#;
+
+    /// pavucontrol's peak meter on the microphone, then a call.
+    const SOURCE_OUTPUTS: &str = r#"[
+        {"index":120,"driver":"PipeWire","source":57,"mute":false,
+            "volume":{"mono":{"value":65536,"value_percent":"100%","db":"0.00 dB"}},
+            "properties":{"application.name":"PulseAudio Volume Control","media.name":"Peak detect",
+                "stream.monitor":"true"}},
+        {"index":121,"driver":"PipeWire","source":58,"mute":false,
+            "volume":{"front-left":{"value":65536,"value_percent":"100%","db":"0.00 dB"}},
+            "properties":{"application.name":"WEBRTC VoiceEngine","media.name":"recStream"}}]"#;
+
+    #[test]
+    fn parses_subscribe_events() {
+        let cases = [
+            ("Event 'change' on sink #56", Some(Facility::Sink)),
+            ("Event 'new' on sink-input #112", Some(Facility::SinkInput)),
+            ("Event 'remove' on source-output #121", Some(Facility::SourceOutput)),
+            ("Event 'change' on source #58", Some(Facility::Source)),
+            ("Event 'change' on server #4294967295", Some(Facility::Server)),
+            ("Event 'change' on card #47", Some(Facility::Card)),
+            ("Event 'new' on client #98", Some(Facility::Other)),
+            ("Event 'change' on server", Some(Facility::Server)),
+            ("", None),
+            ("Connection failure: Connection refused", None),
+        ];
+        for (line, facility) in cases {
+            assert_eq!(parse_event(line), facility, "{line:?}");
+        }
+    }
+
+    fn volume(values: &[u32]) -> HashMap<String, ChannelVolume> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| (format!("channel-{i}"), ChannelVolume { value }))
+            .collect()
+    }
+
+    #[test]
+    fn volume_percents() {
+        let cases: [(&[u32], u8); 7] = [
+            (&[], 0),
+            (&[0, 0], 0),
+            (&[65536, 65536], 100),
+            // pactl's 40 % is 39.9994 %
+            (&[26214], 40),
+            (&[65536, 32768], 75),
+            (&[98304], 150),
+            (&[65536 * 3], 255),
+        ];
+        for (values, percent) in cases {
+            assert_eq!(volume_percent(&volume(values)), percent, "{values:?}");
+        }
+    }
+
+    #[test]
+    fn builds_state_from_pactl_output() {
+        let info: ServerInfo = serde_json::from_str(INFO).unwrap();
+        let sinks: Vec<Device> = serde_json::from_str(SINKS).unwrap();
+        let sources: Vec<Device> = serde_json::from_str(SOURCES).unwrap();
+        let sink_inputs: Vec<Stream> = serde_json::from_str(SINK_INPUTS).unwrap();
+        let source_outputs: Vec<Stream> = serde_json::from_str(SOURCE_OUTPUTS).unwrap();
+        let state = build_state(&info, &sinks, &sources, &sink_inputs, &source_outputs);
+
+        assert_eq!((state.sink_volume, state.sink_muted), (40, false));
+        assert_eq!(state.sink_name, "Built-in Audio Analog Stereo");
+        assert_eq!((state.source_volume, state.source_muted), (75, true));
+
+        let sinks: Vec<(u32, bool)> = state.sinks.iter().map(|sink| (sink.id, sink.is_default)).collect();
+        assert_eq!(sinks, [(56, true), (61, false)]);
+        // The monitor is left out
+        let sources: Vec<(u32, bool)> = state.sources.iter().map(|source| (source.id, source.is_default)).collect();
+        assert_eq!(sources, [(58, true)]);
+
+        // The peak meter is left out
+        assert_eq!(
+            state.streams,
+            [
+                AudioStream {
+                    id: 112,
+                    kind: StreamKind::Playback,
+                    app_name: "Firefox".to_string(),
+                    icon_name: Some("firefox".to_string()),
+                    media_name: "Lofi Girl - YouTube".to_string(),
+                    volume: 75,
+                    muted: false,
+                    device: 56,
+                },
+                AudioStream {
+                    id: 115,
+                    kind: StreamKind::Playback,
+                    app_name: "mpv".to_string(),
+                    icon_name: None,
+                    media_name: "video.mkv".to_string(),
+                    volume: 150,
+                    muted: true,
+                    device: 61,
+                },
+                AudioStream {
+                    id: 121,
+                    kind: StreamKind::Recording,
+                    app_name: "WEBRTC VoiceEngine".to_string(),
+                    icon_name: None,
+                    media_name: "recStream".to_string(),
+                    volume: 100,
+                    muted: false,
+                    device: 58,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_default_device() {
+        let info = ServerInfo {
+            default_sink_name: None,
+            default_source_name: None,
+        };
+        let sinks: Vec<Device> = serde_json::from_str(SINKS).unwrap();
+        let state = build_state(&info, &sinks, &[], &[], &[]);
+        assert_eq!((state.sink_volume, state.sink_name.as_str()), (0, ""));
+        assert!(state.sinks.iter().all(|sink| !sink.is_default));
+    }
+}
@@ -0,0 +1,173 @@
+//! `org.bluez.Agent1`: BlueZ asks the agent for PIN codes, passkeys and
+//! confirmations while pairing. Each request is forwarded to the UI, which
+//! answers through [`AgentReply`].
+
+use futures::channel::{mpsc, oneshot};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use zbus::{interface, proxy, Connection, DBusError};
+
+pub(crate) const AGENT_PATH: &str = "/org/nwidgets/bluetooth/agent";
+/// We can both show and type passkeys.
+const CAPABILITY: &str = "KeyboardDisplay";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentPromptKind {
+    /// Type the PIN code shown by the device (legacy pairing).
+    PinCode,
+    /// Type the 6-digit passkey shown by the device.
+    Passkey,
+    /// Type this PIN code on the device.
+    DisplayPinCode(String),
+    /// Type this passkey on the device, `entered` digits typed so far.
+    DisplayPasskey { passkey: u32, entered: u16 },
+    /// Check that the device shows the same passkey.
+    Confirmation(u32),
+    /// Accept pairing without a passkey.
+    Authorization,
+    /// Let a device use a service (profile UUID).
+    Service(String),
+}
+
+impl AgentPromptKind {
+    /// Display prompts need no answer; they last until pairing ends.
+    pub fn is_display(&self) -> bool {
+        matches!(self, AgentPromptKind::DisplayPinCode(_) | AgentPromptKind::DisplayPasskey { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentPrompt {
+    /// Object path of the device.
+    pub device: String,
+    pub kind: AgentPromptKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentReply {
+    Accept,
+    Reject,
+    PinCode(String),
+    Passkey(u32),
+}
+
+pub(crate) enum AgentEvent {
+    Prompt(AgentPrompt, Option<oneshot::Sender<AgentReply>>),
+    /// BlueZ gave up on the current request.
+    Cancel,
+}
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.bluez.Error")]
+enum AgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Rejected(String),
+    Canceled(String),
+}
+
+struct Agent {
+    tx: mpsc::UnboundedSender<AgentEvent>,
+}
+
+impl Agent {
+    fn show(&self, device: OwnedObjectPath, kind: AgentPromptKind) {
+        let prompt = AgentPrompt {
+            device: device.to_string(),
+            kind,
+        };
+        let _ = self.tx.unbounded_send(AgentEvent::Prompt(prompt, None));
+    }
+
+    async fn ask(&self, device: OwnedObjectPath, kind: AgentPromptKind) -> Result<AgentReply, AgentError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let prompt = AgentPrompt {
+            device: device.to_string(),
+            kind,
+        };
+        if self.tx.unbounded_send(AgentEvent::Prompt(prompt, Some(reply_tx))).is_err() {
+            return Err(AgentError::Rejected("no user interface".to_string()));
+        }
+        // The UI drops the sender when the prompt is cancelled or replaced
+        match reply_rx.await {
+            Ok(AgentReply::Reject) => Err(AgentError::Rejected("rejected by the user".to_string())),
+            Ok(reply) => Ok(reply),
+            Err(_) => Err(AgentError::Canceled("request cancelled".to_string())),
+        }
+    }
+
+    async fn confirm(&self, device: OwnedObjectPath, kind: AgentPromptKind) -> Result<(), AgentError> {
+        match self.ask(device, kind).await? {
+            AgentReply::Accept => Ok(()),
+            _ => Err(AgentError::Rejected("unexpected reply".to_string())),
+        }
+    }
+}
+
+#[interface(name = "org.bluez.Agent1")]
+impl Agent {
+    fn release(&self) {
+        log::info!("Bluetooth agent released by BlueZ");
+    }
+
+    async fn request_pin_code(&self, device: OwnedObjectPath) -> Result<String, AgentError> {
+        match self.ask(device, AgentPromptKind::PinCode).await? {
+            AgentReply::PinCode(pin) => Ok(pin),
+            _ => Err(AgentError::Rejected("unexpected reply".to_string())),
+        }
+    }
+
+    fn display_pin_code(&self, device: OwnedObjectPath, pincode: String) {
+        self.show(device, AgentPromptKind::DisplayPinCode(pincode));
+    }
+
+    async fn request_passkey(&self, device: OwnedObjectPath) -> Result<u32, AgentError> {
+        match self.ask(device, AgentPromptKind::Passkey).await? {
+            AgentReply::Passkey(passkey) => Ok(passkey),
+            _ => Err(AgentError::Rejected("unexpected reply".to_string())),
+        }
+    }
+
+    fn display_passkey(&self, device: OwnedObjectPath, passkey: u32, entered: u16) {
+        self.show(device, AgentPromptKind::DisplayPasskey { passkey, entered });
+    }
+
+    async fn request_confirmation(&self, device: OwnedObjectPath, passkey: u32) -> Result<(), AgentError> {
+        self.confirm(device, AgentPromptKind::Confirmation(passkey)).await
+    }
+
+    async fn request_authorization(&self, device: OwnedObjectPath) -> Result<(), AgentError> {
+        self.confirm(device, AgentPromptKind::Authorization).await
+    }
+
+    async fn authorize_service(&self, device: OwnedObjectPath, uuid: String) -> Result<(), AgentError> {
+        self.confirm(device, AgentPromptKind::Service(uuid)).await
+    }
+
+    fn cancel(&self) {
+        let _ = self.tx.unbounded_send(AgentEvent::Cancel);
+    }
+}
+
+#[proxy(
+    interface = "org.bluez.AgentManager1",
+    default_service = "org.bluez",
+    default_path = "/org/bluez"
+)]
+trait AgentManager1 {
+    fn register_agent(&self, agent: &ObjectPath<'_>, capability: &str) -> zbus::Result<()>;
+    fn request_default_agent(&self, agent: &ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+/// Exports the agent on `connection`; BlueZ only uses it once registered.
+pub(crate) async fn serve(connection: &Connection, tx: mpsc::UnboundedSender<AgentEvent>) -> zbus::Result<()> {
+    connection.object_server().at(AGENT_PATH, Agent { tx }).await?;
+    Ok(())
+}
+
+/// Registers the agent as the default one. Needed again after bluetoothd restarts.
+pub(crate) async fn register(connection: &Connection) -> zbus::Result<()> {
+    let manager = AgentManager1Proxy::new(connection).await?;
+    let path = ObjectPath::from_static_str_unchecked(AGENT_PATH);
+    manager.register_agent(&path, CAPABILITY).await?;
+    manager.request_default_agent(&path).await
+}
@@ -0,0 +1,317 @@
+//! `org.bluez` client: objects come from the ObjectManager and are kept up
+//! to date from InterfacesAdded/Removed and PropertiesChanged.
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use zbus::fdo::{DBusProxy, ObjectManagerProxy};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{proxy, Connection, MatchRule, MessageStream};
+
+use crate::{agent, BluetoothAction, BluetoothDevice, BluetoothState};
+
+const BLUEZ: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+trait Adapter1 {
+    fn start_discovery(&self) -> zbus::Result<()>;
+    fn stop_discovery(&self) -> zbus::Result<()>;
+    fn remove_device(&self, device: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn set_powered(&self, powered: bool) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+trait Device1 {
+    fn connect(&self) -> zbus::Result<()>;
+    fn disconnect(&self) -> zbus::Result<()>;
+    fn pair(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn set_trusted(&self, trusted: bool) -> zbus::Result<()>;
+}
+
+/// Every BlueZ object of interest, by path and interface.
+#[derive(Default)]
+pub struct BluezModel {
+    objects: BTreeMap<String, HashMap<String, Properties>>,
+}
+
+fn unwrap<'a>(value: &'a Value<'a>) -> &'a Value<'a> {
+    match value {
+        Value::Value(inner) => unwrap(inner),
+        value => value,
+    }
+}
+
+fn get_str(props: &Properties, key: &str) -> Option<String> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::Str(s)) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+fn get_bool(props: &Properties, key: &str) -> bool {
+    matches!(props.get(key).map(|value| unwrap(value)), Some(Value::Bool(true)))
+}
+
+fn get_u32(props: &Properties, key: &str) -> Option<u32> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::U32(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn get_u8(props: &Properties, key: &str) -> Option<u8> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::U8(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn get_i16(props: &Properties, key: &str) -> Option<i16> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::I16(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+impl BluezModel {
+    pub fn clear(&mut self) {
+        self.objects.clear();
+    }
+
+    pub fn add_interfaces(&mut self, path: &str, interfaces: HashMap<String, Properties>) {
+        let object = self.objects.entry(path.to_string()).or_default();
+        for (interface, props) in interfaces {
+            object.entry(interface).or_default().extend(props);
+        }
+    }
+
+    pub fn remove_interfaces(&mut self, path: &str, interfaces: &[String]) {
+        if let Some(object) = self.objects.get_mut(path) {
+            for interface in interfaces {
+                object.remove(interface);
+            }
+            if object.is_empty() {
+                self.objects.remove(path);
+            }
+        }
+    }
+
+    /// Applies a PropertiesChanged signal. Returns whether a tracked object changed.
+    pub fn change_properties(&mut self, path: &str, interface: &str, changed: Properties, invalidated: &[String]) -> bool {
+        let Some(props) = self.objects.get_mut(path).and_then(|object| object.get_mut(interface)) else {
+            return false;
+        };
+        props.extend(changed);
+        for key in invalidated {
+            props.remove(key);
+        }
+        true
+    }
+
+    /// Path of the first adapter (usually `/org/bluez/hci0`).
+    pub fn adapter_path(&self) -> Option<&str> {
+        self.objects
+            .iter()
+            .find(|(_, object)| object.contains_key(ADAPTER_INTERFACE))
+            .map(|(path, _)| path.as_str())
+    }
+
+    pub fn state(&self) -> BluetoothState {
+        let adapter_path = self.adapter_path();
+        let adapter = adapter_path.and_then(|path| self.objects.get(path)?.get(ADAPTER_INTERFACE));
+
+        let mut devices: Vec<BluetoothDevice> = self
+            .objects
+            .iter()
+            .filter_map(|(path, object)| {
+                let props = object.get(DEVICE_INTERFACE)?;
+                // Devices of other adapters are ignored
+                let adapter = props.get("Adapter").and_then(|value| match unwrap(value) {
+                    Value::ObjectPath(path) => Some(path.to_string()),
+                    _ => None,
+                });
+                if adapter.is_some() && adapter.as_deref() != adapter_path {
+                    return None;
+                }
+
+                let address = get_str(props, "Address").unwrap_or_default();
+                Some(BluetoothDevice {
+                    path: path.clone(),
+                    name: get_str(props, "Alias")
+                        .or_else(|| get_str(props, "Name"))
+                        .unwrap_or_else(|| address.clone()),
+                    address,
+                    connected: get_bool(props, "Connected"),
+                    paired: get_bool(props, "Paired"),
+                    trusted: get_bool(props, "Trusted"),
+                    icon: get_str(props, "Icon"),
+                    class: get_u32(props, "Class"),
+                    rssi: get_i16(props, "RSSI"),
+                    battery: object
+                        .get(BATTERY_INTERFACE)
+                        .and_then(|battery| get_u8(battery, "Percentage")),
+                })
+            })
+            .collect();
+
+        // Connected first, then paired, then by name
+        devices.sort_by(|a, b| {
+            (!a.connected, !a.paired, a.name.to_lowercase()).cmp(&(!b.connected, !b.paired, b.name.to_lowercase()))
+        });
+
+        BluetoothState {
+            adapter: adapter.and(adapter_path.map(str::to_string)),
+            powered: adapter.is_some_and(|props| get_bool(props, "Powered")),
+            discovering: adapter.is_some_and(|props| get_bool(props, "Discovering")),
+            devices,
+        }
+    }
+}
+
+fn into_strings<K: ToString>(map: HashMap<K, Properties>) -> HashMap<String, Properties> {
+    map.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+}
+
+async fn load(connection: &Connection, model: &mut BluezModel) -> zbus::Result<()> {
+    let manager = ObjectManagerProxy::builder(connection)
+        .destination(BLUEZ)?
+        .path("/")?
+        .build()
+        .await?;
+    model.clear();
+    for (path, interfaces) in manager.get_managed_objects().await? {
+        model.add_interfaces(path.as_str(), into_strings(interfaces));
+    }
+    Ok(())
+}
+
+async fn register_agent(connection: &Connection) {
+    if let Err(err) = agent::register(connection).await {
+        log::warn!("Cannot register the Bluetooth pairing agent: {err}");
+    }
+}
+
+/// Sends the adapter and device state after every change, until the
+/// connection is closed. bluetoothd restarts are followed.
+pub(crate) async fn watch(connection: Connection, tx: mpsc::UnboundedSender<BluetoothState>) -> zbus::Result<()> {
+    let manager = ObjectManagerProxy::builder(&connection)
+        .destination(BLUEZ)?
+        .path("/")?
+        .build()
+        .await?;
+    let dbus = DBusProxy::new(&connection).await?;
+
+    let mut added = manager.receive_interfaces_added().await?;
+    let mut removed = manager.receive_interfaces_removed().await?;
+    let mut owners = dbus.receive_name_owner_changed_with_args(&[(0, BLUEZ)]).await?;
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(BLUEZ)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .build();
+    let mut changes = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    let mut model = BluezModel::default();
+    // bluetoothd may not be running yet
+    match load(&connection, &mut model).await {
+        Ok(()) => register_agent(&connection).await,
+        Err(err) => log::debug!("BlueZ not available: {err}"),
+    }
+    let mut last_state = model.state();
+    if tx.unbounded_send(last_state.clone()).is_err() {
+        return Ok(());
+    }
+
+    loop {
+        tokio::select! {
+            Some(signal) = added.next() => {
+                let Ok((path, interfaces)) =
+                    signal.message().body().deserialize::<(OwnedObjectPath, HashMap<String, Properties>)>()
+                else {
+                    continue;
+                };
+                model.add_interfaces(path.as_str(), interfaces);
+            }
+            Some(signal) = removed.next() => {
+                let Ok(args) = signal.args() else { continue };
+                let interfaces: Vec<String> = args.interfaces().iter().map(|name| name.to_string()).collect();
+                model.remove_interfaces(args.object_path().as_str(), &interfaces);
+            }
+            Some(signal) = owners.next() => {
+                let Ok(args) = signal.args() else { continue };
+                model.clear();
+                if args.new_owner().is_some() {
+                    match load(&connection, &mut model).await {
+                        Ok(()) => register_agent(&connection).await,
+                        Err(err) => log::warn!("Cannot read BlueZ objects: {err}"),
+                    }
+                }
+            }
+            Some(message) = changes.next() => {
+                let Ok(message) = message else { continue };
+                let header = message.header();
+                let Some(path) = header.path() else { continue };
+                let Ok((interface, changed, invalidated)) =
+                    message.body().deserialize::<(String, Properties, Vec<String>)>()
+                else {
+                    continue;
+                };
+                if !model.change_properties(path.as_str(), &interface, changed, &invalidated) {
+                    continue;
+                }
+            }
+            else => break,
+        }
+
+        let state = model.state();
+        if state != last_state {
+            last_state = state.clone();
+            if tx.unbounded_send(state).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs `action` on the device at `path`, or on the adapter for discovery.
+pub(crate) async fn run_action(
+    connection: &Connection,
+    adapter: &str,
+    path: &str,
+    action: BluetoothAction,
+) -> zbus::Result<()> {
+    let adapter = Adapter1Proxy::builder(connection).path(adapter.to_string())?.build().await?;
+    match action {
+        BluetoothAction::StartDiscovery => return adapter.start_discovery().await,
+        BluetoothAction::StopDiscovery => return adapter.stop_discovery().await,
+        BluetoothAction::Remove => return adapter.remove_device(&ObjectPath::try_from(path)?).await,
+        _ => {}
+    }
+
+    let device = Device1Proxy::builder(connection).path(path.to_string())?.build().await?;
+    match action {
+        BluetoothAction::Connect => device.connect().await,
+        BluetoothAction::Disconnect => device.disconnect().await,
+        BluetoothAction::Pair => device.pair().await,
+        BluetoothAction::Trust => device.set_trusted(true).await,
+        BluetoothAction::Untrust => device.set_trusted(false).await,
+        BluetoothAction::Remove | BluetoothAction::StartDiscovery | BluetoothAction::StopDiscovery => Ok(()),
+    }
+}
+
+pub(crate) async fn set_powered(connection: &Connection, adapter: &str, powered: bool) -> zbus::Result<()> {
+    let adapter = Adapter1Proxy::builder(connection).path(adapter.to_string())?.build().await?;
+    adapter.set_powered(powered).await
+}
@@ -1,29 +1,111 @@
-use futures::channel::mpsc;
+use futures::channel::{mpsc, oneshot};
 use futures::StreamExt;
 use gpui::{App, AppContext, AsyncApp, Context, Entity, EventEmitter, Global};
-use tokio::process::Command;
+use nwidgets_service_config::ConfigService;
+use nwidgets_service_notification::NotificationService;
+use std::collections::{HashMap, HashSet};
+use zbus::Connection;
+
+mod agent;
+mod bluez;
+
+pub use agent::{AgentPrompt, AgentPromptKind, AgentReply};
+pub use bluez::BluezModel;
+
+use agent::AgentEvent;
+
+/// Points the battery must regain before a new low battery notification.
+const BATTERY_HYSTERESIS: u8 = 5;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BluetoothDevice {
+    /// D-Bus object path, e.g. `/org/bluez/hci0/dev_00_11_22_33_44_55`.
+    pub path: String,
     pub name: String,
     pub address: String,
     pub connected: bool,
+    pub paired: bool,
+    pub trusted: bool,
+    /// freedesktop icon name, e.g. `audio-headset`.
+    pub icon: Option<String>,
+    /// Class of Device.
+    pub class: Option<u32>,
+    /// Signal strength in dBm, only known while discovering or connected.
+    pub rssi: Option<i16>,
+    /// Battery percentage, for devices reporting it (`org.bluez.Battery1`).
+    pub battery: Option<u8>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct BluetoothState {
+    /// Object path of the adapter, `None` without adapter or bluetoothd.
+    pub adapter: Option<String>,
     pub powered: bool,
+    pub discovering: bool,
     pub devices: Vec<BluetoothDevice>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BluetoothAction {
+    Connect,
+    Disconnect,
+    Pair,
+    Trust,
+    Untrust,
+    /// Forgets the device (unpairs it).
+    Remove,
+    StartDiscovery,
+    StopDiscovery,
+}
+
+impl BluetoothAction {
+    /// Progress label, e.g. "Connecting...".
+    pub fn label(&self) -> &'static str {
+        match self {
+            BluetoothAction::Connect => "Connecting...",
+            BluetoothAction::Disconnect => "Disconnecting...",
+            BluetoothAction::Pair => "Pairing...",
+            BluetoothAction::Trust => "Trusting...",
+            BluetoothAction::Untrust => "Untrusting...",
+            BluetoothAction::Remove => "Removing...",
+            BluetoothAction::StartDiscovery => "Starting scan...",
+            BluetoothAction::StopDiscovery => "Stopping scan...",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BluetoothStateChanged;
 
+/// A pairing prompt appeared, changed or went away; see [`BluetoothService::prompt`].
+#[derive(Debug, Clone)]
+pub struct BluetoothPromptChanged;
+
+/// Progress of an action; `path` is the device, or the adapter for discovery.
+#[derive(Debug, Clone)]
+pub enum BluetoothActionEvent {
+    Started { path: String, action: BluetoothAction },
+    Finished { path: String, action: BluetoothAction },
+    Failed { path: String, action: BluetoothAction, error: String },
+}
+
 pub struct BluetoothService {
     pub state: BluetoothState,
+    /// Action running on each object path.
+    pub pending: HashMap<String, BluetoothAction>,
+    /// Last error of each object path, cleared by its next action.
+    pub errors: HashMap<String, String>,
+    /// Pending request of the pairing agent.
+    pub prompt: Option<AgentPrompt>,
+    prompt_reply: Option<oneshot::Sender<AgentReply>>,
+    /// Devices already notified about their low battery.
+    low_battery: HashSet<String>,
+    connection: Option<Connection>,
 }
 
 impl EventEmitter<BluetoothStateChanged> for BluetoothService {}
+impl EventEmitter<BluetoothActionEvent> for BluetoothService {}
+impl EventEmitter<BluetoothPromptChanged> for BluetoothService {}
 
 struct GlobalBluetoothService(Entity<BluetoothService>);
 impl Global for GlobalBluetoothService {}
@@ -36,38 +118,47 @@ impl BluetoothService {
     pub fn init(cx: &mut App) -> Entity<Self> {
         let service = cx.new(|_cx| Self {
             state: BluetoothState::default(),
+            pending: HashMap::new(),
+            errors: HashMap::new(),
+            prompt: None,
+            prompt_reply: None,
+            low_battery: HashSet::new(),
+            connection: None,
         });
 
         cx.set_global(GlobalBluetoothService(service.clone()));
 
         let (tx, mut rx) = mpsc::unbounded::<BluetoothState>();
+        let (connection_tx, mut connection_rx) = mpsc::unbounded::<Connection>();
+        let (agent_tx, mut agent_rx) = mpsc::unbounded::<AgentEvent>();
 
-        // Background worker to query bluetoothctl
+        // Background worker following BlueZ on the system bus
         gpui_tokio::Tokio::spawn(cx, async move {
-            let mut state = BluetoothState::default();
-
-            if let Ok(out) = Command::new("bluetoothctl").arg("show").output().await {
-                let s = String::from_utf8_lossy(&out.stdout);
-                state.powered = s.contains("Powered: yes");
+            let connection = match Connection::system().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    log::warn!("Bluetooth disabled, no system bus: {err}");
+                    return;
+                }
+            };
+            let _ = connection_tx.unbounded_send(connection.clone());
+            if let Err(err) = agent::serve(&connection, agent_tx).await {
+                log::warn!("Cannot export the Bluetooth pairing agent: {err}");
+            }
+            if let Err(err) = bluez::watch(connection, tx).await {
+                log::warn!("Bluetooth: {err}");
             }
+        })
+        .detach();
 
-            if let Ok(out) = Command::new("bluetoothctl").arg("devices").output().await {
-                let s = String::from_utf8_lossy(&out.stdout);
-                for line in s.lines() {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 3 && parts[0] == "Device" {
-                        let address = parts[1].to_string();
-                        let name = parts[2..].join(" ");
-                        state.devices.push(BluetoothDevice {
-                            name,
-                            address,
-                            connected: false,
-                        });
-                    }
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                if let Some(connection) = connection_rx.next().await {
+                    let _ = cx.update(|cx| service_entity.update(cx, |srv, _cx| srv.connection = Some(connection)));
                 }
             }
-
-            let _ = tx.unbounded_send(state);
         })
         .detach();
 
@@ -81,6 +172,15 @@ impl BluetoothService {
                         service_entity.update(cx, |srv, cx| {
                             if srv.state != new_state {
                                 srv.state = new_state;
+                                // Display prompts are not closed by BlueZ when pairing succeeds
+                                let paired = srv.prompt.as_ref().is_some_and(|prompt| {
+                                    prompt.kind.is_display()
+                                        && srv.state.devices.iter().any(|dev| dev.path == prompt.device && dev.paired)
+                                });
+                                if paired {
+                                    srv.close_prompt(cx);
+                                }
+                                srv.check_batteries(cx);
                                 cx.emit(BluetoothStateChanged);
                                 cx.notify();
                             }
@@ -91,19 +191,179 @@ impl BluetoothService {
         })
         .detach();
 
+        // Pairing agent requests
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(event) = agent_rx.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| match event {
+                            AgentEvent::Prompt(prompt, reply) => {
+                                // A new request replaces (and cancels) the previous one
+                                srv.prompt = Some(prompt);
+                                srv.prompt_reply = reply;
+                                cx.emit(BluetoothPromptChanged);
+                                cx.notify();
+                            }
+                            AgentEvent::Cancel => srv.close_prompt(cx),
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
         service
     }
 
     pub fn toggle_power(&mut self, cx: &mut Context<Self>) {
+        let (Some(connection), Some(adapter)) = (self.connection.clone(), self.state.adapter.clone()) else {
+            return;
+        };
         self.state.powered = !self.state.powered;
+        cx.emit(BluetoothStateChanged);
+        cx.notify();
+        let powered = self.state.powered;
+        // The adapter's PropertiesChanged confirms (or reverts) the new state
+        gpui_tokio::Tokio::spawn(cx, async move {
+            if let Err(err) = bluez::set_powered(&connection, &adapter, powered).await {
+                log::warn!("Bluetooth: cannot set power: {err}");
+            }
+        })
+        .detach();
+    }
+
+    /// Runs `action` on `path` unless an action is already running there.
+    /// The outcome is reported through [`BluetoothActionEvent`], the new
+    /// device state through [`BluetoothStateChanged`].
+    fn run(&mut self, path: String, action: BluetoothAction, cx: &mut Context<Self>) {
+        let (Some(connection), Some(adapter)) = (self.connection.clone(), self.state.adapter.clone()) else {
+            return;
+        };
+        if self.pending.contains_key(&path) {
+            return;
+        }
+        self.pending.insert(path.clone(), action);
+        self.errors.remove(&path);
+        cx.emit(BluetoothActionEvent::Started {
+            path: path.clone(),
+            action,
+        });
         cx.notify();
-        let target_state = if self.state.powered { "on" } else { "off" };
+
+        let (result_tx, result_rx) = oneshot::channel();
+        let target = path.clone();
         gpui_tokio::Tokio::spawn(cx, async move {
-            let _ = Command::new("bluetoothctl")
-                .args(["power", target_state])
-                .status()
-                .await;
+            let result = bluez::run_action(&connection, &adapter, &target, action).await;
+            let _ = result_tx.send(result.map_err(|err| err.to_string()));
         })
         .detach();
+
+        cx.spawn(async move |this, cx| {
+            let result = result_rx.await.unwrap_or_else(|_| Err("cancelled".to_string()));
+            let _ = this.update(cx, |srv, cx| {
+                srv.pending.remove(&path);
+                match result {
+                    Ok(()) => cx.emit(BluetoothActionEvent::Finished { path, action }),
+                    Err(error) => {
+                        log::warn!("Bluetooth {path}: {action:?} failed: {error}");
+                        srv.errors.insert(path.clone(), error.clone());
+                        cx.emit(BluetoothActionEvent::Failed { path, action, error });
+                    }
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    pub fn connect(&mut self, path: &str, cx: &mut Context<Self>) {
+        self.run(path.to_string(), BluetoothAction::Connect, cx);
+    }
+
+    pub fn disconnect(&mut self, path: &str, cx: &mut Context<Self>) {
+        self.run(path.to_string(), BluetoothAction::Disconnect, cx);
+    }
+
+    pub fn pair(&mut self, path: &str, cx: &mut Context<Self>) {
+        self.run(path.to_string(), BluetoothAction::Pair, cx);
+    }
+
+    pub fn trust(&mut self, path: &str, trusted: bool, cx: &mut Context<Self>) {
+        let action = if trusted { BluetoothAction::Trust } else { BluetoothAction::Untrust };
+        self.run(path.to_string(), action, cx);
+    }
+
+    pub fn remove(&mut self, path: &str, cx: &mut Context<Self>) {
+        self.run(path.to_string(), BluetoothAction::Remove, cx);
+    }
+
+    pub fn start_discovery(&mut self, cx: &mut Context<Self>) {
+        if let Some(adapter) = self.state.adapter.clone() {
+            self.run(adapter, BluetoothAction::StartDiscovery, cx);
+        }
+    }
+
+    pub fn stop_discovery(&mut self, cx: &mut Context<Self>) {
+        if let Some(adapter) = self.state.adapter.clone() {
+            self.run(adapter, BluetoothAction::StopDiscovery, cx);
+        }
+    }
+
+    /// Answers the current prompt. Display prompts only need [`AgentReply::Accept`] to close.
+    pub fn answer_prompt(&mut self, reply: AgentReply, cx: &mut Context<Self>) {
+        if let Some(reply_tx) = self.prompt_reply.take() {
+            let _ = reply_tx.send(reply);
+        }
+        self.close_prompt(cx);
+    }
+
+    fn close_prompt(&mut self, cx: &mut Context<Self>) {
+        // Dropping an unanswered sender cancels the request
+        self.prompt_reply = None;
+        if self.prompt.take().is_some() {
+            cx.emit(BluetoothPromptChanged);
+            cx.notify();
+        }
+    }
+
+    /// Notifies once when a connected device drops below `bluetooth.low_battery`.
+    fn check_batteries(&mut self, cx: &mut Context<Self>) {
+        let threshold = ConfigService::config(cx).bluetooth.low_battery;
+        let mut low = Vec::new();
+        for dev in &self.state.devices {
+            let Some(battery) = dev.battery.filter(|_| dev.connected) else {
+                self.low_battery.remove(&dev.path);
+                continue;
+            };
+            if battery < threshold {
+                if self.low_battery.insert(dev.path.clone()) {
+                    low.push((dev.name.clone(), battery));
+                }
+            } else if battery >= threshold.saturating_add(BATTERY_HYSTERESIS) {
+                // Charged again: warn on the next drop
+                self.low_battery.remove(&dev.path);
+            }
+        }
+        // Forget devices BlueZ no longer knows
+        let devices = &self.state.devices;
+        self.low_battery.retain(|path| devices.iter().any(|dev| &dev.path == path));
+
+        if low.is_empty() {
+            return;
+        }
+        let notifications = NotificationService::init(cx);
+        for (name, battery) in low {
+            notifications.update(cx, |notifications, cx| {
+                notifications.notify(
+                    format!("{name}: low battery"),
+                    format!("{battery}% remaining"),
+                    "battery-caution",
+                    1,
+                    cx,
+                );
+            });
+        }
     }
 }
@@ -0,0 +1,60 @@
+//! Fake compositor sockets for the backend tests.
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::{CompositorBackend, CompositorError, CompositorState};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory for the fake sockets under the system temp dir, removed on drop.
+pub struct SocketDir {
+    path: PathBuf,
+}
+
+impl SocketDir {
+    pub fn new() -> Self {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("nwidgets-compositor-{}-{n}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn socket(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for SocketDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Listens on `path` and runs `handler` on every connection, each in its own task.
+pub fn serve<F, Fut>(path: &Path, handler: F)
+where
+    F: Fn(UnixStream) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let listener = UnixListener::bind(path).unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handler(stream));
+        }
+    });
+}
+
+/// Runs `backend.watch` until the fake closes the event stream, returns its
+/// result and every state sent.
+pub async fn watch(backend: &dyn CompositorBackend) -> (Result<(), CompositorError>, Vec<CompositorState>) {
+    let (tx, rx) = mpsc::unbounded();
+    let result = backend.watch(tx).await;
+    (result, rx.collect().await)
+}
@@ -0,0 +1,541 @@
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+use crate::{
+    index_workspaces, sort_outputs, ActiveWindow, CompositorAction, CompositorBackend, CompositorError,
+    CompositorState, LogicalOutput, Output, Window, Workspace,
+};
+
+/// Events after which the state is queried again.
+const REFRESH_EVENTS: &[&str] = &[
+    "workspacev2",
+    "focusedmonv2",
+    "activewindowv2",
+    "openwindow",
+    "closewindow",
+    "movewindowv2",
+    "createworkspacev2",
+    "destroyworkspacev2",
+    "renameworkspace",
+    "monitoraddedv2",
+    "monitorremoved",
+    "urgent",
+];
+
+/// Hyprland: events from `.socket2.sock`, queries and dispatchers through
+/// the `hyprctl` socket `.socket.sock`.
+pub struct HyprlandBackend {
+    events: PathBuf,
+    requests: PathBuf,
+}
+
+impl HyprlandBackend {
+    pub fn new(events: impl Into<PathBuf>, requests: impl Into<PathBuf>) -> Self {
+        Self {
+            events: events.into(),
+            requests: requests.into(),
+        }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+
+        // Hyprland >= 0.40 uses $XDG_RUNTIME_DIR/hypr, older versions /tmp/hypr
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+            .map(|dir| Path::new(&dir).join("hypr").join(&signature))
+            .ok()
+            .filter(|dir| dir.exists())
+            .unwrap_or_else(|| Path::new("/tmp/hypr").join(&signature));
+
+        Some(Self::new(runtime_dir.join(".socket2.sock"), runtime_dir.join(".socket.sock")))
+    }
+}
+
+#[derive(Deserialize)]
+struct HyprWorkspaceRef {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct HyprActiveWindow {
+    address: Option<String>,
+    title: Option<String>,
+    class: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HyprWorkspace {
+    id: i64,
+    name: String,
+    monitor: Option<String>,
+    windows: usize,
+}
+
+#[derive(Deserialize)]
+struct HyprMonitor {
+    name: String,
+    focused: bool,
+    #[serde(rename = "activeWorkspace")]
+    active_workspace: HyprWorkspaceRef,
+    x: i32,
+    y: i32,
+    /// Physical size, before scale and rotation.
+    width: u32,
+    height: u32,
+    scale: f64,
+    /// wl_output transform, odd values are rotated by 90 or 270 degrees.
+    transform: u32,
+    #[serde(default)]
+    disabled: bool,
+}
+
+impl HyprMonitor {
+    fn output(&self) -> Output {
+        let (width, height) = if self.transform % 2 == 1 {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        Output {
+            name: self.name.clone(),
+            logical: (!self.disabled).then(|| LogicalOutput {
+                x: self.x,
+                y: self.y,
+                width: (width as f64 / self.scale).round() as u32,
+                height: (height as f64 / self.scale).round() as u32,
+                scale: self.scale,
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HyprClient {
+    address: String,
+    workspace: HyprWorkspaceRef,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    pid: i32,
+    #[serde(default)]
+    floating: bool,
+}
+
+#[derive(Deserialize)]
+struct HyprDevices {
+    keyboards: Vec<HyprKeyboard>,
+}
+
+#[derive(Deserialize)]
+struct HyprKeyboard {
+    name: String,
+    active_keymap: String,
+    #[serde(default)]
+    main: bool,
+}
+
+/// The keyboard Hyprland reports the layout of, as (name, layout). Virtual
+/// keyboards, e.g. from an on-screen keyboard or a remote desktop, are not it.
+async fn main_keyboard(socket: &Path) -> Result<Option<(String, String)>, CompositorError> {
+    let devices: HyprDevices = query(socket, "j/devices").await?;
+    let keyboard = devices.keyboards.iter().find(|kb| kb.main).or(devices.keyboards.first());
+    Ok(keyboard.map(|kb| (kb.name.clone(), kb.active_keymap.clone())))
+}
+
+/// Window addresses are printed as hex, with or without `0x`.
+fn parse_address(address: &str) -> Option<u64> {
+    u64::from_str_radix(address.trim().trim_start_matches("0x"), 16).ok()
+}
+
+async fn query_raw(socket: &Path, command: &str) -> Result<String, CompositorError> {
+    let mut stream = UnixStream::connect(socket).await?;
+    stream.write_all(command.as_bytes()).await?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).await?;
+    Ok(reply)
+}
+
+async fn query<T: DeserializeOwned>(socket: &Path, command: &str) -> Result<T, CompositorError> {
+    let reply = query_raw(socket, command).await?;
+    serde_json::from_str(&reply).map_err(|_| CompositorError::Protocol(reply.trim().to_string()))
+}
+
+/// `layout` is tracked from the events, it is not part of the queried state.
+async fn fetch_state(
+    socket: &Path,
+    urgent: &HashSet<u64>,
+    layout: Option<String>,
+) -> Result<CompositorState, CompositorError> {
+    let active: HyprActiveWindow = query(socket, "j/activewindow").await?;
+    let mut workspaces: Vec<HyprWorkspace> = query(socket, "j/workspaces").await?;
+    let monitors: Vec<HyprMonitor> = query(socket, "j/monitors").await?;
+    let clients: Vec<HyprClient> = query(socket, "j/clients").await?;
+
+    // Special workspaces (scratchpads) have negative ids
+    workspaces.retain(|ws| ws.id > 0);
+    workspaces.sort_by_key(|ws| ws.id);
+
+    let urgent_workspaces: HashSet<i64> = clients
+        .iter()
+        .filter(|client| parse_address(&client.address).is_some_and(|address| urgent.contains(&address)))
+        .map(|client| client.workspace.id)
+        .collect();
+
+    let mut workspaces: Vec<Workspace> = workspaces
+        .into_iter()
+        .map(|ws| {
+            let monitor = monitors.iter().find(|m| Some(&m.name) == ws.monitor.as_ref());
+            let is_active = monitor.is_some_and(|m| m.active_workspace.id == ws.id);
+            Workspace {
+                id: ws.id as u64,
+                idx: 0,
+                name: ws.name,
+                output: ws.monitor,
+                is_active,
+                is_focused: is_active && monitor.is_some_and(|m| m.focused),
+                is_urgent: urgent_workspaces.contains(&ws.id),
+                window_count: ws.windows,
+            }
+        })
+        .collect();
+    index_workspaces(&mut workspaces);
+
+    let active_id = active.address.as_deref().and_then(parse_address);
+    let mut windows: Vec<Window> = clients
+        .into_iter()
+        .filter_map(|client| {
+            let id = parse_address(&client.address)?;
+            Some(Window {
+                id,
+                title: client.title,
+                app_id: client.class,
+                pid: (client.pid > 0).then_some(client.pid),
+                workspace_id: (client.workspace.id > 0).then_some(client.workspace.id as u64),
+                is_focused: Some(id) == active_id,
+                is_floating: client.floating,
+                is_urgent: urgent.contains(&id),
+            })
+        })
+        .collect();
+    windows.sort_by_key(|w| w.id);
+
+    Ok(CompositorState {
+        active_window: ActiveWindow {
+            id: active_id,
+            title: active.title.unwrap_or_default(),
+            app_id: active.class.unwrap_or_default(),
+        },
+        workspaces,
+        windows,
+        keyboard_layout: layout,
+        outputs: sort_outputs(monitors.iter().map(HyprMonitor::output).collect()),
+    })
+}
+
+fn dispatch_command(action: &CompositorAction) -> String {
+    match *action {
+        CompositorAction::FocusWorkspace(id) => format!("dispatch workspace {id}"),
+        CompositorAction::FocusWindow(id) => format!("dispatch focuswindow address:0x{id:x}"),
+        CompositorAction::CloseWindow(id) => format!("dispatch closewindow address:0x{id:x}"),
+        // `fullscreen` only applies to the focused window
+        CompositorAction::ToggleFullscreen(id) => {
+            format!("[[BATCH]]dispatch focuswindow address:0x{id:x};dispatch fullscreen 0")
+        }
+        CompositorAction::MoveWindowToWorkspace { window, workspace } => {
+            format!("dispatch movetoworkspacesilent {workspace},address:0x{window:x}")
+        }
+    }
+}
+
+impl CompositorBackend for HyprlandBackend {
+    fn name(&self) -> &'static str {
+        "hyprland"
+    }
+
+    fn watch(&self, tx: mpsc::UnboundedSender<CompositorState>) -> BoxFuture<'static, Result<(), CompositorError>> {
+        let events = self.events.clone();
+        let requests = self.requests.clone();
+        async move {
+            let stream = UnixStream::connect(&events).await?;
+            let mut urgent: HashSet<u64> = HashSet::new();
+            let keyboard = main_keyboard(&requests).await?;
+            let mut layout = keyboard.as_ref().map(|(_, layout)| layout.clone());
+
+            let mut last_state = fetch_state(&requests, &urgent, layout.clone()).await?;
+            if tx.unbounded_send(last_state.clone()).is_err() {
+                return Ok(());
+            }
+
+            let mut lines = BufReader::new(stream).lines();
+            while let Some(line) = lines.next_line().await? {
+                let Some((event, data)) = line.split_once(">>") else {
+                    continue;
+                };
+
+                match event {
+                    // `activelayout>>KEYBOARD,LAYOUT`, sent for every keyboard
+                    "activelayout" => {
+                        if let Some((device, name)) = data.split_once(',')
+                            && keyboard.as_ref().is_none_or(|(main, _)| main == device)
+                        {
+                            layout = Some(name.to_string());
+                        }
+                    }
+                    "urgent" => {
+                        if let Some(address) = parse_address(data) {
+                            urgent.insert(address);
+                        }
+                    }
+                    // `activewindowv2>>ADDRESS`: a focused window is no longer urgent
+                    "activewindowv2" => {
+                        if let Some(address) = parse_address(data) {
+                            urgent.remove(&address);
+                        }
+                    }
+                    "closewindow" => {
+                        if let Some(address) = parse_address(data) {
+                            urgent.remove(&address);
+                        }
+                    }
+                    _ => {}
+                }
+
+                let state = if event == "activelayout" {
+                    CompositorState {
+                        keyboard_layout: layout.clone(),
+                        ..last_state.clone()
+                    }
+                } else if REFRESH_EVENTS.contains(&event) {
+                    fetch_state(&requests, &urgent, layout.clone()).await?
+                } else {
+                    continue;
+                };
+
+                if state != last_state {
+                    last_state = state.clone();
+                    if tx.unbounded_send(state).is_err() {
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn execute(&self, action: CompositorAction) -> BoxFuture<'static, Result<(), CompositorError>> {
+        let requests = self.requests.clone();
+        async move {
+            let reply = query_raw(&requests, &dispatch_command(&action)).await?;
+            // Batches answer one `ok` per command
+            if reply.split_whitespace().all(|word| word == "ok") {
+                Ok(())
+            } else {
+                Err(CompositorError::Rejected(reply.trim().to_string()))
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::{self, SocketDir};
+    use std::sync::{Arc, Mutex};
+
+    const ACTIVE_WINDOW: &str = r#"{"address":"0x1234","title":"~","class":"foot","workspace":{"id":1,"name":"1"}}"#;
+
+    /// The special workspace has a negative id and is left out.
+    const WORKSPACES: &str = r#"[
+        {"id":3,"name":"3","monitor":"HDMI-A-1","windows":0},
+        {"id":-98,"name":"special:magic","monitor":"DP-1","windows":0},
+        {"id":2,"name":"2","monitor":"DP-1","windows":1},
+        {"id":1,"name":"1","monitor":"DP-1","windows":1}]"#;
+
+    /// DP-1 is scaled by 1.5, HDMI-A-1 rotated by 90 degrees.
+    const MONITORS: &str = r#"[
+        {"id":0,"name":"DP-1","x":1920,"y":0,"width":3840,"height":2160,"scale":1.50,"transform":0,
+            "focused":true,"activeWorkspace":{"id":1,"name":"1"},"disabled":false},
+        {"id":1,"name":"HDMI-A-1","x":0,"y":0,"width":1920,"height":1080,"scale":1.00,"transform":1,
+            "focused":false,"activeWorkspace":{"id":3,"name":"3"},"disabled":false},
+        {"id":2,"name":"eDP-1","x":0,"y":0,"width":1920,"height":1200,"scale":1.00,"transform":0,
+            "focused":false,"activeWorkspace":{"id":-1,"name":""},"disabled":true}]"#;
+
+    /// The scratchpad window lives on a special workspace.
+    const CLIENTS: &str = r#"[
+        {"address":"0x5678","workspace":{"id":2,"name":"2"},"floating":false,"class":"firefox","title":"Mozilla Firefox","pid":201},
+        {"address":"0x9abc","workspace":{"id":-98,"name":"special:magic"},"floating":true,"class":"pavucontrol","title":"Volume Control","pid":202},
+        {"address":"0x1234","workspace":{"id":1,"name":"1"},"floating":false,"class":"foot","title":"~","pid":200}]"#;
+
+    /// The virtual keyboard comes first and is not the main one.
+    const DEVICES: &str = r#"{"mice":[],"keyboards":[
+        {"name":"wvkbd-virtual-keyboard","active_keymap":"English (US)","main":false},
+        {"name":"power-button","active_keymap":"English (US)","main":false},
+        {"name":"at-translated-set-2-keyboard","active_keymap":"English (US)","main":true}]}"#;
+
+    const EVENTS: &str = "workspacev2>>2,2\n\
+        urgent>>5678\n\
+        configreloaded>>\n\
+        activelayout>>wvkbd-virtual-keyboard,Russian\n\
+        activelayout>>at-translated-set-2-keyboard,German\n\
+        not an event\n\
+        activewindowv2>>5678\n";
+
+    /// Serves both Hyprland sockets and records the dispatched commands.
+    fn serve_hyprland(dir: &SocketDir, commands: Arc<Mutex<Vec<String>>>) -> HyprlandBackend {
+        let events = dir.socket(".socket2.sock");
+        let requests = dir.socket(".socket.sock");
+
+        fake::serve(&events, |mut stream| async move {
+            stream.write_all(EVENTS.as_bytes()).await.unwrap();
+        });
+        fake::serve(&requests, move |mut stream| {
+            let commands = commands.clone();
+            async move {
+                let mut buf = [0u8; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let command = String::from_utf8_lossy(&buf[..len]).into_owned();
+
+                let reply = match command.as_str() {
+                    "j/activewindow" => ACTIVE_WINDOW.to_string(),
+                    "j/workspaces" => WORKSPACES.to_string(),
+                    "j/monitors" => MONITORS.to_string(),
+                    "j/clients" => CLIENTS.to_string(),
+                    "j/devices" => DEVICES.to_string(),
+                    _ if command.contains("0x404") => "No such window found".to_string(),
+                    _ if command.starts_with("[[BATCH]]") => "ok\n\nok".to_string(),
+                    _ => "ok".to_string(),
+                };
+                if !command.starts_with("j/") {
+                    commands.lock().unwrap().push(command);
+                }
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        HyprlandBackend::new(events, requests)
+    }
+
+    #[tokio::test]
+    async fn replays_event_stream() {
+        let dir = SocketDir::new();
+        let backend = serve_hyprland(&dir, Arc::default());
+
+        let (result, states) = fake::watch(&backend).await;
+        result.unwrap();
+        // Refreshes that change nothing are not sent
+        assert_eq!(states.len(), 4);
+
+        let first = &states[0];
+        assert_eq!(
+            first.active_window,
+            ActiveWindow {
+                id: Some(0x1234),
+                title: "~".to_string(),
+                app_id: "foot".to_string(),
+            }
+        );
+        let ids: Vec<u64> = first.workspaces.iter().map(|ws| ws.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let idx: Vec<u32> = first.workspaces.iter().map(|ws| ws.idx).collect();
+        assert_eq!(idx, [1, 2, 1]);
+        assert!(first.workspaces[0].is_active && first.workspaces[0].is_focused);
+        assert!(!first.workspaces[1].is_active);
+        assert!(first.workspaces[2].is_active && !first.workspaces[2].is_focused);
+        assert_eq!(first.focused_output(), Some("DP-1"));
+        assert_eq!(first.keyboard_layout.as_deref(), Some("English (US)"));
+        assert_eq!(
+            first.windows[2],
+            Window {
+                id: 0x9abc,
+                title: "Volume Control".to_string(),
+                app_id: "pavucontrol".to_string(),
+                pid: Some(202),
+                workspace_id: None,
+                is_focused: false,
+                is_floating: true,
+                is_urgent: false,
+            }
+        );
+        // (id, pid, workspace, focused, urgent)
+        let windows = |state: &CompositorState| -> Vec<_> {
+            state.windows.iter().map(|w| (w.id, w.pid, w.workspace_id, w.is_focused, w.is_urgent)).collect()
+        };
+        assert_eq!(
+            windows(first)[..2],
+            [(0x1234, Some(200), Some(1), true, false), (0x5678, Some(201), Some(2), false, false)]
+        );
+
+        assert_eq!(
+            first.outputs,
+            [
+                Output {
+                    name: "HDMI-A-1".to_string(),
+                    logical: Some(LogicalOutput {
+                        x: 0,
+                        y: 0,
+                        width: 1080,
+                        height: 1920,
+                        scale: 1.0
+                    }),
+                },
+                Output {
+                    name: "DP-1".to_string(),
+                    logical: Some(LogicalOutput {
+                        x: 1920,
+                        y: 0,
+                        width: 2560,
+                        height: 1440,
+                        scale: 1.5
+                    }),
+                },
+            ]
+        );
+
+        assert!(states[1].workspaces[1].is_urgent);
+        assert_eq!(windows(&states[1])[1], (0x5678, Some(201), Some(2), false, true));
+        // The virtual keyboard switching to Russian was not sent
+        assert_eq!(states[2].keyboard_layout.as_deref(), Some("German"));
+        // Focusing the urgent window clears it
+        assert!(!states[3].workspaces[1].is_urgent);
+        assert_eq!(states[3].keyboard_layout.as_deref(), Some("German"));
+    }
+
+    #[tokio::test]
+    async fn dispatches_actions() {
+        let dir = SocketDir::new();
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let backend = serve_hyprland(&dir, commands.clone());
+
+        backend.execute(CompositorAction::FocusWorkspace(2)).await.unwrap();
+        backend.execute(CompositorAction::ToggleFullscreen(0x5678)).await.unwrap();
+        backend
+            .execute(CompositorAction::MoveWindowToWorkspace {
+                window: 0x5678,
+                workspace: 3,
+            })
+            .await
+            .unwrap();
+        let err = backend.execute(CompositorAction::CloseWindow(0x404)).await.unwrap_err();
+        assert!(matches!(err, CompositorError::Rejected(message) if message == "No such window found"));
+
+        assert_eq!(
+            *commands.lock().unwrap(),
+            [
+                "dispatch workspace 2",
+                "[[BATCH]]dispatch focuswindow address:0x5678;dispatch fullscreen 0",
+                "dispatch movetoworkspacesilent 3,address:0x5678",
+                "dispatch closewindow address:0x404",
+            ]
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use gpui::{App, AppContext, AsyncApp, Context, DisplayId, Entity, EventEmitter, Global};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod fake;
+mod hyprland;
+mod niri;
+mod sway;
+
+pub use hyprland::HyprlandBackend;
+pub use niri::{NiriActions, NiriBackend, WorkspaceReference};
+pub use sway::SwayBackend;
+
+/// Delay before the first reconnection attempt, doubled after each failure.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// A stream that stayed open this long resets the backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActiveWindow {
+    pub id: Option<u64>,
+    pub title: String,
+    pub app_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    pub id: u64,
+    pub title: String,
+    pub app_id: String,
+    pub pid: Option<i32>,
+    /// `None` for windows outside any workspace, e.g. in the scratchpad.
+    pub workspace_id: Option<u64>,
+    pub is_focused: bool,
+    pub is_floating: bool,
+    pub is_urgent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    pub id: u64,
+    /// Position on its output, starting at 1.
+    pub idx: u32,
+    pub name: String,
+    pub output: Option<String>,
+    /// Shown on its output.
+    pub is_active: bool,
+    /// Active on the focused output.
+    pub is_focused: bool,
+    pub is_urgent: bool,
+    pub window_count: usize,
+}
+
+/// Position and size of an output in the compositor's logical space.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct LogicalOutput {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Output {
+    /// Connector name, e.g. `DP-1`.
+    pub name: String,
+    /// `None` while the output is disabled.
+    pub logical: Option<LogicalOutput>,
+}
+
+impl Output {
+    /// Finds the GPUI display backed by this output.
+    ///
+    /// Wayland does not give displays a connector name on the GPUI side, so
+    /// they are matched on their logical origin, then on their size.
+    pub fn display_id(&self, cx: &App) -> Option<DisplayId> {
+        let logical = self.logical?;
+        let displays = cx.displays();
+
+        let at_origin = displays.iter().find(|display| {
+            let origin = display.bounds().origin;
+            f32::from(origin.x).round() as i32 == logical.x && f32::from(origin.y).round() as i32 == logical.y
+        });
+
+        at_origin
+            .or_else(|| {
+                displays.iter().find(|display| {
+                    let size = display.bounds().size;
+                    f32::from(size.width).round() as u32 == logical.width
+                        && f32::from(size.height).round() as u32 == logical.height
+                })
+            })
+            .map(|display| display.id())
+    }
+}
+
+/// Everything the bar shows about the compositor.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompositorState {
+    pub active_window: ActiveWindow,
+    /// Sorted by output, then by position on the output.
+    pub workspaces: Vec<Workspace>,
+    /// Sorted by id.
+    pub windows: Vec<Window>,
+    pub keyboard_layout: Option<String>,
+    /// Enabled outputs, sorted by logical position (left to right).
+    pub outputs: Vec<Output>,
+}
+
+impl CompositorState {
+    /// Output of the focused workspace.
+    pub fn focused_output(&self) -> Option<&str> {
+        self.workspaces.iter().find(|ws| ws.is_focused)?.output.as_deref()
+    }
+}
+
+/// Numbers workspaces by position on their output, for compositors without
+/// their own index, and sorts them by output. The order on an output is kept.
+pub(crate) fn index_workspaces(workspaces: &mut [Workspace]) {
+    workspaces.sort_by(|a, b| a.output.cmp(&b.output));
+    let mut output = None;
+    let mut idx = 0;
+    for ws in workspaces {
+        if output.as_ref() != Some(&ws.output) {
+            output = Some(ws.output.clone());
+            idx = 0;
+        }
+        idx += 1;
+        ws.idx = idx;
+    }
+}
+
+/// Enabled outputs of `outputs`, left to right.
+pub(crate) fn sort_outputs(mut outputs: Vec<Output>) -> Vec<Output> {
+    outputs.retain(|output| output.logical.is_some());
+    outputs.sort_by_key(|output| output.logical.map(|logical| (logical.x, logical.y)));
+    outputs
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompositorAction {
+    FocusWorkspace(u64),
+    FocusWindow(u64),
+    CloseWindow(u64),
+    ToggleFullscreen(u64),
+    MoveWindowToWorkspace { window: u64, workspace: u64 },
+}
+
+#[derive(Debug)]
+pub enum CompositorError {
+    Io(std::io::Error),
+    /// Error message returned by the compositor.
+    Rejected(String),
+    /// Unexpected reply.
+    Protocol(String),
+}
+
+impl fmt::Display for CompositorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompositorError::Io(err) => write!(f, "compositor socket: {err}"),
+            CompositorError::Rejected(message) => write!(f, "compositor refused: {message}"),
+            CompositorError::Protocol(reply) => write!(f, "unexpected compositor reply: {reply}"),
+        }
+    }
+}
+
+impl std::error::Error for CompositorError {}
+
+impl From<std::io::Error> for CompositorError {
+    fn from(err: std::io::Error) -> Self {
+        CompositorError::Io(err)
+    }
+}
+
+/// IPC client of one compositor.
+///
+/// Backends are built from socket paths so they can run against a fake
+/// socket replaying a recorded event stream.
+pub trait CompositorBackend: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    /// Connects to the event stream and sends the full state after every
+    /// change, starting with the current one. Returns when the stream ends.
+    fn watch(&self, tx: mpsc::UnboundedSender<CompositorState>) -> BoxFuture<'static, Result<(), CompositorError>>;
+
+    fn execute(&self, action: CompositorAction) -> BoxFuture<'static, Result<(), CompositorError>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// No supported compositor is running.
+    Unavailable,
+    Connecting,
+    Connected,
+    /// The event stream was lost; next attempt in `retry_in`. The state is stale meanwhile.
+    Reconnecting { attempt: u32, retry_in: Duration },
+}
+
+#[derive(Default)]
+struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    fn next_delay(&mut self) -> Duration {
+        let delay = INITIAL_BACKOFF.saturating_mul(1 << self.attempt.min(16)).min(MAX_BACKOFF);
+        self.attempt += 1;
+        delay
+    }
+}
+
+enum Update {
+    State(CompositorState),
+    Status(ConnectionStatus),
+}
+
+/// Picks the backend of the running compositor from its environment variables.
+pub fn detect() -> Option<Arc<dyn CompositorBackend>> {
+    if let Some(backend) = NiriBackend::from_env() {
+        return Some(Arc::new(backend));
+    }
+    if let Some(backend) = HyprlandBackend::from_env() {
+        return Some(Arc::new(backend));
+    }
+    if let Some(backend) = SwayBackend::from_env() {
+        return Some(Arc::new(backend));
+    }
+    None
+}
+
+#[derive(Debug, Clone)]
+pub struct CompositorStateChanged;
+
+#[derive(Debug, Clone)]
+pub struct ConnectionStatusChanged(pub ConnectionStatus);
+
+/// An output was plugged, unplugged or moved.
+#[derive(Debug, Clone)]
+pub struct OutputsChanged;
+
+#[derive(Debug, Clone)]
+pub struct FocusedOutputChanged(pub Option<String>);
+
+pub struct CompositorService {
+    pub state: CompositorState,
+    /// State of the event stream.
+    pub connection: ConnectionStatus,
+    backend: Option<Arc<dyn CompositorBackend>>,
+}
+
+impl EventEmitter<CompositorStateChanged> for CompositorService {}
+impl EventEmitter<ConnectionStatusChanged> for CompositorService {}
+impl EventEmitter<OutputsChanged> for CompositorService {}
+impl EventEmitter<FocusedOutputChanged> for CompositorService {}
+
+struct GlobalCompositorService(Entity<CompositorService>);
+impl Global for GlobalCompositorService {}
+
+impl CompositorService {
+    pub fn global(cx: &App) -> Entity<Self> {
+        cx.global::<GlobalCompositorService>().0.clone()
+    }
+
+    /// Name of the detected backend (`niri`, `hyprland`, `sway`).
+    pub fn backend_name(&self) -> Option<&'static str> {
+        self.backend.as_ref().map(|backend| backend.name())
+    }
+
+    pub fn focused_output(&self) -> Option<&Output> {
+        let name = self.state.focused_output()?;
+        self.state.outputs.iter().find(|output| output.name == name)
+    }
+
+    /// Display of the focused output, `None` lets the compositor choose.
+    pub fn focused_display(cx: &App) -> Option<DisplayId> {
+        Self::global(cx).read(cx).focused_output()?.display_id(cx)
+    }
+
+    /// Workspaces of `output`, or all of them without output.
+    pub fn workspaces_on<'a>(&'a self, output: Option<&'a str>) -> impl Iterator<Item = &'a Workspace> + 'a {
+        self.state
+            .workspaces
+            .iter()
+            .filter(move |ws| output.is_none() || ws.output.as_deref() == output)
+    }
+
+    pub fn execute(&self, action: CompositorAction, cx: &App) {
+        let Some(backend) = self.backend.clone() else {
+            return;
+        };
+        gpui_tokio::Tokio::spawn(cx, async move {
+            if let Err(err) = backend.execute(action.clone()).await {
+                log::warn!("{action:?} failed: {err}");
+            }
+        })
+        .detach();
+    }
+
+    /// Focuses the workspace `delta` steps away from the active one of
+    /// `output`, wrapping around at both ends.
+    pub fn cycle_workspace(&self, output: Option<&str>, delta: i32, cx: &App) {
+        let workspaces: Vec<&Workspace> = self.workspaces_on(output).collect();
+        let current = workspaces
+            .iter()
+            .position(|ws| if output.is_some() { ws.is_active } else { ws.is_focused });
+        let Some(current) = current else {
+            return;
+        };
+        let target = (current as i32 + delta).rem_euclid(workspaces.len() as i32) as usize;
+        if target != current {
+            self.execute(CompositorAction::FocusWorkspace(workspaces[target].id), cx);
+        }
+    }
+
+    fn set_connection(&mut self, status: ConnectionStatus, cx: &mut Context<Self>) {
+        if self.connection == status {
+            return;
+        }
+        let name = self.backend_name().unwrap_or("compositor");
+        match status {
+            ConnectionStatus::Connected => log::info!("Connected to {name}"),
+            ConnectionStatus::Reconnecting { attempt, retry_in } => {
+                log::warn!("{name} event stream lost, retry {attempt} in {:.1}s", retry_in.as_secs_f32())
+            }
+            _ => {}
+        }
+        self.connection = status;
+        cx.emit(ConnectionStatusChanged(status));
+        cx.notify();
+    }
+
+    pub fn init(cx: &mut App) -> Entity<Self> {
+        let backend = detect();
+        match &backend {
+            Some(backend) => log::info!("Compositor backend: {}", backend.name()),
+            None => log::warn!("No supported compositor found (niri, Hyprland, Sway)"),
+        }
+
+        let service = cx.new(|_cx| Self {
+            state: CompositorState::default(),
+            connection: match backend {
+                Some(_) => ConnectionStatus::Connecting,
+                None => ConnectionStatus::Unavailable,
+            },
+            backend: backend.clone(),
+        });
+
+        cx.set_global(GlobalCompositorService(service.clone()));
+
+        let Some(backend) = backend else {
+            return service;
+        };
+
+        let (tx, rx) = mpsc::unbounded::<CompositorState>();
+        let (status_tx, status_rx) = mpsc::unbounded::<ConnectionStatus>();
+
+        // Background Tokio task; the event stream is reopened with exponential
+        // backoff. Every connection starts with the full state, which resyncs the UI.
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let mut backoff = Backoff::default();
+            loop {
+                let opened = Instant::now();
+                match backend.watch(tx.clone()).await {
+                    Ok(()) => log::debug!("{} event stream closed", backend.name()),
+                    Err(err) => log::debug!("{} event stream: {err}", backend.name()),
+                }
+                if tx.is_closed() {
+                    break;
+                }
+                if opened.elapsed() >= STABLE_AFTER {
+                    backoff.attempt = 0;
+                }
+                let retry_in = backoff.next_delay();
+                let status = ConnectionStatus::Reconnecting {
+                    attempt: backoff.attempt,
+                    retry_in,
+                };
+                if status_tx.unbounded_send(status).is_err() {
+                    break;
+                }
+                tokio::time::sleep(retry_in).await;
+            }
+        })
+        .detach();
+
+        // UI handler reading updates from MPSC channel
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                let mut updates = futures::stream::select(rx.map(Update::State), status_rx.map(Update::Status));
+                while let Some(update) = updates.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| match update {
+                            Update::State(state) => {
+                                // Sent once the stream is open
+                                srv.set_connection(ConnectionStatus::Connected, cx);
+                                if srv.state == state {
+                                    return;
+                                }
+                                let outputs_changed = srv.state.outputs != state.outputs;
+                                let focused_output = state.focused_output().map(str::to_string);
+                                let focus_moved = srv.state.focused_output() != focused_output.as_deref();
+                                srv.state = state;
+                                cx.emit(CompositorStateChanged);
+                                if outputs_changed {
+                                    cx.emit(OutputsChanged);
+                                }
+                                if focus_moved {
+                                    cx.emit(FocusedOutputChanged(focused_output));
+                                }
+                                cx.notify();
+                            }
+                            Update::Status(status) => srv.set_connection(status, cx),
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
+        service
+    }
+}
@@ -0,0 +1,550 @@
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+use crate::{
+    sort_outputs, ActiveWindow, CompositorAction, CompositorBackend, CompositorError, CompositorState, Output, Window,
+    Workspace,
+};
+
+/// niri, through the JSON socket from `NIRI_SOCKET`.
+pub struct NiriBackend {
+    socket: PathBuf,
+}
+
+impl NiriBackend {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self { socket: socket.into() }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("NIRI_SOCKET").map(Self::new)
+    }
+
+    /// Typed actions on the same niri instance.
+    pub fn actions(&self) -> NiriActions {
+        NiriActions::new(self.socket.clone())
+    }
+}
+
+/// Workspace as niri's `WorkspaceReferenceArg`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum WorkspaceReference {
+    Id(u64),
+    /// Position on the focused output, starting at 1.
+    Index(u8),
+    Name(String),
+}
+
+/// Typed niri actions, for what [`CompositorAction`] cannot express, like
+/// workspaces by index or name.
+///
+/// niri answers a single request per connection then hangs up, so there is
+/// no connection to keep open: each action opens its own.
+#[derive(Debug, Clone)]
+pub struct NiriActions {
+    socket: PathBuf,
+}
+
+impl NiriActions {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self { socket: socket.into() }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("NIRI_SOCKET").map(Self::new)
+    }
+
+    /// Runs an action, e.g. `{"FocusWindow":{"id":3}}`.
+    pub async fn action(&self, action: serde_json::Value) -> Result<(), CompositorError> {
+        request::<IgnoredAny>(&self.socket, json!({ "Action": action })).await?;
+        Ok(())
+    }
+
+    pub async fn focus_window(&self, id: u64) -> Result<(), CompositorError> {
+        self.action(json!({ "FocusWindow": { "id": id } })).await
+    }
+
+    pub async fn close_window(&self, id: u64) -> Result<(), CompositorError> {
+        self.action(json!({ "CloseWindow": { "id": id } })).await
+    }
+
+    pub async fn toggle_fullscreen(&self, id: u64) -> Result<(), CompositorError> {
+        self.action(json!({ "FullscreenWindow": { "id": id } })).await
+    }
+
+    pub async fn move_window_to_workspace(
+        &self,
+        window_id: u64,
+        workspace: WorkspaceReference,
+        focus: bool,
+    ) -> Result<(), CompositorError> {
+        self.action(json!({
+            "MoveWindowToWorkspace": { "window_id": window_id, "reference": workspace, "focus": focus }
+        }))
+        .await
+    }
+
+    pub async fn focus_workspace(&self, workspace: WorkspaceReference) -> Result<(), CompositorError> {
+        self.action(json!({ "FocusWorkspace": { "reference": workspace } })).await
+    }
+}
+
+#[derive(Deserialize)]
+struct NiriWorkspace {
+    id: u64,
+    idx: u32,
+    name: Option<String>,
+    output: Option<String>,
+    #[serde(default)]
+    is_urgent: bool,
+    is_active: bool,
+    is_focused: bool,
+}
+
+#[derive(Deserialize)]
+struct NiriWindow {
+    id: u64,
+    title: Option<String>,
+    app_id: Option<String>,
+    pid: Option<i32>,
+    workspace_id: Option<u64>,
+    is_focused: bool,
+    #[serde(default)]
+    is_floating: bool,
+    #[serde(default)]
+    is_urgent: bool,
+}
+
+#[derive(Deserialize)]
+struct KeyboardLayouts {
+    names: Vec<String>,
+    current_idx: u8,
+}
+
+#[derive(Deserialize)]
+enum NiriEvent {
+    WorkspacesChanged { workspaces: Vec<NiriWorkspace> },
+    WorkspaceActivated { id: u64, focused: bool },
+    WorkspaceUrgencyChanged { id: u64, urgent: bool },
+    WindowsChanged { windows: Vec<NiriWindow> },
+    WindowOpenedOrChanged { window: NiriWindow },
+    WindowClosed { id: u64 },
+    WindowFocusChanged { id: Option<u64> },
+    WindowUrgencyChanged { id: u64, urgent: bool },
+    KeyboardLayoutsChanged { keyboard_layouts: KeyboardLayouts },
+    KeyboardLayoutSwitched { idx: u8 },
+}
+
+#[derive(Deserialize)]
+enum OutputsReply {
+    Outputs(HashMap<String, Output>),
+}
+
+#[derive(Default)]
+struct NiriModel {
+    workspaces: Vec<NiriWorkspace>,
+    windows: Vec<NiriWindow>,
+    layouts: Vec<String>,
+    current_layout: usize,
+    outputs: Vec<Output>,
+}
+
+impl NiriModel {
+    fn apply(&mut self, event: NiriEvent) {
+        match event {
+            NiriEvent::WorkspacesChanged { mut workspaces } => {
+                workspaces.sort_by(|a, b| (&a.output, a.idx).cmp(&(&b.output, b.idx)));
+                self.workspaces = workspaces;
+            }
+            NiriEvent::WorkspaceActivated { id, focused } => {
+                let Some(output) = self.workspaces.iter().find(|ws| ws.id == id).map(|ws| ws.output.clone()) else {
+                    return;
+                };
+                for ws in &mut self.workspaces {
+                    if ws.output == output {
+                        ws.is_active = ws.id == id;
+                    }
+                    if focused {
+                        ws.is_focused = ws.id == id;
+                    }
+                }
+            }
+            NiriEvent::WorkspaceUrgencyChanged { id, urgent } => {
+                if let Some(ws) = self.workspaces.iter_mut().find(|ws| ws.id == id) {
+                    ws.is_urgent = urgent;
+                }
+            }
+            NiriEvent::WindowsChanged { mut windows } => {
+                windows.sort_by_key(|w| w.id);
+                self.windows = windows;
+            }
+            NiriEvent::WindowOpenedOrChanged { window } => {
+                if window.is_focused {
+                    for w in &mut self.windows {
+                        w.is_focused = false;
+                    }
+                }
+                match self.windows.binary_search_by_key(&window.id, |w| w.id) {
+                    Ok(i) => self.windows[i] = window,
+                    Err(i) => self.windows.insert(i, window),
+                }
+            }
+            NiriEvent::WindowClosed { id } => self.windows.retain(|w| w.id != id),
+            NiriEvent::WindowFocusChanged { id } => {
+                for w in &mut self.windows {
+                    w.is_focused = Some(w.id) == id;
+                }
+            }
+            NiriEvent::WindowUrgencyChanged { id, urgent } => {
+                if let Some(w) = self.windows.iter_mut().find(|w| w.id == id) {
+                    w.is_urgent = urgent;
+                }
+            }
+            NiriEvent::KeyboardLayoutsChanged { keyboard_layouts } => {
+                self.layouts = keyboard_layouts.names;
+                self.current_layout = keyboard_layouts.current_idx as usize;
+            }
+            NiriEvent::KeyboardLayoutSwitched { idx } => self.current_layout = idx as usize,
+        }
+    }
+
+    fn state(&self) -> CompositorState {
+        let active_window = self
+            .windows
+            .iter()
+            .find(|w| w.is_focused)
+            .map(|w| ActiveWindow {
+                id: Some(w.id),
+                title: w.title.clone().unwrap_or_default(),
+                app_id: w.app_id.clone().unwrap_or_default(),
+            })
+            .unwrap_or_default();
+
+        let workspaces = self
+            .workspaces
+            .iter()
+            .map(|ws| Workspace {
+                id: ws.id,
+                idx: ws.idx,
+                name: ws.name.clone().unwrap_or_else(|| ws.idx.to_string()),
+                output: ws.output.clone(),
+                is_active: ws.is_active,
+                is_focused: ws.is_focused,
+                is_urgent: ws.is_urgent,
+                window_count: self.windows.iter().filter(|w| w.workspace_id == Some(ws.id)).count(),
+            })
+            .collect();
+
+        let windows = self
+            .windows
+            .iter()
+            .map(|w| Window {
+                id: w.id,
+                title: w.title.clone().unwrap_or_default(),
+                app_id: w.app_id.clone().unwrap_or_default(),
+                pid: w.pid,
+                workspace_id: w.workspace_id,
+                is_focused: w.is_focused,
+                is_floating: w.is_floating,
+                is_urgent: w.is_urgent,
+            })
+            .collect();
+
+        CompositorState {
+            active_window,
+            workspaces,
+            windows,
+            keyboard_layout: self.layouts.get(self.current_layout).cloned(),
+            outputs: self.outputs.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum Reply<T> {
+    Ok(T),
+    Err(String),
+}
+
+/// Sends one request on its own connection, as niri answers a single request per connection.
+async fn request<T: DeserializeOwned>(socket: &Path, request: serde_json::Value) -> Result<T, CompositorError> {
+    let mut stream = UnixStream::connect(socket).await?;
+    let mut payload = request.to_string();
+    payload.push('\n');
+    stream.write_all(payload.as_bytes()).await?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).await?;
+    match serde_json::from_str::<Reply<T>>(&line) {
+        Ok(Reply::Ok(value)) => Ok(value),
+        Ok(Reply::Err(message)) => Err(CompositorError::Rejected(message)),
+        Err(_) => Err(CompositorError::Protocol(line.trim().to_string())),
+    }
+}
+
+async fn fetch_outputs(socket: &Path) -> Result<Vec<Output>, CompositorError> {
+    let OutputsReply::Outputs(outputs) = request(socket, json!("Outputs")).await?;
+    Ok(sort_outputs(outputs.into_values().collect()))
+}
+
+impl CompositorBackend for NiriBackend {
+    fn name(&self) -> &'static str {
+        "niri"
+    }
+
+    fn watch(&self, tx: mpsc::UnboundedSender<CompositorState>) -> BoxFuture<'static, Result<(), CompositorError>> {
+        let socket = self.socket.clone();
+        async move {
+            let mut stream = UnixStream::connect(&socket).await?;
+            stream.write_all(b"\"EventStream\"\n").await?;
+
+            // niri opens the stream with the full state, then sends deltas
+            let mut model = NiriModel::default();
+            let mut last_state = None;
+            let mut lines = BufReader::new(stream).lines();
+            while let Some(line) = lines.next_line().await? {
+                let Ok(event) = serde_json::from_str::<NiriEvent>(&line) else {
+                    continue;
+                };
+                // Sent on connection and on every output hotplug
+                let outputs_changed = matches!(event, NiriEvent::WorkspacesChanged { .. });
+                model.apply(event);
+                if outputs_changed {
+                    model.outputs = fetch_outputs(&socket).await?;
+                }
+
+                let state = model.state();
+                if last_state.as_ref() != Some(&state) {
+                    last_state = Some(state.clone());
+                    if tx.unbounded_send(state).is_err() {
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn execute(&self, action: CompositorAction) -> BoxFuture<'static, Result<(), CompositorError>> {
+        let actions = self.actions();
+        async move {
+            match action {
+                CompositorAction::FocusWorkspace(id) => actions.focus_workspace(WorkspaceReference::Id(id)).await,
+                CompositorAction::FocusWindow(id) => actions.focus_window(id).await,
+                CompositorAction::CloseWindow(id) => actions.close_window(id).await,
+                CompositorAction::ToggleFullscreen(id) => actions.toggle_fullscreen(id).await,
+                CompositorAction::MoveWindowToWorkspace { window, workspace } => {
+                    actions
+                        .move_window_to_workspace(window, WorkspaceReference::Id(workspace), false)
+                        .await
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::{self, SocketDir};
+    use crate::LogicalOutput;
+    use std::sync::{Arc, Mutex};
+
+    /// Event stream recorded from niri, with an event this version does not know.
+    const EVENTS: &[&str] = &[
+        r#"{"Ok":"Handled"}"#,
+        r#"{"WorkspacesChanged":{"workspaces":[
+            {"id":2,"idx":2,"name":null,"output":"DP-1","is_urgent":false,"is_active":false,"is_focused":false,"active_window_id":null},
+            {"id":3,"idx":1,"name":"chat","output":"HDMI-A-1","is_urgent":false,"is_active":true,"is_focused":false,"active_window_id":11},
+            {"id":1,"idx":1,"name":null,"output":"DP-1","is_urgent":false,"is_active":true,"is_focused":true,"active_window_id":10}]}}"#,
+        r#"{"WindowsChanged":{"windows":[
+            {"id":10,"title":"~","app_id":"foot","pid":100,"workspace_id":1,"is_focused":true,"is_floating":false,"is_urgent":false},
+            {"id":11,"title":"Matrix","app_id":"firefox","pid":101,"workspace_id":3,"is_focused":false,"is_floating":false,"is_urgent":false}]}}"#,
+        r#"{"KeyboardLayoutsChanged":{"keyboard_layouts":{"names":["English (US)","German"],"current_idx":0}}}"#,
+        r#"{"OverviewOpenedOrClosed":{"is_open":true}}"#,
+        r#"{"WorkspaceActivated":{"id":2,"focused":true}}"#,
+        r#"{"WindowFocusChanged":{"id":null}}"#,
+        r#"{"KeyboardLayoutSwitched":{"idx":1}}"#,
+        r#"{"WindowOpenedOrChanged":{"window":
+            {"id":12,"title":"video.mkv","app_id":"mpv","pid":102,"workspace_id":2,"is_focused":true,"is_floating":false,"is_urgent":false}}}"#,
+        r#"{"WorkspaceUrgencyChanged":{"id":3,"urgent":true}}"#,
+        r#"{"WindowOpenedOrChanged":{"window":
+            {"id":9,"title":"Picture-in-Picture","app_id":"firefox","pid":101,"workspace_id":1,"is_focused":false,"is_floating":true,"is_urgent":false}}}"#,
+        r#"{"WindowUrgencyChanged":{"id":10,"urgent":true}}"#,
+        r#"{"WindowClosed":{"id":11}}"#,
+    ];
+
+    const OUTPUTS: &str = r#"{"Ok":{"Outputs":{
+        "DP-1":{"name":"DP-1","make":"Dell","model":"U2720Q","current_mode":0,
+            "logical":{"x":1920,"y":0,"width":2560,"height":1440,"scale":1.5,"transform":"Normal"}},
+        "eDP-1":{"name":"eDP-1","make":"BOE","model":"0x095F","current_mode":null,"logical":null},
+        "HDMI-A-1":{"name":"HDMI-A-1","make":"LG","model":"24MP","current_mode":0,
+            "logical":{"x":0,"y":0,"width":1920,"height":1080,"scale":1.0,"transform":"Normal"}}}}}"#;
+
+    /// Answers one request per connection, like niri, and records the requests.
+    fn serve_niri(dir: &SocketDir, requests: Arc<Mutex<Vec<String>>>) -> PathBuf {
+        let path = dir.socket("niri.sock");
+        fake::serve(&path, move |stream| {
+            let requests = requests.clone();
+            async move {
+                let (read, mut write) = stream.into_split();
+                let mut request = String::new();
+                BufReader::new(read).read_line(&mut request).await.unwrap();
+                let request = request.trim().to_string();
+
+                let reply = match request.as_str() {
+                    r#""EventStream""# => EVENTS.iter().map(|event| event.replace('\n', "") + "\n").collect(),
+                    r#""Outputs""# => OUTPUTS.replace('\n', "") + "\n",
+                    _ if request.contains("404") => "{\"Err\":\"Window not found\"}\n".to_string(),
+                    _ => "{\"Ok\":\"Handled\"}\n".to_string(),
+                };
+                requests.lock().unwrap().push(request);
+                write.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        path
+    }
+
+    fn output_names(state: &CompositorState) -> Vec<&str> {
+        state.outputs.iter().map(|output| output.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn replays_event_stream() {
+        let dir = SocketDir::new();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let backend = NiriBackend::new(serve_niri(&dir, requests.clone()));
+
+        let (result, states) = fake::watch(&backend).await;
+        result.unwrap();
+        // One state per event, the unknown one is skipped
+        assert_eq!(states.len(), 11);
+        assert_eq!(*requests.lock().unwrap(), [r#""EventStream""#, r#""Outputs""#]);
+
+        let first = &states[0];
+        assert_eq!(output_names(first), ["HDMI-A-1", "DP-1"]);
+        assert_eq!(
+            first.outputs[1].logical,
+            Some(LogicalOutput {
+                x: 1920,
+                y: 0,
+                width: 2560,
+                height: 1440,
+                scale: 1.5
+            })
+        );
+        let ids: Vec<u64> = first.workspaces.iter().map(|ws| ws.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let idx: Vec<u32> = first.workspaces.iter().map(|ws| ws.idx).collect();
+        assert_eq!(idx, [1, 2, 1]);
+        assert_eq!(first.workspaces[0].name, "1");
+        // Named workspaces keep their index
+        assert_eq!((first.workspaces[2].name.as_str(), first.workspaces[2].idx), ("chat", 1));
+        assert_eq!(first.focused_output(), Some("DP-1"));
+        assert_eq!(first.active_window, ActiveWindow::default());
+
+        assert_eq!(states[1].active_window.app_id, "foot");
+        assert_eq!(states[2].keyboard_layout.as_deref(), Some("English (US)"));
+
+        let last = states.last().unwrap();
+        let ws = |id: u64| last.workspaces.iter().find(|ws| ws.id == id).unwrap();
+        assert!(!ws(1).is_active && !ws(1).is_focused);
+        assert!(ws(2).is_active && ws(2).is_focused);
+        assert!(ws(3).is_active && !ws(3).is_focused && ws(3).is_urgent);
+        assert_eq!((ws(1).window_count, ws(2).window_count, ws(3).window_count), (2, 1, 0));
+        // (id, pid, workspace, focused, floating, urgent)
+        let windows: Vec<_> = last
+            .windows
+            .iter()
+            .map(|w| (w.id, w.pid, w.workspace_id, w.is_focused, w.is_floating, w.is_urgent))
+            .collect();
+        assert_eq!(
+            windows,
+            [
+                (9, Some(101), Some(1), false, true, false),
+                (10, Some(100), Some(1), false, false, true),
+                (12, Some(102), Some(2), true, false, false),
+            ]
+        );
+        assert_eq!(last.windows[0].title, "Picture-in-Picture");
+        assert_eq!(
+            last.active_window,
+            ActiveWindow {
+                id: Some(12),
+                title: "video.mkv".to_string(),
+                app_id: "mpv".to_string(),
+            }
+        );
+        assert_eq!(last.keyboard_layout.as_deref(), Some("German"));
+        assert_eq!(last.focused_output(), Some("DP-1"));
+    }
+
+    fn sent(requests: &Mutex<Vec<String>>) -> Vec<serde_json::Value> {
+        let requests = requests.lock().unwrap();
+        requests.iter().map(|request| serde_json::from_str(request).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn executes_actions() {
+        let dir = SocketDir::new();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let backend = NiriBackend::new(serve_niri(&dir, requests.clone()));
+
+        backend.execute(CompositorAction::FocusWorkspace(2)).await.unwrap();
+        backend
+            .execute(CompositorAction::MoveWindowToWorkspace { window: 12, workspace: 3 })
+            .await
+            .unwrap();
+        let err = backend.execute(CompositorAction::CloseWindow(404)).await.unwrap_err();
+        assert!(matches!(err, CompositorError::Rejected(message) if message == "Window not found"));
+
+        assert_eq!(
+            sent(&requests),
+            [
+                json!({ "Action": { "FocusWorkspace": { "reference": { "Id": 2 } } } }),
+                json!({ "Action": { "MoveWindowToWorkspace": {
+                    "window_id": 12, "reference": { "Id": 3 }, "focus": false
+                } } }),
+                json!({ "Action": { "CloseWindow": { "id": 404 } } }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_typed_actions() {
+        let dir = SocketDir::new();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let actions = NiriBackend::new(serve_niri(&dir, requests.clone())).actions();
+
+        actions.focus_workspace(WorkspaceReference::Index(2)).await.unwrap();
+        actions
+            .move_window_to_workspace(3, WorkspaceReference::Name("chat".to_string()), true)
+            .await
+            .unwrap();
+        actions.toggle_fullscreen(3).await.unwrap();
+        let err = actions.focus_window(404).await.unwrap_err();
+        assert!(matches!(err, CompositorError::Rejected(message) if message == "Window not found"));
+
+        assert_eq!(
+            sent(&requests),
+            [
+                json!({ "Action": { "FocusWorkspace": { "reference": { "Index": 2 } } } }),
+                json!({ "Action": { "MoveWindowToWorkspace": {
+                    "window_id": 3, "reference": { "Name": "chat" }, "focus": true
+                } } }),
+                json!({ "Action": { "FullscreenWindow": { "id": 3 } } }),
+                json!({ "Action": { "FocusWindow": { "id": 404 } } }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,639 @@
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use crate::{
+    index_workspaces, sort_outputs, ActiveWindow, CompositorAction, CompositorBackend, CompositorError,
+    CompositorState, LogicalOutput, Output, Window, Workspace,
+};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+const GET_VERSION: u32 = 7;
+/// Sway only, like the `input` event.
+const GET_INPUTS: u32 = 100;
+
+/// Sway and i3, through the binary i3-ipc protocol. The keyboard layout is
+/// only known on sway.
+pub struct SwayBackend {
+    socket: PathBuf,
+}
+
+impl SwayBackend {
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self { socket: socket.into() }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SWAYSOCK")
+            .or_else(|| std::env::var_os("I3SOCK"))
+            .map(Self::new)
+    }
+}
+
+#[derive(Deserialize)]
+struct SwayWorkspace {
+    id: u64,
+    name: String,
+    output: Option<String>,
+    focused: bool,
+    visible: bool,
+    #[serde(default)]
+    urgent: bool,
+}
+
+#[derive(Deserialize)]
+struct SwayNode {
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    #[serde(default)]
+    focused: bool,
+    #[serde(default)]
+    urgent: bool,
+    pid: Option<u32>,
+    /// X11 window id, the only window marker i3 sends.
+    window: Option<u64>,
+    app_id: Option<String>,
+    window_properties: Option<SwayWindowProperties>,
+    #[serde(default)]
+    nodes: Vec<SwayNode>,
+    #[serde(default)]
+    floating_nodes: Vec<SwayNode>,
+}
+
+#[derive(Deserialize)]
+struct SwayWindowProperties {
+    class: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SwayVersion {
+    /// Only sent by sway.
+    variant: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SwayOutput {
+    name: String,
+    active: bool,
+    rect: SwayRect,
+    /// Missing on i3.
+    scale: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct SwayRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Deserialize)]
+struct SwayInput {
+    #[serde(rename = "type")]
+    kind: String,
+    xkb_active_layout_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CommandOutcome {
+    success: bool,
+    error: Option<String>,
+}
+
+async fn send(stream: &mut UnixStream, kind: u32, payload: &[u8]) -> Result<(), CompositorError> {
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message).await?;
+    Ok(())
+}
+
+/// Reads one message and returns its type and payload.
+async fn receive(stream: &mut UnixStream) -> Result<(u32, Vec<u8>), CompositorError> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).await?;
+    if &header[..6] != MAGIC {
+        return Err(CompositorError::Protocol("bad i3-ipc magic".to_string()));
+    }
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok((kind, payload))
+}
+
+async fn query<T: DeserializeOwned>(socket: &Path, kind: u32, payload: &str) -> Result<T, CompositorError> {
+    let mut stream = UnixStream::connect(socket).await?;
+    send(&mut stream, kind, payload.as_bytes()).await?;
+    let (_, reply) = receive(&mut stream).await?;
+    serde_json::from_slice(&reply)
+        .map_err(|_| CompositorError::Protocol(String::from_utf8_lossy(&reply).into_owned()))
+}
+
+/// Application windows have a pid (sway) or an X11 window (i3 and XWayland),
+/// containers and workspaces have neither.
+fn is_window(node: &SwayNode) -> bool {
+    node.pid.is_some() || node.window.is_some()
+}
+
+fn children(node: &SwayNode) -> impl Iterator<Item = &SwayNode> {
+    node.nodes.iter().chain(&node.floating_nodes)
+}
+
+fn count_windows(node: &SwayNode) -> usize {
+    children(node)
+        .map(|child| if is_window(child) { 1 } else { count_windows(child) })
+        .sum()
+}
+
+/// XWayland and i3 windows only have an X11 class.
+fn app_id(node: &SwayNode) -> String {
+    node.app_id
+        .clone()
+        .or_else(|| node.window_properties.as_ref().and_then(|props| props.class.clone()))
+        .unwrap_or_default()
+}
+
+/// Windows under `node`, with the workspace they are on. The scratchpad is
+/// the `__i3_scratch` workspace, which is not a real one.
+fn collect_windows(node: &SwayNode, workspace: Option<u64>, floating: bool, out: &mut Vec<Window>) {
+    if is_window(node) {
+        out.push(Window {
+            id: node.id,
+            title: node.name.clone().unwrap_or_default(),
+            app_id: app_id(node),
+            pid: node.pid.map(|pid| pid as i32),
+            workspace_id: workspace,
+            is_focused: node.focused,
+            is_floating: floating,
+            is_urgent: node.urgent,
+        });
+    }
+    let workspace = match node.kind.as_str() {
+        "workspace" if node.name.as_deref() == Some("__i3_scratch") => None,
+        "workspace" => Some(node.id),
+        _ => workspace,
+    };
+    for child in &node.nodes {
+        collect_windows(child, workspace, floating, out);
+    }
+    for child in &node.floating_nodes {
+        collect_windows(child, workspace, true, out);
+    }
+}
+
+fn find_focused(node: &SwayNode) -> Option<&SwayNode> {
+    if node.focused && is_window(node) {
+        return Some(node);
+    }
+    children(node).find_map(find_focused)
+}
+
+/// Window counts per workspace id.
+fn collect_workspaces(node: &SwayNode, out: &mut Vec<(u64, usize)>) {
+    if node.kind == "workspace" {
+        out.push((node.id, count_windows(node)));
+        return;
+    }
+    for child in children(node) {
+        collect_workspaces(child, out);
+    }
+}
+
+async fn is_sway(socket: &Path) -> Result<bool, CompositorError> {
+    let version: SwayVersion = query(socket, GET_VERSION, "").await?;
+    Ok(version.variant.as_deref() == Some("sway"))
+}
+
+async fn fetch_state(socket: &Path, sway: bool) -> Result<CompositorState, CompositorError> {
+    let workspaces: Vec<SwayWorkspace> = query(socket, GET_WORKSPACES, "").await?;
+    let tree: SwayNode = query(socket, GET_TREE, "").await?;
+    let outputs: Vec<SwayOutput> = query(socket, GET_OUTPUTS, "").await?;
+    let inputs: Vec<SwayInput> = if sway {
+        query(socket, GET_INPUTS, "").await?
+    } else {
+        Vec::new()
+    };
+
+    let active_window = find_focused(&tree)
+        .map(|node| ActiveWindow {
+            id: Some(node.id),
+            title: node.name.clone().unwrap_or_default(),
+            app_id: app_id(node),
+        })
+        .unwrap_or_default();
+
+    let mut windows = Vec::new();
+    collect_windows(&tree, None, false, &mut windows);
+    windows.sort_by_key(|w| w.id);
+
+    let mut counts = Vec::new();
+    collect_workspaces(&tree, &mut counts);
+
+    let mut workspaces: Vec<Workspace> = workspaces
+        .into_iter()
+        .map(|ws| Workspace {
+            window_count: counts
+                .iter()
+                .find(|(id, _)| *id == ws.id)
+                .map(|(_, count)| *count)
+                .unwrap_or(0),
+            id: ws.id,
+            idx: 0,
+            name: ws.name,
+            output: ws.output,
+            is_active: ws.visible,
+            is_focused: ws.focused,
+            is_urgent: ws.urgent,
+        })
+        .collect();
+    // GET_WORKSPACES is in sway's own order
+    index_workspaces(&mut workspaces);
+
+    let keyboard_layout = inputs
+        .into_iter()
+        .filter(|input| input.kind == "keyboard")
+        .find_map(|input| input.xkb_active_layout_name);
+
+    let outputs = outputs
+        .into_iter()
+        .map(|output| Output {
+            logical: output.active.then_some(LogicalOutput {
+                x: output.rect.x,
+                y: output.rect.y,
+                width: output.rect.width,
+                height: output.rect.height,
+                scale: output.scale.unwrap_or(1.0),
+            }),
+            name: output.name,
+        })
+        .collect();
+
+    Ok(CompositorState {
+        active_window,
+        workspaces,
+        windows,
+        keyboard_layout,
+        outputs: sort_outputs(outputs),
+    })
+}
+
+/// Quotes a workspace name for a sway command.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+async fn run_command(socket: &Path, command: &str) -> Result<(), CompositorError> {
+    let outcomes: Vec<CommandOutcome> = query(socket, RUN_COMMAND, command).await?;
+    match outcomes.into_iter().find(|outcome| !outcome.success) {
+        Some(failed) => Err(CompositorError::Rejected(failed.error.unwrap_or_default())),
+        None => Ok(()),
+    }
+}
+
+async fn workspace_name(socket: &Path, id: u64) -> Result<String, CompositorError> {
+    let workspaces: Vec<SwayWorkspace> = query(socket, GET_WORKSPACES, "").await?;
+    workspaces
+        .into_iter()
+        .find(|ws| ws.id == id)
+        .map(|ws| ws.name)
+        .ok_or_else(|| CompositorError::Rejected(format!("no workspace with id {id}")))
+}
+
+impl CompositorBackend for SwayBackend {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    fn watch(&self, tx: mpsc::UnboundedSender<CompositorState>) -> BoxFuture<'static, Result<(), CompositorError>> {
+        let socket = self.socket.clone();
+        async move {
+            let sway = is_sway(&socket).await?;
+            let subscription: &[u8] = if sway {
+                br#"["workspace","window","output","input"]"#
+            } else {
+                br#"["workspace","window","output"]"#
+            };
+
+            let mut events = UnixStream::connect(&socket).await?;
+            send(&mut events, SUBSCRIBE, subscription).await?;
+            let (_, reply) = receive(&mut events).await?;
+            if !String::from_utf8_lossy(&reply).contains("true") {
+                return Err(CompositorError::Rejected("subscription refused".to_string()));
+            }
+
+            let mut last_state = fetch_state(&socket, sway).await?;
+            if tx.unbounded_send(last_state.clone()).is_err() {
+                return Ok(());
+            }
+
+            // Event payloads are not needed, every event triggers a refetch
+            loop {
+                receive(&mut events).await?;
+                let state = fetch_state(&socket, sway).await?;
+                if state != last_state {
+                    last_state = state.clone();
+                    if tx.unbounded_send(state).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn execute(&self, action: CompositorAction) -> BoxFuture<'static, Result<(), CompositorError>> {
+        let socket = self.socket.clone();
+        async move {
+            let command = match action {
+                CompositorAction::FocusWorkspace(id) => {
+                    format!("workspace {}", quote(&workspace_name(&socket, id).await?))
+                }
+                CompositorAction::FocusWindow(id) => format!("[con_id={id}] focus"),
+                CompositorAction::CloseWindow(id) => format!("[con_id={id}] kill"),
+                CompositorAction::ToggleFullscreen(id) => format!("[con_id={id}] fullscreen toggle"),
+                CompositorAction::MoveWindowToWorkspace { window, workspace } => {
+                    let name = workspace_name(&socket, workspace).await?;
+                    format!("[con_id={window}] move container to workspace {}", quote(&name))
+                }
+            };
+            run_command(&socket, &command).await
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::{self, SocketDir};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const WINDOW_EVENT: u32 = 0x8000_0003;
+
+    /// Requests received by the fake, as (type, payload).
+    type Log = Arc<Mutex<Vec<(u32, String)>>>;
+
+    /// Window as sway or i3 describe it: sway gives native windows a pid and
+    /// an app_id, XWayland ones a pid and an X11 window; i3 only an X11 window.
+    fn window(sway: bool, id: u64, app: &str, xwayland: bool, focused: bool) -> Value {
+        let mut node = json!({
+            "id": id, "type": "con", "name": format!("{app} window"), "focused": focused,
+            "nodes": [], "floating_nodes": [],
+        });
+        if sway {
+            node["pid"] = json!(1000 + id);
+        }
+        if sway && !xwayland {
+            node["app_id"] = json!(app);
+        } else {
+            node["window"] = json!(0x0120_0000 + id);
+            node["window_properties"] = json!({ "class": app });
+        }
+        node
+    }
+
+    /// Workspace 1 holds a split container with two windows and a floating
+    /// one, workspace 2 is empty and the scratchpad is hidden in `__i3`.
+    fn tree(sway: bool, focused: u64) -> Value {
+        let win = |id, app, xwayland| window(sway, id, app, xwayland, id == focused);
+        json!({
+            "id": 1, "type": "root", "name": "root", "nodes": [
+                { "id": 2, "type": "output", "name": "__i3", "nodes": [
+                    { "id": 3, "type": "workspace", "name": "__i3_scratch", "nodes": [],
+                      "floating_nodes": [win(20, "keepassxc", false)] },
+                ]},
+                { "id": 4, "type": "output", "name": "DP-1", "nodes": [
+                    { "id": 10, "type": "workspace", "name": "1",
+                      "nodes": [
+                          { "id": 11, "type": "con", "name": null,
+                            "nodes": [win(12, "foot", false), win(13, "steam", true)] },
+                      ],
+                      "floating_nodes": [win(14, "pavucontrol", false)] },
+                ]},
+                { "id": 5, "type": "output", "name": "HDMI-A-1", "nodes": [
+                    { "id": 15, "type": "workspace", "name": "2", "nodes": [] },
+                ]},
+            ]
+        })
+    }
+
+    fn workspaces() -> Value {
+        json!([
+            { "id": 10, "num": 1, "name": "1", "output": "DP-1", "focused": true, "visible": true, "urgent": false },
+            { "id": 15, "num": 2, "name": "2", "output": "HDMI-A-1", "focused": false, "visible": true, "urgent": true },
+        ])
+    }
+
+    /// i3 does not report the scale.
+    fn outputs(sway: bool) -> Value {
+        let mut outputs = json!([
+            { "name": "DP-1", "active": true, "rect": { "x": 1920, "y": 0, "width": 2560, "height": 1440 } },
+            { "name": "eDP-1", "active": false, "rect": { "x": 0, "y": 0, "width": 0, "height": 0 } },
+            { "name": "HDMI-A-1", "active": true, "rect": { "x": 0, "y": 0, "width": 1920, "height": 1080 } },
+        ]);
+        if sway {
+            outputs[0]["scale"] = json!(1.5);
+            outputs[1]["scale"] = json!(-1.0);
+            outputs[2]["scale"] = json!(1.0);
+        }
+        outputs
+    }
+
+    /// Serves the i3-ipc protocol as sway or i3 would. The first tree has
+    /// window 13 focused, the following ones window 14. The event stream
+    /// sends one window event, then closes.
+    fn serve_ipc(dir: &SocketDir, sway: bool, log: Log) -> SwayBackend {
+        let path = dir.socket("ipc.sock");
+        let trees = Arc::new(AtomicUsize::new(0));
+        fake::serve(&path, move |mut stream| {
+            let log = log.clone();
+            let trees = trees.clone();
+            async move {
+                while let Ok((kind, payload)) = receive(&mut stream).await {
+                    let payload = String::from_utf8(payload).unwrap();
+                    log.lock().unwrap().push((kind, payload.clone()));
+                    let reply = match kind {
+                        GET_VERSION if sway => json!({ "major": 1, "variant": "sway", "human_readable": "1.10" }),
+                        GET_VERSION => json!({ "major": 4, "human_readable": "4.23" }),
+                        GET_WORKSPACES => workspaces(),
+                        GET_OUTPUTS => outputs(sway),
+                        GET_TREE => {
+                            let focused = if trees.fetch_add(1, Ordering::Relaxed) == 0 { 13 } else { 14 };
+                            tree(sway, focused)
+                        }
+                        GET_INPUTS if sway => json!([
+                            { "identifier": "1:1:mouse", "type": "pointer" },
+                            { "identifier": "1:1:kbd", "type": "keyboard", "xkb_active_layout_name": "English (US)" },
+                        ]),
+                        // i3 drops clients sending unknown message types
+                        GET_INPUTS => return,
+                        SUBSCRIBE if !sway && payload.contains("input") => json!({ "success": false }),
+                        SUBSCRIBE => {
+                            let reply = json!({ "success": true }).to_string();
+                            send(&mut stream, SUBSCRIBE, reply.as_bytes()).await.unwrap();
+                            let event = json!({ "change": "focus" }).to_string();
+                            send(&mut stream, WINDOW_EVENT, event.as_bytes()).await.unwrap();
+                            return;
+                        }
+                        RUN_COMMAND if payload.contains("404") => {
+                            json!([{ "success": false, "parse_error": false, "error": "No matching node." }])
+                        }
+                        RUN_COMMAND => json!([{ "success": true }]),
+                        _ => return,
+                    };
+                    send(&mut stream, kind, reply.to_string().as_bytes()).await.unwrap();
+                }
+            }
+        });
+        SwayBackend::new(path)
+    }
+
+    fn kinds(log: &Log) -> Vec<u32> {
+        log.lock().unwrap().iter().map(|(kind, _)| *kind).collect()
+    }
+
+    fn subscription(log: &Log) -> String {
+        let log = log.lock().unwrap();
+        log.iter().find(|(kind, _)| *kind == SUBSCRIBE).unwrap().1.clone()
+    }
+
+    #[tokio::test]
+    async fn follows_sway() {
+        let dir = SocketDir::new();
+        let log = Log::default();
+        let backend = serve_ipc(&dir, true, log.clone());
+
+        let (result, states) = fake::watch(&backend).await;
+        assert!(matches!(result, Err(CompositorError::Io(_))), "stream closed");
+        assert_eq!(states.len(), 2);
+        assert_eq!(subscription(&log), r#"["workspace","window","output","input"]"#);
+
+        let first = &states[0];
+        // XWayland windows only have a class
+        assert_eq!(first.active_window.id, Some(13));
+        assert_eq!(first.active_window.app_id, "steam");
+        assert_eq!(first.keyboard_layout.as_deref(), Some("English (US)"));
+        let counts: Vec<(u64, usize)> = first.workspaces.iter().map(|ws| (ws.id, ws.window_count)).collect();
+        assert_eq!(counts, [(10, 3), (15, 0)]);
+        assert_eq!((first.workspaces[0].idx, first.workspaces[1].idx), (1, 1));
+        assert!(first.workspaces[1].is_active && first.workspaces[1].is_urgent);
+        assert_eq!(first.focused_output(), Some("DP-1"));
+        // (id, pid, workspace, focused, floating), the scratchpad is on no workspace
+        let windows: Vec<_> = first
+            .windows
+            .iter()
+            .map(|w| (w.id, w.pid, w.workspace_id, w.is_focused, w.is_floating))
+            .collect();
+        assert_eq!(
+            windows,
+            [
+                (12, Some(1012), Some(10), false, false),
+                (13, Some(1013), Some(10), true, false),
+                (14, Some(1014), Some(10), false, true),
+                (20, Some(1020), None, false, true),
+            ]
+        );
+
+        let names: Vec<&str> = first.outputs.iter().map(|output| output.name.as_str()).collect();
+        assert_eq!(names, ["HDMI-A-1", "DP-1"]);
+        assert_eq!(
+            first.outputs[1].logical,
+            Some(LogicalOutput {
+                x: 1920,
+                y: 0,
+                width: 2560,
+                height: 1440,
+                scale: 1.5
+            })
+        );
+
+        assert_eq!(
+            states[1].active_window,
+            ActiveWindow {
+                id: Some(14),
+                title: "pavucontrol window".to_string(),
+                app_id: "pavucontrol".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn follows_i3() {
+        let dir = SocketDir::new();
+        let log = Log::default();
+        let backend = serve_ipc(&dir, false, log.clone());
+
+        let (result, states) = fake::watch(&backend).await;
+        assert!(matches!(result, Err(CompositorError::Io(_))), "stream closed");
+        assert!(!kinds(&log).contains(&GET_INPUTS));
+        assert_eq!(subscription(&log), r#"["workspace","window","output"]"#);
+        assert_eq!(states.len(), 2);
+
+        let first = &states[0];
+        assert_eq!(first.active_window.id, Some(13));
+        assert_eq!(first.active_window.app_id, "steam");
+        assert_eq!(first.keyboard_layout, None);
+        let counts: Vec<(u64, usize)> = first.workspaces.iter().map(|ws| (ws.id, ws.window_count)).collect();
+        assert_eq!(counts, [(10, 3), (15, 0)]);
+        assert_eq!(first.outputs.len(), 2);
+        assert_eq!(first.outputs[1].logical.map(|logical| logical.scale), Some(1.0));
+
+        assert_eq!(states[1].active_window.id, Some(14));
+        assert_eq!(states[1].active_window.app_id, "pavucontrol");
+    }
+
+    #[tokio::test]
+    async fn runs_commands() {
+        let dir = SocketDir::new();
+        let log = Log::default();
+        let backend = serve_ipc(&dir, true, log.clone());
+
+        backend.execute(CompositorAction::FocusWorkspace(15)).await.unwrap();
+        backend.execute(CompositorAction::CloseWindow(14)).await.unwrap();
+        let err = backend
+            .execute(CompositorAction::MoveWindowToWorkspace {
+                window: 404,
+                workspace: 10,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CompositorError::Rejected(message) if message == "No matching node."));
+        let err = backend.execute(CompositorAction::FocusWorkspace(99)).await.unwrap_err();
+        assert!(matches!(err, CompositorError::Rejected(_)));
+
+        let commands: Vec<String> = log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(kind, _)| *kind == RUN_COMMAND)
+            .map(|(_, payload)| payload.clone())
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                r#"workspace "2""#,
+                "[con_id=14] kill",
+                r#"[con_id=404] move container to workspace "1""#,
+            ]
+        );
+    }
+}
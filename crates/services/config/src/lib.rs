@@ -0,0 +1,182 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+use gpui::{App, AppContext, AsyncApp, Entity, EventEmitter, Global, SharedString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+mod schema;
+mod watcher;
+
+pub use schema::*;
+pub use watcher::DirWatcher;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Parse(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Invalid(path, errors) => {
+                write!(f, "{}: invalid configuration", path.display())?;
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// `$XDG_CONFIG_HOME/nwidgets`, falling back to `~/.config/nwidgets`.
+pub fn config_dir() -> PathBuf {
+    let base = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    base.join("nwidgets")
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// Reads and validates a config file. A missing file yields the defaults.
+pub fn load_from(path: &Path) -> Result<Config, ConfigError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(ConfigError::Io(path.to_path_buf(), err)),
+    };
+
+    let config: Config = toml::from_str(&content).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+
+    let errors = config.validate();
+    if !errors.is_empty() {
+        return Err(ConfigError::Invalid(path.to_path_buf(), errors));
+    }
+
+    Ok(config)
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigChanged;
+
+pub struct ConfigService {
+    pub config: Config,
+    pub path: PathBuf,
+    /// Last load error, kept until the file parses again.
+    pub error: Option<SharedString>,
+}
+
+impl EventEmitter<ConfigChanged> for ConfigService {}
+
+struct GlobalConfigService(Entity<ConfigService>);
+impl Global for GlobalConfigService {}
+
+impl ConfigService {
+    pub fn global(cx: &App) -> Entity<Self> {
+        cx.global::<GlobalConfigService>().0.clone()
+    }
+
+    /// Shortcut for reading the current configuration.
+    pub fn config(cx: &App) -> &Config {
+        &cx.global::<GlobalConfigService>().0.read(cx).config
+    }
+
+    pub fn init(cx: &mut App) -> Entity<Self> {
+        let path = config_path();
+
+        // The first load is synchronous: windows are sized from it right after.
+        let (config, error) = match load_from(&path) {
+            Ok(config) => (config, None),
+            Err(err) => {
+                log::error!("{err}");
+                (Config::default(), Some(SharedString::from(err.to_string())))
+            }
+        };
+
+        let service = cx.new(|_cx| Self {
+            config,
+            path: path.clone(),
+            error,
+        });
+
+        cx.set_global(GlobalConfigService(service.clone()));
+
+        let (tx, mut rx) = mpsc::unbounded::<Result<Config, String>>();
+
+        // Background watcher reloading the file whenever it changes on disk
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let Some(dir) = path.parent().map(Path::to_path_buf) else {
+                return;
+            };
+            let Some(file_name) = path.file_name().map(|name| name.to_os_string()) else {
+                return;
+            };
+
+            if let Err(err) = std::fs::create_dir_all(&dir) {
+                log::warn!("Cannot create {}: {err}, config hot reload disabled", dir.display());
+                return;
+            }
+
+            let watcher = DirWatcher::new(&dir, move |p| p.file_name() == Some(file_name.as_os_str()));
+            let mut watcher = match watcher {
+                Ok(watcher) => watcher,
+                Err(err) => {
+                    log::warn!("Cannot watch {}: {err}", dir.display());
+                    return;
+                }
+            };
+
+            while watcher.changed().await {
+                let result = load_from(&path).map_err(|err| err.to_string());
+                if tx.unbounded_send(result).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        // UI Thread listener
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(result) = rx.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| match result {
+                            Ok(config) => {
+                                srv.error = None;
+                                if srv.config != config {
+                                    log::info!("Reloaded {}", srv.path.display());
+                                    srv.config = config;
+                                    cx.emit(ConfigChanged);
+                                }
+                                cx.notify();
+                            }
+                            Err(err) => {
+                                // Keep running with the last valid configuration
+                                log::error!("{err}");
+                                srv.error = Some(err.into());
+                                cx.notify();
+                            }
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
+        service
+    }
+}
@@ -0,0 +1,293 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub theme: ThemeConfig,
+    pub bar: BarConfig,
+    pub launcher: LauncherConfig,
+    pub notifications: NotificationsConfig,
+    pub osd: OsdConfig,
+    pub system_monitor: SystemMonitorConfig,
+    pub bluetooth: BluetoothConfig,
+    pub ssh: SshConfig,
+    pub power: PowerConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Built-in theme (`nord-dark`, `nord-light`) or a file name from `themes/`.
+    pub name: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "nord-dark".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarComponent {
+    Workspaces,
+    ActiveWindow,
+    Pomodoro,
+    Media,
+    #[serde(rename = "quicksettings")]
+    QuickSettings,
+    #[serde(rename = "datetime")]
+    DateTime,
+    /// Download/upload speeds, not shown by default.
+    #[serde(rename = "netspeed")]
+    NetSpeed,
+    /// CPU load graph, not shown by default.
+    #[serde(rename = "sysgraph")]
+    SysGraph,
+    /// Battery level, hidden on machines without a battery.
+    Battery,
+}
+
+impl BarComponent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BarComponent::Workspaces => "workspaces",
+            BarComponent::ActiveWindow => "active_window",
+            BarComponent::Pomodoro => "pomodoro",
+            BarComponent::Media => "media",
+            BarComponent::QuickSettings => "quicksettings",
+            BarComponent::DateTime => "datetime",
+            BarComponent::NetSpeed => "netspeed",
+            BarComponent::SysGraph => "sysgraph",
+            BarComponent::Battery => "battery",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarConfig {
+    pub height: f32,
+    /// Fixed width; by default each bar spans its whole output.
+    pub width: Option<f32>,
+    pub left: Vec<BarComponent>,
+    pub center: Vec<BarComponent>,
+    pub right: Vec<BarComponent>,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            height: 50.0,
+            width: None,
+            left: vec![BarComponent::Workspaces, BarComponent::ActiveWindow],
+            center: vec![BarComponent::Pomodoro, BarComponent::Media],
+            right: vec![BarComponent::Battery, BarComponent::QuickSettings, BarComponent::DateTime],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LauncherConfig {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for LauncherConfig {
+    fn default() -> Self {
+        Self {
+            width: 700.0,
+            height: 482.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub timeout_secs: u64,
+    pub max_toasts: usize,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 5,
+            max_toasts: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OsdConfig {
+    pub timeout_ms: u64,
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 2000 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystemMonitorConfig {
+    /// Sampling interval while a view shows the stats (panel, bar graphs).
+    pub interval_secs: u64,
+    /// Sampling interval when only background checks need the stats.
+    pub idle_interval_secs: u64,
+    /// Mount points shown in the panel, in order.
+    pub mounts: Vec<String>,
+    /// Free space percentage below which a mount triggers a notification, 0 to disable.
+    pub low_disk_space: u8,
+}
+
+impl Default for SystemMonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 2,
+            idle_interval_secs: 30,
+            mounts: vec!["/".to_string()],
+            low_disk_space: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BluetoothConfig {
+    /// Battery percentage below which a connected device triggers a notification, 0 to disable.
+    pub low_battery: u8,
+}
+
+impl Default for BluetoothConfig {
+    fn default() -> Self {
+        Self { low_battery: 15 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SshConfig {
+    /// systemd unit started and stopped by the panel's SSH toggle.
+    pub unit: String,
+    /// Unit of the user's systemd instance (`systemctl --user`), e.g. an SSH tunnel.
+    pub user: bool,
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            unit: "sshd.service".to_string(),
+            user: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Battery percentage below which a notification warns, 0 to disable.
+    pub low_battery: u8,
+    /// Battery percentage below which an urgent notification warns, 0 to disable.
+    pub critical_battery: u8,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            low_battery: 20,
+            critical_battery: 5,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the values serde cannot express on its own. Each entry is a
+    /// human readable message prefixed with the offending key.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.theme.name.trim().is_empty() {
+            errors.push("theme.name must not be empty".to_string());
+        }
+        check_range(&mut errors, "bar.height", self.bar.height, 20.0, 200.0);
+        if let Some(width) = self.bar.width {
+            check_range(&mut errors, "bar.width", width, 100.0, 16384.0);
+        }
+        check_range(&mut errors, "launcher.width", self.launcher.width, 200.0, 4000.0);
+        check_range(&mut errors, "launcher.height", self.launcher.height, 100.0, 4000.0);
+
+        if self.notifications.timeout_secs == 0 {
+            errors.push("notifications.timeout_secs must be at least 1".to_string());
+        }
+        if !(1..=20).contains(&self.notifications.max_toasts) {
+            errors.push(format!(
+                "notifications.max_toasts must be between 1 and 20 (got {})",
+                self.notifications.max_toasts
+            ));
+        }
+        if self.osd.timeout_ms < 100 {
+            errors.push(format!("osd.timeout_ms must be at least 100 (got {})", self.osd.timeout_ms));
+        }
+        if self.system_monitor.interval_secs == 0 {
+            errors.push("system_monitor.interval_secs must be at least 1".to_string());
+        }
+        if self.system_monitor.idle_interval_secs == 0 {
+            errors.push("system_monitor.idle_interval_secs must be at least 1".to_string());
+        }
+        if self.system_monitor.low_disk_space > 100 {
+            errors.push(format!(
+                "system_monitor.low_disk_space must be between 0 and 100 (got {})",
+                self.system_monitor.low_disk_space
+            ));
+        }
+        if let Some(mount) = self.system_monitor.mounts.iter().find(|mount| !mount.starts_with('/')) {
+            errors.push(format!("system_monitor.mounts: `{mount}` is not an absolute path"));
+        }
+        if self.bluetooth.low_battery > 100 {
+            errors.push(format!(
+                "bluetooth.low_battery must be between 0 and 100 (got {})",
+                self.bluetooth.low_battery
+            ));
+        }
+        for (key, value) in [
+            ("power.low_battery", self.power.low_battery),
+            ("power.critical_battery", self.power.critical_battery),
+        ] {
+            if value > 100 {
+                errors.push(format!("{key} must be between 0 and 100 (got {value})"));
+            }
+        }
+        if self.power.low_battery > 0 && self.power.critical_battery > self.power.low_battery {
+            errors.push(format!(
+                "power.critical_battery ({}) must not be above power.low_battery ({})",
+                self.power.critical_battery, self.power.low_battery
+            ));
+        }
+        if self.ssh.unit.trim().is_empty() {
+            errors.push("ssh.unit must not be empty".to_string());
+        }
+
+        let mut seen: Vec<BarComponent> = Vec::new();
+        for component in self.bar.left.iter().chain(&self.bar.center).chain(&self.bar.right) {
+            if seen.contains(component) {
+                errors.push(format!("bar: component `{}` is listed more than once", component.as_str()));
+            } else {
+                seen.push(*component);
+            }
+        }
+
+        errors
+    }
+}
+
+fn check_range(errors: &mut Vec<String>, key: &str, value: f32, min: f32, max: f32) {
+    if !(min..=max).contains(&value) {
+        errors.push(format!("{key} must be between {min} and {max} (got {value})"));
+    }
+}
@@ -0,0 +1,38 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// Non-recursive directory watcher yielding once per burst of changes.
+///
+/// Directories are watched rather than files because editors usually
+/// replace files atomically, which would orphan a file watch.
+pub struct DirWatcher {
+    _watcher: RecommendedWatcher,
+    rx: UnboundedReceiver<()>,
+}
+
+impl DirWatcher {
+    pub fn new(dir: &Path, filter: impl Fn(&Path) -> bool + Send + 'static) -> notify::Result<Self> {
+        let (tx, rx) = unbounded_channel::<()>();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if !matches!(event.kind, EventKind::Access(_)) && event.paths.iter().any(|p| filter(p)) {
+                    let _ = tx.send(());
+                }
+            }
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self { _watcher: watcher, rx })
+    }
+
+    /// Waits for the next change, letting the burst of write/rename events settle first.
+    pub async fn changed(&mut self) -> bool {
+        if self.rx.recv().await.is_none() {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        while self.rx.try_recv().is_ok() {}
+        true
+    }
+}
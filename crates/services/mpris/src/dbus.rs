@@ -0,0 +1,430 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use zbus::fdo::{DBusProxy, PropertiesProxy};
+use zbus::names::{BusName, InterfaceName};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{proxy, Connection};
+
+use crate::{PlaybackStatus, Player, TrackMetadata};
+
+pub(crate) const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+#[proxy(interface = "org.mpris.MediaPlayer2", default_path = "/org/mpris/MediaPlayer2")]
+trait MediaPlayer2 {
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+}
+
+#[proxy(interface = "org.mpris.MediaPlayer2.Player", default_path = "/org/mpris/MediaPlayer2")]
+pub(crate) trait MprisPlayer {
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+    fn set_position(&self, track_id: &ObjectPath<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn seeked(&self, position: i64) -> zbus::Result<()>;
+}
+
+pub(crate) enum Update {
+    Player(Player),
+    Removed(String),
+}
+
+/// Dictionary entries may come wrapped in an extra variant.
+fn unwrap<'a>(value: &'a Value<'a>) -> &'a Value<'a> {
+    match value {
+        Value::Value(inner) => unwrap(inner),
+        value => value,
+    }
+}
+
+fn string(value: &Value<'_>) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.to_string()),
+        Value::ObjectPath(path) => Some(path.to_string()),
+        Value::Value(inner) => string(inner),
+        _ => None,
+    }
+}
+
+fn integer(value: &Value<'_>) -> Option<i64> {
+    match *value {
+        Value::I64(n) => Some(n),
+        Value::U64(n) => i64::try_from(n).ok(),
+        Value::I32(n) => Some(n.into()),
+        Value::U32(n) => Some(n.into()),
+        Value::F64(n) => Some(n as i64),
+        Value::Value(ref inner) => integer(inner),
+        _ => None,
+    }
+}
+
+fn micros(value: i64) -> Duration {
+    Duration::from_micros(value.max(0) as u64)
+}
+
+/// Reads the `Metadata` dictionary (`xesam:*` and `mpris:*` keys).
+pub fn parse_metadata(metadata: &HashMap<String, OwnedValue>) -> TrackMetadata {
+    let get = |key: &str| metadata.get(key).map(|value| unwrap(value));
+
+    let artists = match get("xesam:artist") {
+        Some(Value::Array(array)) => array.iter().filter_map(string).collect(),
+        // Some players send a single string
+        Some(value) => string(value).into_iter().collect(),
+        None => Vec::new(),
+    };
+
+    TrackMetadata {
+        track_id: get("mpris:trackid").and_then(string),
+        title: get("xesam:title").and_then(string).unwrap_or_default(),
+        artists,
+        album: get("xesam:album").and_then(string).unwrap_or_default(),
+        art_url: get("mpris:artUrl").and_then(string).filter(|url| !url.is_empty()),
+        length: get("mpris:length").and_then(integer).map(micros),
+    }
+}
+
+/// Builds a player from the `GetAll` reply of its `Player` interface.
+pub fn parse_player(bus_name: &str, identity: String, properties: &HashMap<String, OwnedValue>) -> Player {
+    let get = |key: &str| properties.get(key).map(|value| unwrap(value));
+    let flag = |key: &str| matches!(get(key), Some(Value::Bool(true)));
+
+    let metadata = match get("Metadata") {
+        Some(Value::Dict(dict)) => {
+            let map: HashMap<String, OwnedValue> = dict
+                .iter()
+                .filter_map(|(key, value)| Some((string(key)?, value.try_to_owned().ok()?)))
+                .collect();
+            parse_metadata(&map)
+        }
+        _ => TrackMetadata::default(),
+    };
+
+    let status = match get("PlaybackStatus").and_then(string).as_deref() {
+        Some("Playing") => PlaybackStatus::Playing,
+        Some("Paused") => PlaybackStatus::Paused,
+        _ => PlaybackStatus::Stopped,
+    };
+
+    Player {
+        bus_name: bus_name.to_string(),
+        identity,
+        status,
+        metadata,
+        position: get("Position").and_then(integer).map(micros).unwrap_or_default(),
+        position_at: Instant::now(),
+        rate: match get("Rate") {
+            Some(Value::F64(rate)) => *rate,
+            _ => 1.0,
+        },
+        can_play: flag("CanPlay"),
+        can_pause: flag("CanPause"),
+        can_go_next: flag("CanGoNext"),
+        can_go_previous: flag("CanGoPrevious"),
+        can_seek: flag("CanSeek"),
+    }
+}
+
+async fn fetch_player(properties: &PropertiesProxy<'_>, bus_name: &str, identity: &str) -> zbus::Result<Player> {
+    let interface = InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE);
+    let all = properties.get_all(Some(interface).into()).await?;
+    Ok(parse_player(bus_name, identity.to_string(), &all))
+}
+
+/// Follows one player until it leaves the bus.
+async fn watch_player(connection: Connection, bus_name: String, tx: mpsc::UnboundedSender<Update>) -> zbus::Result<()> {
+    let identity = MediaPlayer2Proxy::builder(&connection)
+        .destination(bus_name.clone())?
+        .build()
+        .await?
+        .identity()
+        .await
+        .unwrap_or_else(|_| bus_name.trim_start_matches(BUS_PREFIX).to_string());
+
+    let properties = PropertiesProxy::builder(&connection)
+        .destination(bus_name.clone())?
+        .path(OBJECT_PATH)?
+        .build()
+        .await?;
+    let player = MprisPlayerProxy::builder(&connection)
+        .destination(bus_name.clone())?
+        .build()
+        .await?;
+
+    // Subscribed before the first read so that no change is missed
+    let mut changes = properties.receive_properties_changed().await?;
+    let mut seeks = player.receive_seeked().await?;
+
+    let mut state = fetch_player(&properties, &bus_name, &identity).await?;
+    if tx.unbounded_send(Update::Player(state.clone())).is_err() {
+        return Ok(());
+    }
+
+    loop {
+        tokio::select! {
+            Some(_) = changes.next() => {
+                // `Position` never triggers PropertiesChanged: re-read everything
+                state = fetch_player(&properties, &bus_name, &identity).await?;
+            }
+            Some(seeked) = seeks.next() => {
+                let Ok(args) = seeked.args() else { continue };
+                state.position = micros(args.position);
+                state.position_at = Instant::now();
+            }
+            else => break,
+        }
+        if tx.unbounded_send(Update::Player(state.clone())).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Tracks every `org.mpris.MediaPlayer2.*` name on `connection`.
+///
+/// Taking the connection lets the service run against a private bus.
+pub(crate) async fn watch(connection: Connection, tx: mpsc::UnboundedSender<Update>) -> zbus::Result<()> {
+    let dbus = DBusProxy::new(&connection).await?;
+    let mut owners = dbus.receive_name_owner_changed().await?;
+    let mut players: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    let spawn_player = |name: String| {
+        let connection = connection.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            if let Err(err) = watch_player(connection, name.clone(), tx.clone()).await {
+                log::debug!("MPRIS player {name}: {err}");
+            }
+        })
+    };
+
+    for name in dbus.list_names().await? {
+        if name.starts_with(BUS_PREFIX) {
+            players.insert(name.to_string(), spawn_player(name.to_string()));
+        }
+    }
+
+    while let Some(signal) = owners.next().await {
+        let Ok(args) = signal.args() else { continue };
+        let BusName::WellKnown(name) = args.name() else { continue };
+        if !name.starts_with(BUS_PREFIX) {
+            continue;
+        }
+        let name = name.to_string();
+
+        if let Some(task) = players.remove(&name) {
+            task.abort();
+            if tx.unbounded_send(Update::Removed(name.clone())).is_err() {
+                break;
+            }
+        }
+        // A new owner: the player (re)appeared
+        if args.new_owner().is_some() {
+            players.insert(name.clone(), spawn_player(name));
+        }
+    }
+
+    for task in players.into_values() {
+        task.abort();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MprisState;
+    use nwidgets_dbus_test::Bus;
+    use zbus::interface;
+    use zbus::object_server::SignalContext;
+
+    const VLC: &str = "org.mpris.MediaPlayer2.vlc";
+    const SPOTIFY: &str = "org.mpris.MediaPlayer2.spotify";
+
+    struct FakeRoot {
+        identity: &'static str,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2")]
+    impl FakeRoot {
+        #[zbus(property)]
+        fn identity(&self) -> &str {
+            self.identity
+        }
+    }
+
+    struct FakePlayer {
+        status: &'static str,
+        track: u32,
+        title: &'static str,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> &str {
+            self.status
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let track_id = format!("/org/mpris/MediaPlayer2/Track/{}", self.track);
+            [
+                ("mpris:trackid", Value::from(ObjectPath::try_from(track_id).unwrap())),
+                ("mpris:length", Value::from(215_000_000i64)),
+                ("mpris:artUrl", Value::from("file:///tmp/cover.png")),
+                ("xesam:title", Value::from(self.title)),
+                ("xesam:artist", Value::from(vec!["Daft Punk"])),
+                ("xesam:album", Value::from("Discovery")),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.try_to_owned().unwrap()))
+            .collect()
+        }
+
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            12_000_000
+        }
+
+        #[zbus(property)]
+        fn rate(&self) -> f64 {
+            1.0
+        }
+
+        #[zbus(property)]
+        fn can_play(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_pause(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_go_next(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn can_go_previous(&self) -> bool {
+            false
+        }
+
+        #[zbus(property)]
+        fn can_seek(&self) -> bool {
+            true
+        }
+
+        #[zbus(signal)]
+        async fn seeked(ctx: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+    }
+
+    /// Connects a player owning `name` on the bus.
+    async fn start_player(bus: &Bus, name: &str, identity: &'static str, status: &'static str) -> Connection {
+        let connection = bus.connect().await;
+        let player = FakePlayer {
+            status,
+            track: 1,
+            title: "One More Time",
+        };
+        connection.object_server().at(OBJECT_PATH, FakeRoot { identity }).await.unwrap();
+        connection.object_server().at(OBJECT_PATH, player).await.unwrap();
+        connection.request_name(name).await.unwrap();
+        connection
+    }
+
+    /// Applies updates to `state` until `done` holds.
+    async fn until(
+        rx: &mut mpsc::UnboundedReceiver<Update>,
+        state: &mut MprisState,
+        done: impl Fn(&MprisState) -> bool,
+    ) {
+        let wait = async {
+            while !done(state) {
+                state.apply(rx.next().await.expect("watcher stopped"));
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), wait).await.expect("no matching update within 5 s");
+    }
+
+    fn active(state: &MprisState) -> Option<&str> {
+        state.active.as_deref()
+    }
+
+    #[tokio::test]
+    async fn follows_fake_players() {
+        let bus = Bus::start();
+        let vlc = start_player(&bus, VLC, "VLC media player", "Paused").await;
+
+        let (tx, mut rx) = mpsc::unbounded();
+        tokio::spawn(watch(bus.connect().await, tx));
+        let mut state = MprisState::default();
+        until(&mut rx, &mut state, |state| !state.players.is_empty()).await;
+
+        // The only player is active even while paused
+        assert_eq!(active(&state), Some(VLC));
+        let player = state.active_player().unwrap();
+        assert_eq!(player.identity, "VLC media player");
+        assert_eq!(player.status, PlaybackStatus::Paused);
+        assert_eq!(player.position, Duration::from_secs(12));
+        assert!(player.can_pause && player.can_go_next && !player.can_go_previous);
+        assert_eq!(
+            player.metadata,
+            TrackMetadata {
+                track_id: Some("/org/mpris/MediaPlayer2/Track/1".to_string()),
+                title: "One More Time".to_string(),
+                artists: vec!["Daft Punk".to_string()],
+                album: "Discovery".to_string(),
+                art_url: Some("file:///tmp/cover.png".to_string()),
+                length: Some(Duration::from_secs(215)),
+            }
+        );
+
+        // A player that starts playing takes over
+        let spotify = start_player(&bus, SPOTIFY, "Spotify", "Playing").await;
+        until(&mut rx, &mut state, |state| active(state) == Some(SPOTIFY)).await;
+        assert_eq!(state.players.len(), 2);
+
+        let vlc_player = vlc.object_server().interface::<_, FakePlayer>(OBJECT_PATH).await.unwrap();
+        {
+            let mut player = vlc_player.get_mut().await;
+            player.track = 2;
+            player.title = "Aerodynamic";
+        }
+        vlc_player.get().await.metadata_changed(vlc_player.signal_context()).await.unwrap();
+        until(&mut rx, &mut state, |state| {
+            state.players.iter().any(|player| player.metadata.title == "Aerodynamic")
+        })
+        .await;
+        // Paused players do not take over
+        assert_eq!(active(&state), Some(SPOTIFY));
+
+        vlc_player.get_mut().await.status = "Playing";
+        vlc_player.get().await.playback_status_changed(vlc_player.signal_context()).await.unwrap();
+        until(&mut rx, &mut state, |state| active(state) == Some(VLC)).await;
+        let track_id = state.active_player().unwrap().metadata.track_id.clone();
+        assert_eq!(track_id.as_deref(), Some("/org/mpris/MediaPlayer2/Track/2"));
+
+        let spotify_player = spotify.object_server().interface::<_, FakePlayer>(OBJECT_PATH).await.unwrap();
+        FakePlayer::seeked(spotify_player.signal_context(), 90_000_000).await.unwrap();
+        until(&mut rx, &mut state, |state| {
+            state.players.iter().any(|player| player.bus_name == SPOTIFY && player.position == Duration::from_secs(90))
+        })
+        .await;
+
+        // The active player quits: the other one takes over
+        drop(vlc_player);
+        vlc.release_name(VLC).await.unwrap();
+        until(&mut rx, &mut state, |state| state.players.len() == 1).await;
+        assert_eq!(active(&state), Some(SPOTIFY));
+    }
+}
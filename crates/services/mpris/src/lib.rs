@@ -0,0 +1,257 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+use gpui::{App, AppContext, AsyncApp, Entity, EventEmitter, Global};
+use std::time::{Duration, Instant};
+use zbus::zvariant::ObjectPath;
+use zbus::Connection;
+
+mod dbus;
+
+pub use dbus::{parse_metadata, parse_player};
+
+use dbus::{MprisPlayerProxy, Update};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrackMetadata {
+    pub track_id: Option<String>,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub art_url: Option<String>,
+    pub length: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    /// `org.mpris.MediaPlayer2.<name>`.
+    pub bus_name: String,
+    /// Human readable name, e.g. `Spotify`.
+    pub identity: String,
+    pub status: PlaybackStatus,
+    pub metadata: TrackMetadata,
+    /// Position at `position_at`; see [`Player::position_now`].
+    pub position: Duration,
+    pub position_at: Instant,
+    pub rate: f64,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_seek: bool,
+}
+
+impl Player {
+    /// Players only report their position on seeks, so it is extrapolated
+    /// from the last known value while playing.
+    pub fn position_now(&self) -> Duration {
+        let mut position = self.position;
+        if self.status == PlaybackStatus::Playing {
+            position += self.position_at.elapsed().mul_f64(self.rate.max(0.0));
+        }
+        match self.metadata.length {
+            Some(length) => position.min(length),
+            None => position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MprisState {
+    pub players: Vec<Player>,
+    /// Player shown in the bar: the last one that started playing.
+    pub active: Option<String>,
+}
+
+impl MprisState {
+    pub fn active_player(&self) -> Option<&Player> {
+        let name = self.active.as_deref()?;
+        self.players.iter().find(|player| player.bus_name == name)
+    }
+
+    /// Applies an update from the bus. A player that starts playing becomes
+    /// the active one.
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Player(player) => {
+                let started = player.status == PlaybackStatus::Playing
+                    && self
+                        .players
+                        .iter()
+                        .find(|p| p.bus_name == player.bus_name)
+                        .is_none_or(|p| p.status != PlaybackStatus::Playing);
+                if started {
+                    self.active = Some(player.bus_name.clone());
+                }
+                match self.players.iter_mut().find(|p| p.bus_name == player.bus_name) {
+                    Some(existing) => *existing = player,
+                    None => self.players.push(player),
+                }
+            }
+            Update::Removed(name) => self.players.retain(|p| p.bus_name != name),
+        }
+        self.pick_active();
+    }
+
+    fn pick_active(&mut self) {
+        if self.active_player().is_some() {
+            return;
+        }
+        self.active = self
+            .players
+            .iter()
+            .find(|player| player.status == PlaybackStatus::Playing)
+            .or(self.players.first())
+            .map(|player| player.bus_name.clone());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MprisStateChanged;
+
+pub struct MprisService {
+    pub state: MprisState,
+    connection: Option<Connection>,
+}
+
+impl EventEmitter<MprisStateChanged> for MprisService {}
+
+struct GlobalMprisService(Entity<MprisService>);
+impl Global for GlobalMprisService {}
+
+impl MprisService {
+    pub fn global(cx: &App) -> Entity<Self> {
+        cx.global::<GlobalMprisService>().0.clone()
+    }
+
+    pub fn init(cx: &mut App) -> Entity<Self> {
+        let service = cx.new(|_cx| Self {
+            state: MprisState::default(),
+            connection: None,
+        });
+
+        cx.set_global(GlobalMprisService(service.clone()));
+
+        let (tx, mut rx) = mpsc::unbounded::<Update>();
+        let (connection_tx, mut connection_rx) = mpsc::unbounded::<Connection>();
+
+        // Background Tokio task following the players on the session bus
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let connection = match Connection::session().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    log::warn!("MPRIS disabled, no session bus: {err}");
+                    return;
+                }
+            };
+            let _ = connection_tx.unbounded_send(connection.clone());
+            if let Err(err) = dbus::watch(connection, tx).await {
+                log::warn!("MPRIS: {err}");
+            }
+        })
+        .detach();
+
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                if let Some(connection) = connection_rx.next().await {
+                    let _ = cx.update(|cx| service_entity.update(cx, |srv, _cx| srv.connection = Some(connection)));
+                }
+            }
+        })
+        .detach();
+
+        // UI handler reading updates from MPSC channel
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(update) = rx.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| {
+                            let mut state = srv.state.clone();
+                            state.apply(update);
+                            if srv.state != state {
+                                srv.state = state;
+                                cx.emit(MprisStateChanged);
+                                cx.notify();
+                            }
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
+        service
+    }
+
+    fn call<F, Fut>(&self, bus_name: &str, cx: &App, f: F)
+    where
+        F: FnOnce(MprisPlayerProxy<'static>) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = zbus::Result<()>> + Send,
+    {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        let bus_name = bus_name.to_string();
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let proxy = match MprisPlayerProxy::builder(&connection).destination(bus_name.clone()) {
+                Ok(builder) => builder.build().await,
+                Err(err) => Err(err),
+            };
+            let result = match proxy {
+                Ok(proxy) => f(proxy).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                log::warn!("MPRIS {bus_name}: {err}");
+            }
+        })
+        .detach();
+    }
+
+    pub fn play_pause(&self, bus_name: &str, cx: &App) {
+        self.call(bus_name, cx, |player| async move { player.play_pause().await });
+    }
+
+    pub fn next(&self, bus_name: &str, cx: &App) {
+        self.call(bus_name, cx, |player| async move { player.next().await });
+    }
+
+    pub fn previous(&self, bus_name: &str, cx: &App) {
+        self.call(bus_name, cx, |player| async move { player.previous().await });
+    }
+
+    /// Moves the playback position by `offset_secs`, negative to go back.
+    pub fn seek(&self, bus_name: &str, offset_secs: i64, cx: &App) {
+        let offset = offset_secs.saturating_mul(1_000_000);
+        self.call(bus_name, cx, move |player| async move { player.seek(offset).await });
+    }
+
+    /// Jumps to `position` in the current track.
+    pub fn set_position(&self, bus_name: &str, position: Duration, cx: &App) {
+        let track_id = self
+            .state
+            .players
+            .iter()
+            .find(|player| player.bus_name == bus_name)
+            .and_then(|player| player.metadata.track_id.clone());
+        let Some(track_id) = track_id else {
+            return;
+        };
+        let position = position.as_micros().min(i64::MAX as u128) as i64;
+        self.call(bus_name, cx, move |player| async move {
+            let track_id = ObjectPath::try_from(track_id.as_str())?;
+            player.set_position(&track_id, position).await
+        });
+    }
+}
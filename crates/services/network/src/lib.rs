@@ -1,30 +1,209 @@
-use futures::channel::mpsc;
+use futures::channel::{mpsc, oneshot};
 use futures::StreamExt;
 use gpui::{App, AppContext, AsyncApp, Context, Entity, EventEmitter, Global};
-use tokio::process::Command;
+use std::collections::HashMap;
+use zbus::Connection;
+
+mod nm;
+mod secret_agent;
+
+pub use nm::{parse_profile, NmModel, SavedProfile};
+pub use secret_agent::SecretPrompt;
+
+use secret_agent::SecretEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiSecurity {
+    Open,
+    Wep,
+    /// WPA/WPA2 personal (or WPA3 transition).
+    Wpa,
+    /// WPA3 personal (SAE) only.
+    Wpa3,
+    /// 802.1X, needs more than a password.
+    Enterprise,
+}
+
+impl WifiSecurity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WifiSecurity::Open => "Open",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::Wpa => "WPA2",
+            WifiSecurity::Wpa3 => "WPA3",
+            WifiSecurity::Enterprise => "802.1X",
+        }
+    }
+
+    pub fn is_secure(&self) -> bool {
+        *self != WifiSecurity::Open
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WifiNetwork {
+    /// Object path of the strongest access point of this SSID.
+    pub path: String,
+    /// Object path of the wifi device seeing it.
+    pub device: String,
     pub ssid: String,
     pub signal: u8,
     pub active: bool,
+    pub security: WifiSecurity,
+    /// A saved connection exists for this SSID.
+    pub known: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedNetwork {
+    /// Object path of the saved connection.
+    pub path: String,
+    pub ssid: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VpnKind {
+    WireGuard,
+    /// NetworkManager VPN plugin, e.g. `openvpn`.
+    Plugin(String),
+}
+
+impl VpnKind {
+    pub fn label(&self) -> &str {
+        match self {
+            VpnKind::WireGuard => "WireGuard",
+            VpnKind::Plugin(name) if name.is_empty() => "VPN",
+            VpnKind::Plugin(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VpnState {
+    Disconnected,
+    Connecting,
+    Connected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VpnConnection {
+    /// Object path of the saved connection.
+    pub path: String,
+    pub id: String,
+    pub uuid: String,
+    pub kind: VpnKind,
+    /// Object path of the active connection, while connecting or connected.
+    pub active: Option<String>,
+    pub state: VpnState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Connectivity {
+    #[default]
+    Unknown,
+    /// No route to the internet.
+    None,
+    /// Behind a captive portal.
+    Portal,
+    /// Connected, but the internet is unreachable.
+    Limited,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionType {
+    Wired,
+    Wifi,
+    Vpn,
+    Other,
+}
+
+impl ConnectionType {
+    /// From a NetworkManager connection type, e.g. `802-11-wireless`.
+    pub fn from_nm(kind: &str) -> Self {
+        match kind {
+            "802-3-ethernet" => ConnectionType::Wired,
+            "802-11-wireless" => ConnectionType::Wifi,
+            "vpn" | "wireguard" => ConnectionType::Vpn,
+            _ => ConnectionType::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Ethernet,
+    Wifi,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkDevice {
+    pub path: String,
+    /// Interface name, e.g. `wlan0`.
+    pub interface: String,
+    pub kind: DeviceKind,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveConnection {
+    pub path: String,
+    /// Connection name, e.g. the SSID or "Wired connection 1".
+    pub id: String,
+    pub uuid: String,
+    pub kind: ConnectionType,
+    /// `false` while still connecting.
+    pub activated: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct NetworkState {
+    pub networking_enabled: bool,
     pub wifi_enabled: bool,
+    pub connectivity: Connectivity,
+    /// Type of the connection holding the default route.
+    pub primary: Option<ConnectionType>,
     pub active_ssid: Option<String>,
+    /// Signal of the connected access point, in percent.
+    pub wifi_strength: Option<u8>,
+    pub devices: Vec<NetworkDevice>,
+    pub active_connections: Vec<ActiveConnection>,
+    /// Visible wifi networks, connected one first, then by signal.
     pub networks: Vec<WifiNetwork>,
+    /// Saved wifi connections.
+    pub saved: Vec<SavedNetwork>,
+    /// VPN and WireGuard connections, by name.
+    pub vpns: Vec<VpnConnection>,
+}
+
+impl NetworkState {
+    pub fn vpn_connected(&self) -> bool {
+        self.vpns.iter().any(|vpn| vpn.state == VpnState::Connected)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct NetworkStateChanged;
 
+/// A secret request appeared or went away; see [`NetworkService::secret_prompt`].
+#[derive(Debug, Clone)]
+pub struct NetworkPromptChanged;
+
 pub struct NetworkService {
     pub state: NetworkState,
+    /// SSID being connected to.
+    pub connecting: Option<String>,
+    /// Last connection error of each SSID or VPN name, cleared by the next attempt.
+    pub errors: HashMap<String, String>,
+    /// Pending request of the secret agent.
+    pub secret_prompt: Option<SecretPrompt>,
+    secret_reply: Option<oneshot::Sender<Option<String>>>,
+    connection: Option<Connection>,
 }
 
 impl EventEmitter<NetworkStateChanged> for NetworkService {}
+impl EventEmitter<NetworkPromptChanged> for NetworkService {}
 
 struct GlobalNetworkService(Entity<NetworkService>);
 impl Global for GlobalNetworkService {}
@@ -37,44 +216,46 @@ impl NetworkService {
     pub fn init(cx: &mut App) -> Entity<Self> {
         let service = cx.new(|_cx| Self {
             state: NetworkState::default(),
+            connecting: None,
+            errors: HashMap::new(),
+            secret_prompt: None,
+            secret_reply: None,
+            connection: None,
         });
 
         cx.set_global(GlobalNetworkService(service.clone()));
 
         let (tx, mut rx) = mpsc::unbounded::<NetworkState>();
+        let (connection_tx, mut connection_rx) = mpsc::unbounded::<Connection>();
+        let (secret_tx, mut secret_rx) = mpsc::unbounded::<SecretEvent>();
 
-        // Background worker to query nmcli
+        // Background worker following NetworkManager on the system bus
         gpui_tokio::Tokio::spawn(cx, async move {
-            let mut state = NetworkState::default();
-
-            if let Ok(out) = Command::new("nmcli").args(["radio", "wifi"]).output().await {
-                let s = String::from_utf8_lossy(&out.stdout);
-                state.wifi_enabled = s.trim() == "enabled";
+            let connection = match Connection::system().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    log::warn!("Network disabled, no system bus: {err}");
+                    return;
+                }
+            };
+            let _ = connection_tx.unbounded_send(connection.clone());
+            if let Err(err) = secret_agent::serve(&connection, secret_tx).await {
+                log::warn!("Cannot export the NetworkManager secret agent: {err}");
+            }
+            if let Err(err) = nm::watch(connection, tx).await {
+                log::warn!("NetworkManager: {err}");
             }
+        })
+        .detach();
 
-            if let Ok(out) = Command::new("nmcli").args(["-t", "-f", "IN-USE,SSID,SIGNAL", "dev", "wifi"]).output().await {
-                let s = String::from_utf8_lossy(&out.stdout);
-                for line in s.lines() {
-                    let parts: Vec<&str> = line.split(':').collect();
-                    if parts.len() >= 3 {
-                        let active = parts[0] == "*";
-                        let ssid = parts[1].to_string();
-                        let signal = parts[2].parse::<u8>().unwrap_or(0);
-                        if !ssid.is_empty() {
-                            if active {
-                                state.active_ssid = Some(ssid.clone());
-                            }
-                            state.networks.push(WifiNetwork {
-                                ssid,
-                                signal,
-                                active,
-                            });
-                        }
-                    }
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                if let Some(connection) = connection_rx.next().await {
+                    let _ = cx.update(|cx| service_entity.update(cx, |srv, _cx| srv.connection = Some(connection)));
                 }
             }
-
-            let _ = tx.unbounded_send(state);
         })
         .detach();
 
@@ -98,19 +279,176 @@ impl NetworkService {
         })
         .detach();
 
+        // Secret agent requests
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(event) = secret_rx.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| match event {
+                            SecretEvent::Prompt(prompt, reply) => {
+                                // A new request replaces (and cancels) the previous one
+                                srv.secret_prompt = Some(prompt);
+                                srv.secret_reply = Some(reply);
+                                cx.emit(NetworkPromptChanged);
+                                cx.notify();
+                            }
+                            SecretEvent::Cancel => srv.answer_secret(None, cx),
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
         service
     }
 
     pub fn toggle_wifi(&mut self, cx: &mut Context<Self>) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
         self.state.wifi_enabled = !self.state.wifi_enabled;
+        cx.emit(NetworkStateChanged);
+        cx.notify();
+        let enabled = self.state.wifi_enabled;
+        // NetworkManager's PropertiesChanged confirms (or reverts) the new state
+        gpui_tokio::Tokio::spawn(cx, async move {
+            if let Err(err) = nm::set_wireless_enabled(&connection, enabled).await {
+                log::warn!("Cannot toggle wifi: {err}");
+            }
+        })
+        .detach();
+    }
+
+    /// Connects to `ssid`, through its saved connection when there is one.
+    /// Without a saved connection, `password` is used for secured networks.
+    pub fn connect(&mut self, ssid: &str, password: Option<String>, cx: &mut Context<Self>) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        let Some(network) = self.state.networks.iter().find(|network| network.ssid == ssid).cloned() else {
+            return;
+        };
+        let saved = self
+            .state
+            .saved
+            .iter()
+            .find(|saved| saved.ssid == ssid)
+            .map(|saved| saved.path.clone());
+
+        self.connecting = Some(ssid.to_string());
+        self.errors.remove(ssid);
+        cx.notify();
+
+        let (result_tx, result_rx) = oneshot::channel();
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let result = nm::connect(&connection, &network, saved.as_deref(), password).await;
+            let _ = result_tx.send(result.map_err(|err| err.to_string()));
+        })
+        .detach();
+
+        let ssid = ssid.to_string();
+        cx.spawn(async move |this, cx| {
+            let result = result_rx.await.unwrap_or_else(|_| Err("cancelled".to_string()));
+            let _ = this.update(cx, |srv, cx| {
+                if srv.connecting.as_deref() == Some(ssid.as_str()) {
+                    srv.connecting = None;
+                }
+                if let Err(error) = result {
+                    log::warn!("Cannot connect to {ssid}: {error}");
+                    srv.errors.insert(ssid, error);
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    /// Deletes the saved connections of `ssid`.
+    pub fn forget(&mut self, ssid: &str, cx: &mut Context<Self>) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        let paths: Vec<String> = self
+            .state
+            .saved
+            .iter()
+            .filter(|saved| saved.ssid == ssid)
+            .map(|saved| saved.path.clone())
+            .collect();
+        gpui_tokio::Tokio::spawn(cx, async move {
+            if let Err(err) = nm::forget(&connection, &paths).await {
+                log::warn!("Cannot forget network: {err}");
+            }
+        })
+        .detach();
+    }
+
+    pub fn rescan(&self, cx: &App) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        let devices: Vec<String> = self
+            .state
+            .devices
+            .iter()
+            .filter(|device| device.kind == DeviceKind::Wifi)
+            .map(|device| device.path.clone())
+            .collect();
+        gpui_tokio::Tokio::spawn(cx, async move {
+            // NetworkManager refuses scans requested too often, which is harmless
+            if let Err(err) = nm::rescan(&connection, &devices).await {
+                log::debug!("Wifi scan: {err}");
+            }
+        })
+        .detach();
+    }
+
+    /// Connects the VPN at `path`, or disconnects it when it is active.
+    pub fn toggle_vpn(&mut self, path: &str, cx: &mut Context<Self>) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        let Some(vpn) = self.state.vpns.iter().find(|vpn| vpn.path == path).cloned() else {
+            return;
+        };
+        self.errors.remove(&vpn.id);
         cx.notify();
-        let target_state = if self.state.wifi_enabled { "on" } else { "off" };
+
+        let (result_tx, result_rx) = oneshot::channel();
         gpui_tokio::Tokio::spawn(cx, async move {
-            let _ = Command::new("nmcli")
-                .args(["radio", "wifi", target_state])
-                .status()
-                .await;
+            let result = match &vpn.active {
+                Some(active) => nm::deactivate(&connection, active).await,
+                None => nm::activate(&connection, &vpn.path).await,
+            };
+            let _ = result_tx.send(result.map_err(|err| err.to_string()));
+        })
+        .detach();
+
+        let id = vpn.id;
+        cx.spawn(async move |this, cx| {
+            let Ok(Err(error)) = result_rx.await else {
+                return;
+            };
+            log::warn!("Cannot toggle VPN {id}: {error}");
+            let _ = this.update(cx, |srv, cx| {
+                srv.errors.insert(id, error);
+                cx.notify();
+            });
         })
         .detach();
     }
+
+    /// Answers the current secret request, `None` to cancel it.
+    pub fn answer_secret(&mut self, secret: Option<String>, cx: &mut Context<Self>) {
+        if let Some(reply) = self.secret_reply.take() {
+            let _ = reply.send(secret);
+        }
+        if self.secret_prompt.take().is_some() {
+            cx.emit(NetworkPromptChanged);
+            cx.notify();
+        }
+    }
 }
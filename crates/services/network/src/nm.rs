@@ -0,0 +1,770 @@
+//! `org.freedesktop.NetworkManager` client: objects come from the
+//! ObjectManager and are kept up to date from InterfacesAdded/Removed and
+//! PropertiesChanged, saved connections from their Updated signal.
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use zbus::fdo::{DBusProxy, ObjectManagerProxy};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{proxy, Connection, MatchRule, MessageStream};
+
+use crate::{
+    secret_agent, ActiveConnection, ConnectionType, Connectivity, DeviceKind, NetworkDevice, NetworkState,
+    SavedNetwork, VpnConnection, VpnKind, VpnState, WifiNetwork, WifiSecurity,
+};
+
+const NM: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const DEVICE_INTERFACE: &str = "org.freedesktop.NetworkManager.Device";
+const WIRELESS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const ACCESS_POINT_INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const ACTIVE_INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const SETTINGS_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+
+/// `NM_DEVICE_TYPE_*`
+const DEVICE_TYPE_ETHERNET: u32 = 1;
+const DEVICE_TYPE_WIFI: u32 = 2;
+/// `NM_DEVICE_STATE_ACTIVATED`
+const DEVICE_STATE_ACTIVATED: u32 = 100;
+/// `NM_ACTIVE_CONNECTION_STATE_ACTIVATED`
+const ACTIVE_STATE_ACTIVATED: u32 = 2;
+/// `NM_802_11_AP_FLAGS_PRIVACY`
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+/// `NM_802_11_AP_SEC_KEY_MGMT_*`
+const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+const AP_SEC_KEY_MGMT_EAP_SUITE_B_192: u32 = 0x2000;
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait NetworkManager {
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn add_and_activate_connection(
+        &self,
+        connection: HashMap<&str, HashMap<&str, Value<'_>>>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn set_wireless_enabled(&self, enabled: bool) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+trait Settings {
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait SettingsConnection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, Properties>>;
+    fn delete(&self) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wireless",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Wireless {
+    fn request_scan(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+}
+
+/// Saved connection shown by nwidgets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SavedProfile {
+    Wifi { ssid: String },
+    Vpn { id: String, uuid: String, kind: VpnKind },
+}
+
+/// Every NetworkManager object, by path and interface.
+#[derive(Default)]
+pub struct NmModel {
+    objects: BTreeMap<String, HashMap<String, Properties>>,
+    /// Saved wifi and VPN connections, by settings path.
+    profiles: BTreeMap<String, SavedProfile>,
+}
+
+fn unwrap<'a>(value: &'a Value<'a>) -> &'a Value<'a> {
+    match value {
+        Value::Value(inner) => unwrap(inner),
+        value => value,
+    }
+}
+
+fn get_str(props: &Properties, key: &str) -> Option<String> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::Str(s)) if !s.is_empty() => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+fn get_bool(props: &Properties, key: &str) -> bool {
+    matches!(props.get(key).map(|value| unwrap(value)), Some(Value::Bool(true)))
+}
+
+fn get_u32(props: &Properties, key: &str) -> u32 {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::U32(n)) => *n,
+        _ => 0,
+    }
+}
+
+fn get_u8(props: &Properties, key: &str) -> u8 {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::U8(n)) => *n,
+        _ => 0,
+    }
+}
+
+/// Object path property; NetworkManager uses `/` for "none".
+fn get_path(props: &Properties, key: &str) -> Option<String> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::ObjectPath(path)) if path.as_str() != "/" => Some(path.to_string()),
+        _ => None,
+    }
+}
+
+fn get_paths(props: &Properties, key: &str) -> Vec<String> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::Array(array)) => array
+            .iter()
+            .filter_map(|value| match value {
+                Value::ObjectPath(path) => Some(path.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn get_bytes(props: &Properties, key: &str) -> Vec<u8> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::Array(array)) => array
+            .iter()
+            .filter_map(|value| match value {
+                Value::U8(byte) => Some(*byte),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Reads the settings of a saved connection, `None` for the types nwidgets ignores.
+pub fn parse_profile(settings: &HashMap<String, Properties>) -> Option<SavedProfile> {
+    let connection = settings.get("connection")?;
+    match get_str(connection, "type")?.as_str() {
+        "802-11-wireless" => {
+            let ssid = String::from_utf8_lossy(&get_bytes(settings.get("802-11-wireless")?, "ssid")).into_owned();
+            (!ssid.is_empty()).then_some(SavedProfile::Wifi { ssid })
+        }
+        kind @ ("vpn" | "wireguard") => Some(SavedProfile::Vpn {
+            id: get_str(connection, "id").unwrap_or_default(),
+            uuid: get_str(connection, "uuid")?,
+            kind: match kind {
+                "wireguard" => VpnKind::WireGuard,
+                // e.g. `org.freedesktop.NetworkManager.openvpn`
+                _ => VpnKind::Plugin(
+                    settings
+                        .get("vpn")
+                        .and_then(|vpn| get_str(vpn, "service-type"))
+                        .and_then(|service| service.rsplit('.').next().map(str::to_string))
+                        .unwrap_or_default(),
+                ),
+            },
+        }),
+        _ => None,
+    }
+}
+
+impl NmModel {
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.profiles.clear();
+    }
+
+    pub fn add_profile(&mut self, path: &str, profile: SavedProfile) {
+        self.profiles.insert(path.to_string(), profile);
+    }
+
+    pub fn remove_profile(&mut self, path: &str) {
+        self.profiles.remove(path);
+    }
+
+    fn saved_ssids(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.profiles.iter().filter_map(|(path, profile)| match profile {
+            SavedProfile::Wifi { ssid } => Some((path, ssid)),
+            SavedProfile::Vpn { .. } => None,
+        })
+    }
+
+    pub fn add_interfaces(&mut self, path: &str, interfaces: HashMap<String, Properties>) {
+        let object = self.objects.entry(path.to_string()).or_default();
+        for (interface, props) in interfaces {
+            object.entry(interface).or_default().extend(props);
+        }
+    }
+
+    pub fn remove_interfaces(&mut self, path: &str, interfaces: &[String]) {
+        if let Some(object) = self.objects.get_mut(path) {
+            for interface in interfaces {
+                object.remove(interface);
+            }
+            if object.is_empty() {
+                self.objects.remove(path);
+            }
+        }
+    }
+
+    /// Applies a PropertiesChanged signal. Returns whether a tracked object changed.
+    pub fn change_properties(&mut self, path: &str, interface: &str, changed: Properties, invalidated: &[String]) -> bool {
+        let Some(props) = self.objects.get_mut(path).and_then(|object| object.get_mut(interface)) else {
+            return false;
+        };
+        props.extend(changed);
+        for key in invalidated {
+            props.remove(key);
+        }
+        true
+    }
+
+    fn props(&self, path: &str, interface: &str) -> Option<&Properties> {
+        self.objects.get(path)?.get(interface)
+    }
+
+    fn access_point(&self, path: &str, device: &str, active: bool) -> Option<WifiNetwork> {
+        let props = self.props(path, ACCESS_POINT_INTERFACE)?;
+        let ssid = String::from_utf8_lossy(&get_bytes(props, "Ssid")).into_owned();
+        let key_mgmt = get_u32(props, "WpaFlags") | get_u32(props, "RsnFlags");
+        let security = if key_mgmt & (AP_SEC_KEY_MGMT_802_1X | AP_SEC_KEY_MGMT_EAP_SUITE_B_192) != 0 {
+            WifiSecurity::Enterprise
+        } else if key_mgmt & AP_SEC_KEY_MGMT_PSK != 0 {
+            // WPA3 transition networks accept a PSK too
+            WifiSecurity::Wpa
+        } else if key_mgmt & AP_SEC_KEY_MGMT_SAE != 0 {
+            WifiSecurity::Wpa3
+        } else if get_u32(props, "Flags") & AP_FLAGS_PRIVACY != 0 {
+            WifiSecurity::Wep
+        } else {
+            WifiSecurity::Open
+        };
+        Some(WifiNetwork {
+            path: path.to_string(),
+            device: device.to_string(),
+            known: self.saved_ssids().any(|(_, saved)| *saved == ssid),
+            ssid,
+            signal: get_u8(props, "Strength"),
+            active,
+            security,
+        })
+    }
+
+    pub fn state(&self) -> NetworkState {
+        let Some(manager) = self.props(NM_PATH, NM) else {
+            return NetworkState::default();
+        };
+
+        let devices: Vec<NetworkDevice> = get_paths(manager, "Devices")
+            .into_iter()
+            .filter_map(|path| {
+                let props = self.props(&path, DEVICE_INTERFACE)?;
+                Some(NetworkDevice {
+                    interface: get_str(props, "Interface").unwrap_or_default(),
+                    kind: match get_u32(props, "DeviceType") {
+                        DEVICE_TYPE_ETHERNET => DeviceKind::Ethernet,
+                        DEVICE_TYPE_WIFI => DeviceKind::Wifi,
+                        _ => DeviceKind::Other,
+                    },
+                    connected: get_u32(props, "State") == DEVICE_STATE_ACTIVATED,
+                    path,
+                })
+            })
+            .collect();
+
+        let active_connections: Vec<ActiveConnection> = get_paths(manager, "ActiveConnections")
+            .into_iter()
+            .filter_map(|path| {
+                let props = self.props(&path, ACTIVE_INTERFACE)?;
+                Some(ActiveConnection {
+                    id: get_str(props, "Id").unwrap_or_default(),
+                    uuid: get_str(props, "Uuid").unwrap_or_default(),
+                    kind: ConnectionType::from_nm(&get_str(props, "Type").unwrap_or_default()),
+                    activated: get_u32(props, "State") == ACTIVE_STATE_ACTIVATED,
+                    path,
+                })
+            })
+            .collect();
+
+        // Access points of every wifi device, strongest first and one per SSID
+        let mut networks: Vec<WifiNetwork> = Vec::new();
+        let mut active_ap = None;
+        for device in devices.iter().filter(|device| device.kind == DeviceKind::Wifi) {
+            let Some(wireless) = self.props(&device.path, WIRELESS_INTERFACE) else {
+                continue;
+            };
+            let active = get_path(wireless, "ActiveAccessPoint").filter(|_| device.connected);
+            if let Some(network) = active.as_deref().and_then(|path| self.access_point(path, &device.path, true)) {
+                active_ap = Some(network);
+            }
+            networks.extend(
+                get_paths(wireless, "AccessPoints")
+                    .iter()
+                    .filter_map(|path| self.access_point(path, &device.path, active.as_deref() == Some(path))),
+            );
+        }
+        networks.retain(|network| !network.ssid.is_empty());
+        networks.sort_by(|a, b| b.active.cmp(&a.active).then(b.signal.cmp(&a.signal)));
+        let mut seen = Vec::new();
+        networks.retain(|network| {
+            if seen.contains(&network.ssid) {
+                return false;
+            }
+            seen.push(network.ssid.clone());
+            true
+        });
+
+        // VPN profiles, with their activation when NetworkManager has one
+        let mut vpns: Vec<VpnConnection> = self
+            .profiles
+            .iter()
+            .filter_map(|(path, profile)| {
+                let SavedProfile::Vpn { id, uuid, kind } = profile else {
+                    return None;
+                };
+                let active = active_connections.iter().find(|active| active.uuid == *uuid);
+                Some(VpnConnection {
+                    path: path.clone(),
+                    id: id.clone(),
+                    uuid: uuid.clone(),
+                    kind: kind.clone(),
+                    active: active.map(|active| active.path.clone()),
+                    state: match active {
+                        Some(active) if active.activated => VpnState::Connected,
+                        Some(_) => VpnState::Connecting,
+                        None => VpnState::Disconnected,
+                    },
+                })
+            })
+            .collect();
+        vpns.sort_by_key(|vpn| vpn.id.to_lowercase());
+
+        let primary = get_path(manager, "PrimaryConnection")
+            .and_then(|_| get_str(manager, "PrimaryConnectionType"))
+            .map(|kind| ConnectionType::from_nm(&kind));
+
+        NetworkState {
+            networking_enabled: get_bool(manager, "NetworkingEnabled"),
+            wifi_enabled: get_bool(manager, "WirelessEnabled"),
+            connectivity: match get_u32(manager, "Connectivity") {
+                1 => Connectivity::None,
+                2 => Connectivity::Portal,
+                3 => Connectivity::Limited,
+                4 => Connectivity::Full,
+                _ => Connectivity::Unknown,
+            },
+            primary,
+            active_ssid: active_ap.as_ref().map(|network| network.ssid.clone()),
+            wifi_strength: active_ap.map(|network| network.signal),
+            devices,
+            active_connections,
+            networks,
+            saved: self
+                .saved_ssids()
+                .map(|(path, ssid)| SavedNetwork {
+                    path: path.clone(),
+                    ssid: ssid.clone(),
+                })
+                .collect(),
+            vpns,
+        }
+    }
+}
+
+fn into_strings<K: ToString>(map: HashMap<K, Properties>) -> HashMap<String, Properties> {
+    map.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+}
+
+async fn read_profile(connection: &Connection, path: &str) -> zbus::Result<Option<SavedProfile>> {
+    let settings = SettingsConnectionProxy::builder(connection)
+        .path(path.to_string())?
+        .build()
+        .await?
+        .get_settings()
+        .await?;
+    Ok(parse_profile(&settings))
+}
+
+async fn load(connection: &Connection, model: &mut NmModel) -> zbus::Result<()> {
+    let manager = ObjectManagerProxy::builder(connection)
+        .destination(NM)?
+        .path("/org/freedesktop")?
+        .build()
+        .await?;
+    model.clear();
+    for (path, interfaces) in manager.get_managed_objects().await? {
+        model.add_interfaces(path.as_str(), into_strings(interfaces));
+    }
+    for path in SettingsProxy::new(connection).await?.list_connections().await? {
+        if let Some(profile) = read_profile(connection, path.as_str()).await? {
+            model.add_profile(path.as_str(), profile);
+        }
+    }
+    Ok(())
+}
+
+async fn register_agent(connection: &Connection) {
+    if let Err(err) = secret_agent::register(connection).await {
+        log::warn!("Cannot register the NetworkManager secret agent: {err}");
+    }
+}
+
+/// Sends the network state after every change, until the connection is
+/// closed. NetworkManager restarts are followed.
+pub(crate) async fn watch(connection: Connection, tx: mpsc::UnboundedSender<NetworkState>) -> zbus::Result<()> {
+    let manager = ObjectManagerProxy::builder(&connection)
+        .destination(NM)?
+        .path("/org/freedesktop")?
+        .build()
+        .await?;
+    let dbus = DBusProxy::new(&connection).await?;
+
+    let mut added = manager.receive_interfaces_added().await?;
+    let mut removed = manager.receive_interfaces_removed().await?;
+    let mut owners = dbus.receive_name_owner_changed_with_args(&[(0, NM)]).await?;
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(NM)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .build();
+    let mut changes = MessageStream::for_match_rule(rule, &connection, None).await?;
+    // Saved connections edited in place, e.g. renamed
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(NM)?
+        .interface(SETTINGS_CONNECTION_INTERFACE)?
+        .member("Updated")?
+        .build();
+    let mut updates = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    let mut model = NmModel::default();
+    // NetworkManager may not be running yet
+    match load(&connection, &mut model).await {
+        Ok(()) => register_agent(&connection).await,
+        Err(err) => log::debug!("NetworkManager not available: {err}"),
+    }
+    let mut last_state = model.state();
+    if tx.unbounded_send(last_state.clone()).is_err() {
+        return Ok(());
+    }
+
+    loop {
+        tokio::select! {
+            Some(signal) = added.next() => {
+                let Ok((path, interfaces)) =
+                    signal.message().body().deserialize::<(OwnedObjectPath, HashMap<String, Properties>)>()
+                else {
+                    continue;
+                };
+                if interfaces.contains_key(SETTINGS_CONNECTION_INTERFACE) {
+                    match read_profile(&connection, path.as_str()).await {
+                        Ok(Some(profile)) => model.add_profile(path.as_str(), profile),
+                        Ok(None) => {}
+                        Err(err) => log::debug!("Cannot read connection {}: {err}", path.as_str()),
+                    }
+                }
+                model.add_interfaces(path.as_str(), interfaces);
+            }
+            Some(signal) = removed.next() => {
+                let Ok(args) = signal.args() else { continue };
+                let interfaces: Vec<String> = args.interfaces().iter().map(|name| name.to_string()).collect();
+                if interfaces.iter().any(|name| name == SETTINGS_CONNECTION_INTERFACE) {
+                    model.remove_profile(args.object_path().as_str());
+                }
+                model.remove_interfaces(args.object_path().as_str(), &interfaces);
+            }
+            Some(signal) = owners.next() => {
+                let Ok(args) = signal.args() else { continue };
+                model.clear();
+                if args.new_owner().is_some() {
+                    match load(&connection, &mut model).await {
+                        Ok(()) => register_agent(&connection).await,
+                        Err(err) => log::warn!("Cannot read NetworkManager objects: {err}"),
+                    }
+                }
+            }
+            Some(message) = changes.next() => {
+                let Ok(message) = message else { continue };
+                let header = message.header();
+                let Some(path) = header.path() else { continue };
+                let Ok((interface, changed, invalidated)) =
+                    message.body().deserialize::<(String, Properties, Vec<String>)>()
+                else {
+                    continue;
+                };
+                if !model.change_properties(path.as_str(), &interface, changed, &invalidated) {
+                    continue;
+                }
+            }
+            Some(message) = updates.next() => {
+                let Ok(message) = message else { continue };
+                let header = message.header();
+                let Some(path) = header.path() else { continue };
+                match read_profile(&connection, path.as_str()).await {
+                    Ok(Some(profile)) => model.add_profile(path.as_str(), profile),
+                    // Now of a type nwidgets ignores
+                    Ok(None) => model.remove_profile(path.as_str()),
+                    Err(err) => log::debug!("Cannot read connection {}: {err}", path.as_str()),
+                }
+            }
+            else => break,
+        }
+
+        let state = model.state();
+        if state != last_state {
+            last_state = state.clone();
+            if tx.unbounded_send(state).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn set_wireless_enabled(connection: &Connection, enabled: bool) -> zbus::Result<()> {
+    NetworkManagerProxy::new(connection).await?.set_wireless_enabled(enabled).await
+}
+
+/// Activates the saved connection `saved`, or creates one for `network`.
+/// Without a password, NetworkManager asks the secret agent if needed.
+pub(crate) async fn connect(
+    connection: &Connection,
+    network: &WifiNetwork,
+    saved: Option<&str>,
+    password: Option<String>,
+) -> zbus::Result<()> {
+    if let Some(saved) = saved {
+        return activate(connection, saved).await;
+    }
+
+    let mut settings: HashMap<&str, HashMap<&str, Value<'_>>> = HashMap::new();
+    settings.insert(
+        "802-11-wireless",
+        HashMap::from([("ssid", Value::from(network.ssid.as_bytes().to_vec()))]),
+    );
+    let key_mgmt = match network.security {
+        WifiSecurity::Open => None,
+        WifiSecurity::Wep => Some(("none", "wep-key0")),
+        WifiSecurity::Wpa => Some(("wpa-psk", "psk")),
+        WifiSecurity::Wpa3 => Some(("sae", "psk")),
+        WifiSecurity::Enterprise => {
+            return Err(zbus::Error::Failure(
+                "802.1X networks must be set up with nm-connection-editor".to_string(),
+            ))
+        }
+    };
+    if let Some((key_mgmt, secret)) = key_mgmt {
+        let mut security = HashMap::from([("key-mgmt", Value::from(key_mgmt))]);
+        if let Some(password) = password {
+            if network.security == WifiSecurity::Wep {
+                security.insert("wep-key-type", Value::from(wep_key_type(&password)));
+            }
+            security.insert(secret, Value::from(password));
+        }
+        settings.insert("802-11-wireless-security", security);
+    }
+
+    NetworkManagerProxy::new(connection)
+        .await?
+        .add_and_activate_connection(
+            settings,
+            &ObjectPath::try_from(network.device.as_str())?,
+            &ObjectPath::try_from(network.path.as_str())?,
+        )
+        .await?;
+    Ok(())
+}
+
+/// NetworkManager's `wep-key-type` for a WEP key as typed: 1 for the key
+/// itself (5 or 13 ASCII characters, 10 or 26 hex digits), 2 for a
+/// passphrase it hashes into one.
+fn wep_key_type(key: &str) -> u32 {
+    match key.len() {
+        5 | 13 if key.is_ascii() => 1,
+        10 | 26 if key.chars().all(|c| c.is_ascii_hexdigit()) => 1,
+        _ => 2,
+    }
+}
+
+/// Activates a saved connection, NetworkManager picks the device.
+pub(crate) async fn activate(connection: &Connection, path: &str) -> zbus::Result<()> {
+    let none = ObjectPath::from_static_str_unchecked("/");
+    NetworkManagerProxy::new(connection)
+        .await?
+        .activate_connection(&ObjectPath::try_from(path)?, &none, &none)
+        .await?;
+    Ok(())
+}
+
+/// Tears down an active connection.
+pub(crate) async fn deactivate(connection: &Connection, active: &str) -> zbus::Result<()> {
+    NetworkManagerProxy::new(connection)
+        .await?
+        .deactivate_connection(&ObjectPath::try_from(active)?)
+        .await
+}
+
+/// Deletes saved connections.
+pub(crate) async fn forget(connection: &Connection, paths: &[String]) -> zbus::Result<()> {
+    for path in paths {
+        SettingsConnectionProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await?
+            .delete()
+            .await?;
+    }
+    Ok(())
+}
+
+/// Asks every wifi device to scan; results arrive as AccessPoints changes.
+pub(crate) async fn rescan(connection: &Connection, devices: &[String]) -> zbus::Result<()> {
+    for device in devices {
+        WirelessProxy::builder(connection)
+            .path(device.clone())?
+            .build()
+            .await?
+            .request_scan(HashMap::new())
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nwidgets_dbus_test::Bus;
+    use std::time::Duration;
+    use zbus::fdo::ObjectManager;
+    use zbus::interface;
+    use zbus::object_server::SignalContext;
+
+    const PROFILE: &str = "/org/freedesktop/NetworkManager/Settings/1";
+
+    #[test]
+    fn wep_key_types() {
+        let cases = [
+            ("abcde", 1),
+            ("abcdefghijklm", 1),
+            ("0123456789", 1),
+            ("0123456789abcdef0123456789", 1),
+            // 10 characters but not hex
+            ("correcthor", 2),
+            ("correct horse battery staple", 2),
+            ("", 2),
+        ];
+        for (key, key_type) in cases {
+            assert_eq!(wep_key_type(key), key_type, "{key:?}");
+        }
+    }
+
+    struct FakeManager;
+
+    #[interface(name = "org.freedesktop.NetworkManager")]
+    impl FakeManager {
+        #[zbus(property)]
+        fn networking_enabled(&self) -> bool {
+            true
+        }
+    }
+
+    struct FakeSettings;
+
+    #[interface(name = "org.freedesktop.NetworkManager.Settings")]
+    impl FakeSettings {
+        fn list_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![ObjectPath::from_static_str_unchecked(PROFILE).into()]
+        }
+    }
+
+    struct FakeConnection {
+        kind: &'static str,
+        id: &'static str,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+    impl FakeConnection {
+        fn get_settings(&self) -> HashMap<String, Properties> {
+            let connection = [("type", self.kind), ("id", self.id), ("uuid", "6d0bc6a3-0c3b-4c9b-9b40-1f1e1c1a5e21")];
+            let connection = connection
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), Value::from(value).try_to_owned().unwrap()))
+                .collect();
+            HashMap::from([("connection".to_string(), connection)])
+        }
+
+        #[zbus(signal)]
+        async fn updated(ctx: &SignalContext<'_>) -> zbus::Result<()>;
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<NetworkState>) -> NetworkState {
+        tokio::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .expect("no network state within 5 s")
+            .expect("watcher stopped")
+    }
+
+    fn vpn_names(state: &NetworkState) -> Vec<&str> {
+        state.vpns.iter().map(|vpn| vpn.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn rereads_updated_profiles() {
+        let bus = Bus::start();
+        let nm = bus.connect().await;
+        let server = nm.object_server();
+        server.at("/org/freedesktop", ObjectManager).await.unwrap();
+        server.at(NM_PATH, FakeManager).await.unwrap();
+        server.at("/org/freedesktop/NetworkManager/Settings", FakeSettings).await.unwrap();
+        server.at(PROFILE, FakeConnection { kind: "wireguard", id: "Work" }).await.unwrap();
+        nm.request_name(NM).await.unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded();
+        let client = bus.connect().await;
+        tokio::spawn(watch(client, tx));
+        assert_eq!(vpn_names(&next(&mut rx).await), vec!["Work"]);
+
+        let profile = server.interface::<_, FakeConnection>(PROFILE).await.unwrap();
+        profile.get_mut().await.id = "Office";
+        FakeConnection::updated(profile.signal_context()).await.unwrap();
+        assert_eq!(vpn_names(&next(&mut rx).await), vec!["Office"]);
+
+        // Turned into a connection type nwidgets does not list
+        profile.get_mut().await.kind = "802-3-ethernet";
+        FakeConnection::updated(profile.signal_context()).await.unwrap();
+        assert!(next(&mut rx).await.vpns.is_empty());
+    }
+}
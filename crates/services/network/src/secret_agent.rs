@@ -0,0 +1,276 @@
+//! `org.freedesktop.NetworkManager.SecretAgent`: NetworkManager asks the
+//! agent for passwords it does not have, e.g. a wifi key that changed.
+//! Each request is forwarded to the UI.
+
+use futures::channel::{mpsc, oneshot};
+use std::collections::HashMap;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use zbus::{interface, proxy, Connection, DBusError};
+
+const AGENT_PATH: &str = "/org/freedesktop/NetworkManager/SecretAgent";
+const IDENTIFIER: &str = "org.nwidgets.network";
+/// `NM_SECRET_AGENT_GET_SECRETS_FLAG_ALLOW_INTERACTION`
+const FLAG_ALLOW_INTERACTION: u32 = 0x1;
+/// `NM_SECRET_AGENT_GET_SECRETS_FLAG_REQUEST_NEW`
+const FLAG_REQUEST_NEW: u32 = 0x2;
+
+type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretPrompt {
+    /// Name of the connection, e.g. the SSID.
+    pub connection: String,
+    /// Setting needing the secret, e.g. `802-11-wireless-security` or `vpn`.
+    pub setting: String,
+    /// The previous secret was rejected.
+    pub retry: bool,
+}
+
+pub(crate) enum SecretEvent {
+    /// Answered with the secret, `None` when the user cancels.
+    Prompt(SecretPrompt, oneshot::Sender<Option<String>>),
+    /// NetworkManager gave up on the current request.
+    Cancel,
+}
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.freedesktop.NetworkManager.SecretAgent")]
+enum SecretError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    NoSecrets(String),
+    UserCanceled(String),
+}
+
+fn section_str(settings: &Settings, section: &str, key: &str) -> Option<String> {
+    let value = settings.get(section)?.get(key)?;
+    match &**value {
+        Value::Str(s) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+/// Where the typed secret goes in the reply.
+#[derive(Debug, PartialEq, Eq)]
+enum SecretSlot {
+    /// Key of the setting itself.
+    Key(&'static str),
+    /// Key of the `secrets` string dict of the `vpn` setting, read by the VPN plugin.
+    VpnSecret(String),
+}
+
+/// Slot of the secret in `setting`, for the common cases. `None` for secrets
+/// a single prompt cannot fill.
+fn secret_slot(settings: &Settings, setting: &str, hints: &[String]) -> Option<SecretSlot> {
+    match setting {
+        "802-11-wireless-security" => match section_str(settings, setting, "key-mgmt").as_deref() {
+            Some("none") => Some(SecretSlot::Key("wep-key0")),
+            Some("ieee8021x") => Some(SecretSlot::Key("leap-password")),
+            _ => Some(SecretSlot::Key("psk")),
+        },
+        // Hints name the plugin's secrets, besides messages to show
+        "vpn" => {
+            let key = hints.iter().find(|hint| !hint.starts_with("x-vpn-message:"));
+            Some(SecretSlot::VpnSecret(key.map_or("password", |key| key).to_string()))
+        }
+        // Peer preshared keys are hinted as `peers.<public key>.preshared-key`
+        "wireguard" if hints.iter().all(|hint| hint == "private-key") => Some(SecretSlot::Key("private-key")),
+        "wireguard" => None,
+        _ if hints.iter().any(|hint| hint == "pin") => Some(SecretSlot::Key("pin")),
+        _ => Some(SecretSlot::Key("password")),
+    }
+}
+
+struct SecretAgent {
+    tx: mpsc::UnboundedSender<SecretEvent>,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.SecretAgent")]
+impl SecretAgent {
+    async fn get_secrets(
+        &self,
+        connection: Settings,
+        _connection_path: OwnedObjectPath,
+        setting_name: String,
+        hints: Vec<String>,
+        flags: u32,
+    ) -> Result<Settings, SecretError> {
+        // Nothing is stored here, so without a prompt there is nothing to give,
+        // even for REQUEST_NEW
+        if flags & FLAG_ALLOW_INTERACTION == 0 {
+            return Err(SecretError::NoSecrets("interaction not allowed".to_string()));
+        }
+        let Some(slot) = secret_slot(&connection, &setting_name, &hints) else {
+            return Err(SecretError::NoSecrets(format!("cannot prompt for {setting_name} secrets {hints:?}")));
+        };
+
+        let prompt = SecretPrompt {
+            connection: section_str(&connection, "connection", "id").unwrap_or_default(),
+            setting: setting_name.clone(),
+            retry: flags & FLAG_REQUEST_NEW != 0,
+        };
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if self.tx.unbounded_send(SecretEvent::Prompt(prompt, reply_tx)).is_err() {
+            return Err(SecretError::NoSecrets("no user interface".to_string()));
+        }
+        let Ok(Some(secret)) = reply_rx.await else {
+            return Err(SecretError::UserCanceled("cancelled by the user".to_string()));
+        };
+
+        let (key, value) = match slot {
+            SecretSlot::Key(key) => (key.to_string(), Value::from(secret)),
+            SecretSlot::VpnSecret(key) => ("secrets".to_string(), Value::from(HashMap::from([(key, secret)]))),
+        };
+        let value = value.try_to_owned().map_err(|err| SecretError::ZBus(err.into()))?;
+        Ok(HashMap::from([(setting_name, HashMap::from([(key, value)]))]))
+    }
+
+    fn cancel_get_secrets(&self, _connection_path: OwnedObjectPath, _setting_name: String) {
+        let _ = self.tx.unbounded_send(SecretEvent::Cancel);
+    }
+
+    // Secrets are stored by NetworkManager itself
+    fn save_secrets(&self, _connection: Settings, _connection_path: OwnedObjectPath) {}
+
+    fn delete_secrets(&self, _connection: Settings, _connection_path: OwnedObjectPath) {}
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AgentManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/AgentManager"
+)]
+trait AgentManager {
+    fn register(&self, identifier: &str) -> zbus::Result<()>;
+}
+
+/// Exports the agent on `connection`; NetworkManager only uses it once registered.
+pub(crate) async fn serve(connection: &Connection, tx: mpsc::UnboundedSender<SecretEvent>) -> zbus::Result<()> {
+    connection.object_server().at(AGENT_PATH, SecretAgent { tx }).await?;
+    Ok(())
+}
+
+/// Needed again after NetworkManager restarts.
+pub(crate) async fn register(connection: &Connection) -> zbus::Result<()> {
+    AgentManagerProxy::new(connection).await?.register(IDENTIFIER).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn settings(sections: &[(&str, &str, &str)]) -> Settings {
+        let mut settings = Settings::new();
+        for (section, key, value) in sections {
+            let value = Value::from(*value).try_to_owned().unwrap();
+            settings.entry(section.to_string()).or_default().insert(key.to_string(), value);
+        }
+        settings
+    }
+
+    fn hints(hints: &[&str]) -> Vec<String> {
+        hints.iter().map(|hint| hint.to_string()).collect()
+    }
+
+    /// Runs one request with `flags`, typing `secret` at the prompt.
+    async fn request_secrets(
+        connection: Settings,
+        setting: &str,
+        hints: &[&str],
+        flags: u32,
+        secret: &str,
+    ) -> Result<Settings, SecretError> {
+        let (tx, mut rx) = mpsc::unbounded();
+        let agent = SecretAgent { tx };
+        let path = OwnedObjectPath::try_from("/org/freedesktop/NetworkManager/Settings/1").unwrap();
+        let setting = setting.to_string();
+        let hints = self::hints(hints);
+        let request = async move { agent.get_secrets(connection, path, setting, hints, flags).await };
+        let user = async {
+            if let Some(SecretEvent::Prompt(_, reply)) = rx.next().await {
+                let _ = reply.send(Some(secret.to_string()));
+            }
+        };
+        futures::join!(request, user).0
+    }
+
+    async fn get_secrets(
+        connection: Settings,
+        setting: &str,
+        hints: &[&str],
+        secret: &str,
+    ) -> Result<Settings, SecretError> {
+        request_secrets(connection, setting, hints, FLAG_ALLOW_INTERACTION, secret).await
+    }
+
+    #[test]
+    fn wifi_secret_follows_key_management() {
+        let psk = settings(&[("802-11-wireless-security", "key-mgmt", "wpa-psk")]);
+        let wep = settings(&[("802-11-wireless-security", "key-mgmt", "none")]);
+        let setting = "802-11-wireless-security";
+        assert_eq!(secret_slot(&psk, setting, &[]), Some(SecretSlot::Key("psk")));
+        assert_eq!(secret_slot(&wep, setting, &[]), Some(SecretSlot::Key("wep-key0")));
+        assert_eq!(secret_slot(&Settings::new(), "gsm", &hints(&["pin"])), Some(SecretSlot::Key("pin")));
+        assert_eq!(secret_slot(&Settings::new(), "802-1x", &[]), Some(SecretSlot::Key("password")));
+    }
+
+    #[test]
+    fn vpn_secret_is_named_by_the_hints() {
+        let vpn = |hints: &[&str]| secret_slot(&Settings::new(), "vpn", &self::hints(hints));
+        assert_eq!(vpn(&[]), Some(SecretSlot::VpnSecret("password".to_string())));
+        assert_eq!(
+            vpn(&["x-vpn-message:Enter your token", "cert-pass"]),
+            Some(SecretSlot::VpnSecret("cert-pass".to_string()))
+        );
+    }
+
+    #[test]
+    fn wireguard_prompts_only_for_the_private_key() {
+        let wireguard = |hints: &[&str]| secret_slot(&Settings::new(), "wireguard", &self::hints(hints));
+        assert_eq!(wireguard(&[]), Some(SecretSlot::Key("private-key")));
+        assert_eq!(wireguard(&["private-key"]), Some(SecretSlot::Key("private-key")));
+        assert_eq!(wireguard(&["peers.cGVlcg==.preshared-key"]), None);
+    }
+
+    #[tokio::test]
+    async fn answers_wifi_key() {
+        let connection = settings(&[("connection", "id", "Home"), ("802-11-wireless-security", "key-mgmt", "wpa-psk")]);
+        let reply = get_secrets(connection, "802-11-wireless-security", &[], "hunter22").await.unwrap();
+        let psk = &reply["802-11-wireless-security"]["psk"];
+        assert_eq!(String::try_from(psk.try_clone().unwrap()).unwrap(), "hunter22");
+    }
+
+    #[tokio::test]
+    async fn answers_vpn_secrets_dict() {
+        let connection = settings(&[
+            ("connection", "id", "Work"),
+            ("vpn", "service-type", "org.freedesktop.NetworkManager.openvpn"),
+        ]);
+        let reply = get_secrets(connection, "vpn", &["password"], "hunter22").await.unwrap();
+        assert_eq!(reply["vpn"].len(), 1);
+        let secrets = HashMap::<String, String>::try_from(reply["vpn"]["secrets"].try_clone().unwrap()).unwrap();
+        assert_eq!(secrets, HashMap::from([("password".to_string(), "hunter22".to_string())]));
+    }
+
+    #[tokio::test]
+    async fn refuses_wireguard_peer_keys() {
+        let connection = settings(&[("connection", "id", "wg0")]);
+        let reply = get_secrets(connection, "wireguard", &["peers.cGVlcg==.preshared-key"], "hunter22").await;
+        assert!(matches!(reply, Err(SecretError::NoSecrets(_))));
+    }
+
+    #[tokio::test]
+    async fn prompts_only_when_interaction_is_allowed() {
+        let connection = || {
+            settings(&[("connection", "id", "Home"), ("802-11-wireless-security", "key-mgmt", "wpa-psk")])
+        };
+        let setting = "802-11-wireless-security";
+        for flags in [0, FLAG_REQUEST_NEW] {
+            let reply = request_secrets(connection(), setting, &[], flags, "hunter22").await;
+            assert!(matches!(reply, Err(SecretError::NoSecrets(_))), "flags {flags:#x}");
+        }
+        let flags = FLAG_ALLOW_INTERACTION | FLAG_REQUEST_NEW;
+        assert!(request_secrets(connection(), setting, &[], flags, "hunter22").await.is_ok());
+    }
+}
@@ -108,6 +108,7 @@ impl NotificationServer {
 
 pub struct NotificationService {
     pub history: VecDeque<Notification>,
+    dbus_state: Arc<Mutex<DbusState>>,
 }
 
 impl EventEmitter<NotificationAdded> for NotificationService {}
@@ -126,17 +127,18 @@ impl NotificationService {
             return Self::global(cx);
         }
 
-        let service = cx.new(|_| Self {
-            history: VecDeque::with_capacity(HISTORY_CAPACITY),
-        });
-        cx.set_global(GlobalNotificationService(service.clone()));
-
         let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Notification>();
         let dbus_state = Arc::new(Mutex::new(DbusState {
             next_id: 0,
             sender: Some(tx),
         }));
 
+        let service = cx.new(|_| Self {
+            history: VecDeque::with_capacity(HISTORY_CAPACITY),
+            dbus_state: Arc::clone(&dbus_state),
+        });
+        cx.set_global(GlobalNotificationService(service.clone()));
+
         let state_for_dbus = Arc::clone(&dbus_state);
 
         // Start D-Bus server
@@ -184,6 +186,43 @@ impl NotificationService {
         service
     }
 
+    /// Shows a notification from nwidgets itself, as if it came over D-Bus.
+    pub fn notify(
+        &mut self,
+        summary: impl Into<SharedString>,
+        body: impl Into<SharedString>,
+        app_icon: impl Into<SharedString>,
+        urgency: u8,
+        cx: &mut Context<Self>,
+    ) -> u32 {
+        let id = {
+            let mut st = self.dbus_state.lock();
+            st.next_id += 1;
+            st.next_id
+        };
+        let notification = Notification {
+            id,
+            app_name: "nwidgets".into(),
+            summary: summary.into(),
+            body: body.into(),
+            urgency,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            actions: Vec::new(),
+            app_icon: app_icon.into(),
+        };
+
+        self.history.push_front(notification.clone());
+        if self.history.len() > HISTORY_CAPACITY {
+            self.history.pop_back();
+        }
+        cx.emit(NotificationAdded { notification });
+        cx.notify();
+        id
+    }
+
     pub fn clear(&mut self, cx: &mut Context<Self>) {
         self.history.clear();
         cx.emit(NotificationsCleared);
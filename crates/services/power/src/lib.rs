@@ -0,0 +1,462 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+use gpui::{App, AppContext, AsyncApp, Context, Entity, EventEmitter, Global};
+use nwidgets_service_config::{ConfigService, PowerConfig};
+use nwidgets_service_notification::NotificationService;
+use zbus::Connection;
+
+mod upower;
+
+pub use upower::parse_device;
+
+/// Points the battery must regain before the low battery warnings are armed again.
+const BATTERY_HYSTERESIS: u8 = 5;
+
+/// `Type` of an UPower device; the rarer kinds are `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeviceKind {
+    #[default]
+    Unknown,
+    LinePower,
+    Battery,
+    Ups,
+    Mouse,
+    Keyboard,
+    Phone,
+    Tablet,
+    GamingInput,
+    Headset,
+    Other,
+}
+
+impl DeviceKind {
+    pub fn from_upower(kind: u32) -> Self {
+        match kind {
+            0 => DeviceKind::Unknown,
+            1 => DeviceKind::LinePower,
+            2 => DeviceKind::Battery,
+            3 => DeviceKind::Ups,
+            5 => DeviceKind::Mouse,
+            6 => DeviceKind::Keyboard,
+            8 => DeviceKind::Phone,
+            10 => DeviceKind::Tablet,
+            12 => DeviceKind::GamingInput,
+            17 | 19 => DeviceKind::Headset,
+            _ => DeviceKind::Other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeviceKind::Unknown | DeviceKind::Other => "Device",
+            DeviceKind::LinePower => "AC adapter",
+            DeviceKind::Battery => "Battery",
+            DeviceKind::Ups => "UPS",
+            DeviceKind::Mouse => "Mouse",
+            DeviceKind::Keyboard => "Keyboard",
+            DeviceKind::Phone => "Phone",
+            DeviceKind::Tablet => "Tablet",
+            DeviceKind::GamingInput => "Controller",
+            DeviceKind::Headset => "Headset",
+        }
+    }
+}
+
+/// `State` of an UPower device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatteryState {
+    #[default]
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    FullyCharged,
+    PendingCharge,
+    PendingDischarge,
+}
+
+impl BatteryState {
+    pub fn from_upower(state: u32) -> Self {
+        match state {
+            1 => BatteryState::Charging,
+            2 => BatteryState::Discharging,
+            3 => BatteryState::Empty,
+            4 => BatteryState::FullyCharged,
+            5 => BatteryState::PendingCharge,
+            6 => BatteryState::PendingDischarge,
+            _ => BatteryState::Unknown,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BatteryState::Unknown => "Unknown",
+            BatteryState::Charging => "Charging",
+            BatteryState::Discharging => "Discharging",
+            BatteryState::Empty => "Empty",
+            BatteryState::FullyCharged => "Fully charged",
+            BatteryState::PendingCharge => "Not charging",
+            BatteryState::PendingDischarge => "Waiting to discharge",
+        }
+    }
+
+    /// Draining the battery.
+    pub fn is_discharging(&self) -> bool {
+        matches!(self, BatteryState::Discharging | BatteryState::Empty | BatteryState::PendingDischarge)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerDevice {
+    /// UPower object path.
+    pub path: String,
+    pub kind: DeviceKind,
+    /// Vendor and model, or the kernel name when UPower knows neither.
+    pub name: String,
+    pub percentage: u8,
+    pub state: BatteryState,
+    /// Seconds, when UPower has an estimate.
+    pub time_to_empty: Option<u64>,
+    pub time_to_full: Option<u64>,
+    /// Powers the system (laptop battery, AC adapter), as opposed to a peripheral.
+    pub power_supply: bool,
+    /// Line power: plugged in.
+    pub online: bool,
+    pub present: bool,
+}
+
+impl PowerDevice {
+    /// Time left until empty or full, whichever the battery is heading to.
+    pub fn time_left(&self) -> Option<u64> {
+        match self.state {
+            BatteryState::Charging => self.time_to_full,
+            state if state.is_discharging() => self.time_to_empty,
+            _ => None,
+        }
+    }
+
+    /// Material icon for the level and state.
+    pub fn icon(&self) -> &'static str {
+        match self.kind {
+            DeviceKind::LinePower => return "power",
+            DeviceKind::Mouse => return "mouse",
+            DeviceKind::Keyboard => return "keyboard",
+            DeviceKind::Phone => return "smartphone",
+            DeviceKind::Tablet => return "tablet",
+            DeviceKind::GamingInput => return "sports_esports",
+            DeviceKind::Headset => return "headphones",
+            _ => {}
+        }
+        battery_icon(self.percentage, self.state)
+    }
+}
+
+/// Material battery icon for `percentage` and `state`.
+pub fn battery_icon(percentage: u8, state: BatteryState) -> &'static str {
+    if matches!(state, BatteryState::Charging | BatteryState::FullyCharged) {
+        return match percentage {
+            0..=29 => "battery_charging_20",
+            30..=49 => "battery_charging_30",
+            50..=59 => "battery_charging_50",
+            60..=79 => "battery_charging_60",
+            80..=89 => "battery_charging_80",
+            90..=94 => "battery_charging_90",
+            _ => "battery_charging_full",
+        };
+    }
+    match percentage {
+        0..=4 => "battery_alert",
+        5..=14 => "battery_0_bar",
+        15..=29 => "battery_1_bar",
+        30..=44 => "battery_2_bar",
+        45..=59 => "battery_3_bar",
+        60..=74 => "battery_4_bar",
+        75..=84 => "battery_5_bar",
+        85..=94 => "battery_6_bar",
+        _ => "battery_full",
+    }
+}
+
+/// `1 h 05 min`, `12 min`.
+pub fn format_time(secs: u64) -> String {
+    let minutes = secs / 60;
+    if minutes < 60 {
+        format!("{minutes} min")
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PowerState {
+    /// UPower is running.
+    pub available: bool,
+    pub on_battery: bool,
+    /// The system batteries as one (UPower's display device), `None` without battery.
+    pub battery: Option<PowerDevice>,
+    /// Batteries, AC adapters and peripherals, power supplies first.
+    pub devices: Vec<PowerDevice>,
+}
+
+impl PowerState {
+    /// On mains power, as far as UPower knows.
+    pub fn ac_online(&self) -> bool {
+        let mut adapters = self.devices.iter().filter(|device| device.kind == DeviceKind::LinePower).peekable();
+        if adapters.peek().is_none() {
+            return !self.on_battery;
+        }
+        adapters.any(|adapter| adapter.online)
+    }
+}
+
+/// Warning raised once per discharge when the battery crosses a threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryAlert {
+    Low,
+    Critical,
+}
+
+impl BatteryAlert {
+    /// Threshold of `config` that `percentage` is below, the critical one first.
+    pub fn for_level(percentage: u8, config: &PowerConfig) -> Option<Self> {
+        if percentage < config.critical_battery {
+            Some(BatteryAlert::Critical)
+        } else if percentage < config.low_battery {
+            Some(BatteryAlert::Low)
+        } else {
+            None
+        }
+    }
+}
+
+/// Alert to raise for `battery`, if any. `raised` is the highest alert raised
+/// since the battery last charged; it is updated.
+fn next_alert(
+    battery: Option<&PowerDevice>,
+    config: &PowerConfig,
+    raised: &mut Option<BatteryAlert>,
+) -> Option<BatteryAlert> {
+    let Some(battery) = battery.filter(|battery| battery.state.is_discharging()) else {
+        // Charging: warn again on the next discharge
+        *raised = None;
+        return None;
+    };
+    let highest = config.low_battery.max(config.critical_battery);
+    if battery.percentage >= highest.saturating_add(BATTERY_HYSTERESIS) {
+        *raised = None;
+    }
+    let alert = BatteryAlert::for_level(battery.percentage, config).filter(|alert| Some(*alert) > *raised)?;
+    *raised = Some(alert);
+    Some(alert)
+}
+
+#[derive(Debug, Clone)]
+pub struct PowerStateChanged;
+
+/// The battery just went below `power.low_battery` or `power.critical_battery`.
+#[derive(Debug, Clone)]
+pub struct LowBattery {
+    pub alert: BatteryAlert,
+    pub percentage: u8,
+}
+
+pub struct PowerService {
+    pub state: PowerState,
+    /// Highest warning raised since the battery last charged.
+    alert: Option<BatteryAlert>,
+}
+
+impl EventEmitter<PowerStateChanged> for PowerService {}
+impl EventEmitter<LowBattery> for PowerService {}
+
+struct GlobalPowerService(Entity<PowerService>);
+impl Global for GlobalPowerService {}
+
+impl PowerService {
+    pub fn global(cx: &App) -> Entity<Self> {
+        cx.global::<GlobalPowerService>().0.clone()
+    }
+
+    pub fn init(cx: &mut App) -> Entity<Self> {
+        let service = cx.new(|_cx| Self {
+            state: PowerState::default(),
+            alert: None,
+        });
+
+        cx.set_global(GlobalPowerService(service.clone()));
+
+        let (tx, mut rx) = mpsc::unbounded::<PowerState>();
+
+        // Background worker following UPower
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let connection = match Connection::system().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    log::warn!("Power service disabled, no system bus: {err}");
+                    return;
+                }
+            };
+            if let Err(err) = upower::watch(connection, tx).await {
+                log::warn!("UPower watcher stopped: {err}");
+            }
+        })
+        .detach();
+
+        // UI Thread listener
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(new_state) = rx.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| {
+                            if srv.state != new_state {
+                                srv.state = new_state;
+                                srv.check_battery(cx);
+                                cx.emit(PowerStateChanged);
+                                cx.notify();
+                            }
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
+        service
+    }
+
+    fn check_battery(&mut self, cx: &mut Context<Self>) {
+        let config = ConfigService::config(cx).power.clone();
+        let battery = self.state.battery.as_ref();
+        let Some((alert, battery)) = next_alert(battery, &config, &mut self.alert).zip(battery) else {
+            return;
+        };
+        let percentage = battery.percentage;
+        let remaining = match battery.time_to_empty {
+            Some(secs) => format!("{percentage}% remaining, about {} left", format_time(secs)),
+            None => format!("{percentage}% remaining"),
+        };
+        let (summary, icon, urgency) = match alert {
+            BatteryAlert::Low => ("Low battery", "battery-low", 1),
+            BatteryAlert::Critical => ("Battery critically low", "battery-caution", 2),
+        };
+        let notifications = NotificationService::init(cx);
+        notifications.update(cx, |notifications, cx| {
+            notifications.notify(summary, remaining, icon, urgency, cx);
+        });
+        cx.emit(LowBattery { alert, percentage });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(percentage: u8, state: BatteryState) -> PowerDevice {
+        PowerDevice {
+            path: "/org/freedesktop/UPower/devices/DisplayDevice".into(),
+            kind: DeviceKind::Battery,
+            name: "Battery".into(),
+            percentage,
+            state,
+            time_to_empty: None,
+            time_to_full: None,
+            power_supply: true,
+            online: false,
+            present: true,
+        }
+    }
+
+    /// Alerts raised by a battery going through `levels`.
+    fn alerts(config: &PowerConfig, levels: &[(u8, BatteryState)]) -> Vec<Option<BatteryAlert>> {
+        let mut raised = None;
+        levels
+            .iter()
+            .map(|&(percentage, state)| next_alert(Some(&battery(percentage, state)), config, &mut raised))
+            .collect()
+    }
+
+    const DISCHARGING: BatteryState = BatteryState::Discharging;
+    const CHARGING: BatteryState = BatteryState::Charging;
+
+    #[test]
+    fn escalates_from_low_to_critical_once() {
+        let config = PowerConfig::default();
+        assert_eq!(
+            alerts(
+                &config,
+                &[(21, DISCHARGING), (19, DISCHARGING), (12, DISCHARGING), (4, DISCHARGING), (2, DISCHARGING)]
+            ),
+            vec![None, Some(BatteryAlert::Low), None, Some(BatteryAlert::Critical), None]
+        );
+        // Straight to critical, e.g. after a suspend
+        assert_eq!(
+            alerts(&config, &[(30, DISCHARGING), (3, DISCHARGING), (3, DISCHARGING)]),
+            vec![None, Some(BatteryAlert::Critical), None]
+        );
+    }
+
+    #[test]
+    fn charging_rearms() {
+        let config = PowerConfig::default();
+        assert_eq!(
+            alerts(
+                &config,
+                &[(19, DISCHARGING), (19, CHARGING), (21, CHARGING), (21, DISCHARGING), (19, DISCHARGING)]
+            ),
+            vec![Some(BatteryAlert::Low), None, None, None, Some(BatteryAlert::Low)]
+        );
+        let mut raised = Some(BatteryAlert::Critical);
+        assert_eq!(next_alert(None, &config, &mut raised), None);
+        assert_eq!(raised, None);
+    }
+
+    #[test]
+    fn hysteresis_without_charging() {
+        let config = PowerConfig::default();
+        // Readings bouncing around the threshold warn once
+        assert_eq!(
+            alerts(&config, &[(19, DISCHARGING), (20, DISCHARGING), (24, DISCHARGING), (19, DISCHARGING)]),
+            vec![Some(BatteryAlert::Low), None, None, None]
+        );
+        // Back well above it, e.g. a recalibrated gauge
+        assert_eq!(
+            alerts(&config, &[(19, DISCHARGING), (25, DISCHARGING), (19, DISCHARGING)]),
+            vec![Some(BatteryAlert::Low), None, Some(BatteryAlert::Low)]
+        );
+    }
+
+    #[test]
+    fn disabled_thresholds() {
+        let config = PowerConfig {
+            low_battery: 0,
+            critical_battery: 5,
+        };
+        assert_eq!(
+            alerts(&config, &[(10, DISCHARGING), (4, DISCHARGING)]),
+            vec![None, Some(BatteryAlert::Critical)]
+        );
+        let config = PowerConfig {
+            low_battery: 0,
+            critical_battery: 0,
+        };
+        assert_eq!(alerts(&config, &[(1, DISCHARGING), (0, DISCHARGING)]), vec![None, None]);
+    }
+
+    #[test]
+    fn ac_online_without_adapter() {
+        let state = PowerState {
+            available: true,
+            on_battery: true,
+            battery: Some(battery(50, DISCHARGING)),
+            devices: Vec::new(),
+        };
+        assert!(!state.ac_online());
+        let state = PowerState {
+            on_battery: false,
+            ..state
+        };
+        assert!(state.ac_online());
+    }
+}
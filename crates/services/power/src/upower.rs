@@ -0,0 +1,561 @@
+//! `org.freedesktop.UPower` client: devices come from EnumerateDevices and
+//! are kept up to date from DeviceAdded/Removed and PropertiesChanged.
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use zbus::fdo::{DBusProxy, PropertiesProxy};
+use zbus::message::Type as MessageType;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use zbus::{proxy, Connection, MatchRule, MessageStream};
+
+use crate::{BatteryState, DeviceKind, PowerDevice, PowerState};
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    fn enumerate_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    /// Composite of the batteries powering the system.
+    fn get_display_device(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(signal)]
+    fn device_added(&self, device: OwnedObjectPath) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn device_removed(&self, device: OwnedObjectPath) -> zbus::Result<()>;
+}
+
+fn unwrap<'a>(value: &'a Value<'a>) -> &'a Value<'a> {
+    match value {
+        Value::Value(inner) => unwrap(inner),
+        value => value,
+    }
+}
+
+fn get_str(props: &Properties, key: &str) -> Option<String> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::Str(s)) if !s.is_empty() => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+fn get_bool(props: &Properties, key: &str) -> bool {
+    matches!(props.get(key).map(|value| unwrap(value)), Some(Value::Bool(true)))
+}
+
+fn get_u32(props: &Properties, key: &str) -> Option<u32> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::U32(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn get_f64(props: &Properties, key: &str) -> Option<f64> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::F64(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Seconds, UPower reporting 0 when it has no estimate.
+fn get_secs(props: &Properties, key: &str) -> Option<u64> {
+    match props.get(key).map(|value| unwrap(value)) {
+        Some(Value::I64(n)) if *n > 0 => Some(*n as u64),
+        _ => None,
+    }
+}
+
+/// From the properties of an `org.freedesktop.UPower.Device`.
+pub fn parse_device(path: &str, props: &HashMap<String, OwnedValue>) -> PowerDevice {
+    let kind = DeviceKind::from_upower(get_u32(props, "Type").unwrap_or(0));
+    let name = match (get_str(props, "Vendor"), get_str(props, "Model")) {
+        (Some(vendor), Some(model)) if !model.starts_with(&vendor) => format!("{vendor} {model}"),
+        (_, Some(model)) => model,
+        (Some(vendor), None) => vendor,
+        (None, None) => get_str(props, "NativePath").unwrap_or_else(|| kind.label().to_string()),
+    };
+    PowerDevice {
+        path: path.to_string(),
+        kind,
+        name,
+        percentage: get_f64(props, "Percentage").unwrap_or(0.0).round().clamp(0.0, 100.0) as u8,
+        state: BatteryState::from_upower(get_u32(props, "State").unwrap_or(0)),
+        time_to_empty: get_secs(props, "TimeToEmpty"),
+        time_to_full: get_secs(props, "TimeToFull"),
+        power_supply: get_bool(props, "PowerSupply"),
+        online: get_bool(props, "Online"),
+        present: get_bool(props, "IsPresent"),
+    }
+}
+
+/// Properties of the daemon and of every device, by path.
+#[derive(Default)]
+pub struct UPowerModel {
+    daemon: Properties,
+    display_device: Option<String>,
+    devices: BTreeMap<String, Properties>,
+}
+
+impl UPowerModel {
+    pub fn clear(&mut self) {
+        self.daemon.clear();
+        self.display_device = None;
+        self.devices.clear();
+    }
+
+    /// Applies a PropertiesChanged signal. Returns whether a tracked object changed.
+    pub fn change_properties(&mut self, path: &str, interface: &str, changed: Properties, invalidated: &[String]) -> bool {
+        let props = match interface {
+            UPOWER if path == UPOWER_PATH => &mut self.daemon,
+            DEVICE_INTERFACE => match self.devices.get_mut(path) {
+                Some(props) => props,
+                None => return false,
+            },
+            _ => return false,
+        };
+        props.extend(changed);
+        for key in invalidated {
+            props.remove(key);
+        }
+        true
+    }
+
+    pub fn state(&self) -> PowerState {
+        let battery = self
+            .display_device
+            .as_ref()
+            .and_then(|path| Some(parse_device(path, self.devices.get(path)?)))
+            .filter(|device| device.kind == DeviceKind::Battery && device.present);
+
+        let mut devices: Vec<PowerDevice> = self
+            .devices
+            .iter()
+            .filter(|(path, _)| Some(*path) != self.display_device.as_ref())
+            .map(|(path, props)| parse_device(path, props))
+            // Empty battery bays
+            .filter(|device| device.present || device.kind == DeviceKind::LinePower)
+            .collect();
+        // System batteries and chargers first, then peripherals by name
+        devices.sort_by(|a, b| (!a.power_supply, &a.name).cmp(&(!b.power_supply, &b.name)));
+
+        PowerState {
+            available: !self.daemon.is_empty(),
+            on_battery: get_bool(&self.daemon, "OnBattery"),
+            battery,
+            devices,
+        }
+    }
+}
+
+async fn properties(connection: &Connection, path: &str, interface: &'static str) -> zbus::Result<Properties> {
+    let proxy = PropertiesProxy::builder(connection)
+        .destination(UPOWER)?
+        .path(path.to_string())?
+        .build()
+        .await?;
+    Ok(proxy.get_all(Some(InterfaceName::from_static_str_unchecked(interface)).into()).await?)
+}
+
+async fn load(connection: &Connection, model: &mut UPowerModel) -> zbus::Result<()> {
+    let upower = UPowerProxy::new(connection).await?;
+    model.clear();
+    model.daemon = properties(connection, UPOWER_PATH, UPOWER).await?;
+    let display_device = upower.get_display_device().await?;
+    for path in upower.enumerate_devices().await?.iter().chain([&display_device]) {
+        // A device may go away in between
+        if let Ok(props) = properties(connection, path.as_str(), DEVICE_INTERFACE).await {
+            model.devices.insert(path.to_string(), props);
+        }
+    }
+    model.display_device = Some(display_device.to_string());
+    Ok(())
+}
+
+/// Sends the power state after every change, until the connection is
+/// closed. upowerd restarts are followed.
+pub(crate) async fn watch(connection: Connection, tx: mpsc::UnboundedSender<PowerState>) -> zbus::Result<()> {
+    let upower = UPowerProxy::new(&connection).await?;
+    let dbus = DBusProxy::new(&connection).await?;
+
+    let mut added = upower.receive_device_added().await?;
+    let mut removed = upower.receive_device_removed().await?;
+    let mut owners = dbus.receive_name_owner_changed_with_args(&[(0, UPOWER)]).await?;
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(UPOWER)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .build();
+    let mut changes = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    let mut model = UPowerModel::default();
+    // upowerd is started on demand, or not installed on desktops
+    if let Err(err) = load(&connection, &mut model).await {
+        log::debug!("UPower not available: {err}");
+    }
+    let mut last_state = model.state();
+    if tx.unbounded_send(last_state.clone()).is_err() {
+        return Ok(());
+    }
+
+    loop {
+        tokio::select! {
+            Some(signal) = added.next() => {
+                let Ok(args) = signal.args() else { continue };
+                let path = args.device().to_string();
+                match properties(&connection, &path, DEVICE_INTERFACE).await {
+                    Ok(props) => {
+                        model.devices.insert(path, props);
+                    }
+                    Err(err) => log::debug!("Cannot read UPower device {path}: {err}"),
+                }
+            }
+            Some(signal) = removed.next() => {
+                let Ok(args) = signal.args() else { continue };
+                model.devices.remove(args.device().as_str());
+            }
+            Some(signal) = owners.next() => {
+                let Ok(args) = signal.args() else { continue };
+                model.clear();
+                if args.new_owner().is_some() {
+                    if let Err(err) = load(&connection, &mut model).await {
+                        log::warn!("Cannot read UPower devices: {err}");
+                    }
+                }
+            }
+            Some(message) = changes.next() => {
+                let Ok(message) = message else { continue };
+                let header = message.header();
+                let Some(path) = header.path() else { continue };
+                let Ok((interface, changed, invalidated)) =
+                    message.body().deserialize::<(String, Properties, Vec<String>)>()
+                else {
+                    continue;
+                };
+                if !model.change_properties(path.as_str(), &interface, changed, &invalidated) {
+                    continue;
+                }
+            }
+            else => break,
+        }
+
+        let state = model.state();
+        if state != last_state {
+            last_state = state.clone();
+            if tx.unbounded_send(state).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nwidgets_dbus_test::Bus;
+    use std::time::Duration;
+    use zbus::object_server::SignalContext;
+    use zbus::zvariant::ObjectPath;
+    use zbus::interface;
+
+    const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+    const BATTERY: &str = "/org/freedesktop/UPower/devices/battery_BAT0";
+    const AC: &str = "/org/freedesktop/UPower/devices/line_power_AC";
+    const MOUSE: &str = "/org/freedesktop/UPower/devices/mouse_hidpp_battery_0";
+
+    fn props(entries: &[(&str, Value<'static>)]) -> Properties {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.try_to_owned().unwrap()))
+            .collect()
+    }
+
+    fn battery_props(percentage: f64, state: u32) -> Properties {
+        props(&[
+            ("Type", Value::from(2u32)),
+            ("NativePath", Value::from("BAT0")),
+            ("Vendor", Value::from("SMP")),
+            ("Model", Value::from("5B10W13975")),
+            ("Percentage", Value::from(percentage)),
+            ("State", Value::from(state)),
+            ("TimeToEmpty", Value::from(5400i64)),
+            ("TimeToFull", Value::from(0i64)),
+            ("PowerSupply", Value::from(true)),
+            ("IsPresent", Value::from(true)),
+        ])
+    }
+
+    fn ac_props(online: bool) -> Properties {
+        props(&[
+            ("Type", Value::from(1u32)),
+            ("NativePath", Value::from("AC")),
+            ("PowerSupply", Value::from(true)),
+            ("Online", Value::from(online)),
+        ])
+    }
+
+    fn laptop() -> UPowerModel {
+        let mut model = UPowerModel::default();
+        model.daemon = props(&[("OnBattery", Value::from(true))]);
+        model.display_device = Some(DISPLAY_DEVICE.into());
+        model.devices.insert(DISPLAY_DEVICE.into(), battery_props(57.4, 2));
+        model.devices.insert(BATTERY.into(), battery_props(57.4, 2));
+        model.devices.insert(AC.into(), ac_props(false));
+        model
+    }
+
+    #[test]
+    fn parses_devices() {
+        let battery = parse_device(BATTERY, &battery_props(57.4, 2));
+        assert_eq!(battery.kind, DeviceKind::Battery);
+        assert_eq!(battery.name, "SMP 5B10W13975");
+        assert_eq!(battery.percentage, 57);
+        assert_eq!(battery.state, BatteryState::Discharging);
+        assert_eq!(battery.time_to_empty, Some(5400));
+        // 0 means no estimate
+        assert_eq!(battery.time_to_full, None);
+        assert!(battery.present && battery.power_supply);
+
+        let ac = parse_device(AC, &ac_props(true));
+        assert_eq!((ac.kind, ac.name.as_str(), ac.online, ac.present), (DeviceKind::LinePower, "AC", true, false));
+
+        let mouse = parse_device(
+            MOUSE,
+            &props(&[
+                ("Type", Value::from(5u32)),
+                ("Vendor", Value::from("Logitech")),
+                ("Model", Value::from("Logitech MX Master 3")),
+            ]),
+        );
+        assert_eq!(mouse.name, "Logitech MX Master 3");
+        assert_eq!(parse_device(MOUSE, &Properties::new()).name, "Device");
+    }
+
+    #[test]
+    fn laptop_state() {
+        let state = laptop().state();
+        assert!(state.available && state.on_battery && !state.ac_online());
+        let battery = state.battery.unwrap();
+        assert_eq!((battery.path.as_str(), battery.percentage), (DISPLAY_DEVICE, 57));
+        // The display device is not listed again
+        let paths: Vec<&str> = state.devices.iter().map(|device| device.path.as_str()).collect();
+        assert_eq!(paths, vec![AC, BATTERY]);
+    }
+
+    #[test]
+    fn peripherals_after_power_supplies() {
+        let mut model = laptop();
+        model.devices.insert(
+            MOUSE.into(),
+            props(&[
+                ("Type", Value::from(5u32)),
+                ("Model", Value::from("MX Master 3")),
+                ("IsPresent", Value::from(true)),
+            ]),
+        );
+        // Empty second battery bay
+        let mut empty_bay = battery_props(0.0, 0);
+        empty_bay.insert("IsPresent".into(), Value::from(false).try_to_owned().unwrap());
+        model.devices.insert("/org/freedesktop/UPower/devices/battery_BAT1".into(), empty_bay);
+
+        let paths: Vec<String> = model.state().devices.into_iter().map(|device| device.path).collect();
+        assert_eq!(paths, vec![AC, BATTERY, MOUSE]);
+    }
+
+    #[test]
+    fn desktop_has_no_battery() {
+        let mut model = UPowerModel::default();
+        model.daemon = props(&[("OnBattery", Value::from(false))]);
+        model.display_device = Some(DISPLAY_DEVICE.into());
+        // UPower still exports a display device, of unknown type
+        let display_device = props(&[("Type", Value::from(0u32)), ("IsPresent", Value::from(false))]);
+        model.devices.insert(DISPLAY_DEVICE.into(), display_device);
+        let state = model.state();
+        assert!(state.available && state.battery.is_none() && state.devices.is_empty() && state.ac_online());
+
+        assert!(!UPowerModel::default().state().available);
+    }
+
+    #[test]
+    fn applies_property_changes() {
+        let mut model = laptop();
+        let changed = props(&[("Online", Value::from(true))]);
+        assert!(model.change_properties(AC, DEVICE_INTERFACE, changed, &[]));
+        let changed = props(&[("OnBattery", Value::from(false))]);
+        assert!(model.change_properties(UPOWER_PATH, UPOWER, changed, &[]));
+        assert!(model.change_properties(DISPLAY_DEVICE, DEVICE_INTERFACE, Properties::new(), &["TimeToEmpty".into()]));
+        // Unknown objects and interfaces
+        assert!(!model.change_properties(MOUSE, DEVICE_INTERFACE, ac_props(true), &[]));
+        assert!(!model.change_properties(AC, "org.freedesktop.UPower.KbdBacklight", Properties::new(), &[]));
+
+        let state = model.state();
+        assert!(!state.on_battery && state.ac_online());
+        assert_eq!(state.battery.unwrap().time_to_empty, None);
+    }
+
+    struct FakeUPower {
+        on_battery: bool,
+        devices: Vec<OwnedObjectPath>,
+    }
+
+    #[interface(name = "org.freedesktop.UPower")]
+    impl FakeUPower {
+        fn enumerate_devices(&self) -> Vec<OwnedObjectPath> {
+            self.devices.clone()
+        }
+
+        fn get_display_device(&self) -> OwnedObjectPath {
+            ObjectPath::from_static_str_unchecked(DISPLAY_DEVICE).into()
+        }
+
+        #[zbus(property)]
+        fn on_battery(&self) -> bool {
+            self.on_battery
+        }
+
+        #[zbus(signal)]
+        async fn device_added(ctx: &SignalContext<'_>, device: ObjectPath<'_>) -> zbus::Result<()>;
+
+        #[zbus(signal)]
+        async fn device_removed(ctx: &SignalContext<'_>, device: ObjectPath<'_>) -> zbus::Result<()>;
+    }
+
+    struct FakeDevice {
+        kind: u32,
+        native_path: &'static str,
+        percentage: f64,
+        state: u32,
+        online: bool,
+    }
+
+    #[interface(name = "org.freedesktop.UPower.Device")]
+    impl FakeDevice {
+        #[zbus(property, name = "Type")]
+        fn kind(&self) -> u32 {
+            self.kind
+        }
+
+        #[zbus(property)]
+        fn native_path(&self) -> &str {
+            self.native_path
+        }
+
+        #[zbus(property)]
+        fn percentage(&self) -> f64 {
+            self.percentage
+        }
+
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.state
+        }
+
+        #[zbus(property)]
+        fn online(&self) -> bool {
+            self.online
+        }
+
+        #[zbus(property)]
+        fn power_supply(&self) -> bool {
+            self.kind <= 2
+        }
+
+        #[zbus(property)]
+        fn is_present(&self) -> bool {
+            self.kind != 1
+        }
+    }
+
+    fn device(kind: u32, native_path: &'static str, percentage: f64) -> FakeDevice {
+        FakeDevice {
+            kind,
+            native_path,
+            percentage,
+            state: 2,
+            online: false,
+        }
+    }
+
+    async fn serve(upowerd: &Connection) {
+        let server = upowerd.object_server();
+        let devices = vec![ObjectPath::from_static_str_unchecked(AC).into()];
+        server.at(UPOWER_PATH, FakeUPower { on_battery: true, devices }).await.unwrap();
+        server.at(DISPLAY_DEVICE, device(2, "", 80.0)).await.unwrap();
+        server.at(AC, device(1, "AC", 0.0)).await.unwrap();
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<PowerState>) -> PowerState {
+        tokio::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .expect("no power state within 5 s")
+            .expect("watcher stopped")
+    }
+
+    fn paths(state: &PowerState) -> Vec<&str> {
+        state.devices.iter().map(|device| device.path.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn follows_a_fake_upower() {
+        let bus = Bus::start();
+        let upowerd = bus.connect().await;
+        serve(&upowerd).await;
+        upowerd.request_name(UPOWER).await.unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded();
+        let client = bus.connect().await;
+        tokio::spawn(watch(client, tx));
+
+        let state = next(&mut rx).await;
+        assert!(state.available && state.on_battery && !state.ac_online());
+        assert_eq!(state.battery.as_ref().map(|battery| battery.percentage), Some(80));
+        assert_eq!(paths(&state), vec![AC]);
+
+        // Charger plugged in: PropertiesChanged on the adapter, then on the daemon
+        let adapter = upowerd.object_server().interface::<_, FakeDevice>(AC).await.unwrap();
+        adapter.get_mut().await.online = true;
+        adapter.get().await.online_changed(adapter.signal_context()).await.unwrap();
+        assert!(next(&mut rx).await.ac_online());
+        let daemon = upowerd.object_server().interface::<_, FakeUPower>(UPOWER_PATH).await.unwrap();
+        daemon.get_mut().await.on_battery = false;
+        daemon.get().await.on_battery_changed(daemon.signal_context()).await.unwrap();
+        assert!(!next(&mut rx).await.on_battery);
+
+        // A mouse connects, then goes away
+        upowerd.object_server().at(MOUSE, device(5, "hidpp_battery_0", 45.0)).await.unwrap();
+        let mouse = ObjectPath::from_static_str_unchecked(MOUSE);
+        daemon.get_mut().await.devices.push(mouse.clone().into());
+        FakeUPower::device_added(daemon.signal_context(), mouse.clone()).await.unwrap();
+        let state = next(&mut rx).await;
+        assert_eq!(paths(&state), vec![AC, MOUSE]);
+        assert_eq!(state.devices[1].percentage, 45);
+
+        upowerd.object_server().remove::<FakeDevice, _>(MOUSE).await.unwrap();
+        daemon.get_mut().await.devices.pop();
+        FakeUPower::device_removed(daemon.signal_context(), mouse).await.unwrap();
+        assert_eq!(paths(&next(&mut rx).await), vec![AC]);
+
+        // upowerd restarts
+        upowerd.release_name(UPOWER).await.unwrap();
+        assert_eq!(next(&mut rx).await, PowerState::default());
+        drop(upowerd);
+        let upowerd = bus.connect().await;
+        serve(&upowerd).await;
+        upowerd.request_name(UPOWER).await.unwrap();
+        let state = next(&mut rx).await;
+        assert!(state.available && state.on_battery);
+        assert_eq!(paths(&state), vec![AC]);
+    }
+}
@@ -0,0 +1,192 @@
+use futures::channel::{mpsc, oneshot};
+use futures::StreamExt;
+use gpui::{App, AppContext, AsyncApp, Context, Entity, EventEmitter, Global};
+use nwidgets_service_config::{ConfigChanged, ConfigService, SshConfig};
+use zbus::Connection;
+
+mod systemd;
+
+pub use systemd::parse_unit_state;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnitState {
+    /// systemd could not be reached.
+    #[default]
+    Unavailable,
+    /// No such unit.
+    NotFound,
+    Inactive,
+    Activating,
+    Active,
+    Deactivating,
+    Failed,
+}
+
+impl UnitState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnitState::Unavailable => "Unavailable",
+            UnitState::NotFound => "Not installed",
+            UnitState::Inactive => "Stopped",
+            UnitState::Activating => "Starting...",
+            UnitState::Active => "Running",
+            UnitState::Deactivating => "Stopping...",
+            UnitState::Failed => "Failed",
+        }
+    }
+
+    /// Running or on its way to.
+    pub fn is_on(&self) -> bool {
+        matches!(self, UnitState::Active | UnitState::Activating)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SshState {
+    /// Unit being followed, e.g. `sshd.service`.
+    pub unit: String,
+    pub state: UnitState,
+}
+
+/// Unit to follow and the bus of its systemd instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SshTarget {
+    unit: String,
+    /// `systemctl --user` unit.
+    user: bool,
+}
+
+impl From<&SshConfig> for SshTarget {
+    fn from(config: &SshConfig) -> Self {
+        Self {
+            unit: config.unit.clone(),
+            user: config.user,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SshStateChanged;
+
+pub struct SshService {
+    pub state: SshState,
+    /// A start or stop job is running.
+    pub pending: bool,
+    /// Last start/stop error, cleared by the next attempt.
+    pub error: Option<String>,
+    connection: Option<Connection>,
+}
+
+impl EventEmitter<SshStateChanged> for SshService {}
+
+struct GlobalSshService(Entity<SshService>);
+impl Global for GlobalSshService {}
+
+impl SshService {
+    pub fn global(cx: &App) -> Entity<Self> {
+        cx.global::<GlobalSshService>().0.clone()
+    }
+
+    pub fn init(cx: &mut App) -> Entity<Self> {
+        let service = cx.new(|_cx| Self {
+            state: SshState::default(),
+            pending: false,
+            error: None,
+            connection: None,
+        });
+
+        cx.set_global(GlobalSshService(service.clone()));
+
+        let (tx, mut rx) = mpsc::unbounded::<SshState>();
+        let (connection_tx, mut connection_rx) = mpsc::unbounded::<Connection>();
+
+        // Unit to follow, changed live with the configuration
+        let target = SshTarget::from(&ConfigService::config(cx).ssh);
+        let (target_tx, target_rx) = tokio::sync::watch::channel(target);
+        cx.subscribe(&ConfigService::global(cx), move |config, _: &ConfigChanged, cx| {
+            let target = SshTarget::from(&config.read(cx).config.ssh);
+            target_tx.send_if_modified(|current| {
+                let changed = *current != target;
+                *current = target;
+                changed
+            });
+        })
+        .detach();
+
+        // Background worker following the unit through systemd
+        gpui_tokio::Tokio::spawn(cx, systemd::watch(target_rx, tx, connection_tx)).detach();
+
+        // Bus of the current unit, system or user
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(connection) = connection_rx.next().await {
+                    let _ = cx.update(|cx| service_entity.update(cx, |srv, _cx| srv.connection = Some(connection)));
+                }
+            }
+        })
+        .detach();
+
+        // UI Thread listener
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while let Some(new_state) = rx.next().await {
+                    let _ = cx.update(|cx| {
+                        service_entity.update(cx, |srv, cx| {
+                            if srv.state != new_state {
+                                if srv.state.unit != new_state.unit {
+                                    srv.error = None;
+                                }
+                                srv.state = new_state;
+                                cx.emit(SshStateChanged);
+                                cx.notify();
+                            }
+                        });
+                    });
+                }
+            }
+        })
+        .detach();
+
+        service
+    }
+
+    /// Starts the unit, or stops it when it is running.
+    pub fn toggle(&mut self, cx: &mut Context<Self>) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        if self.pending || matches!(self.state.state, UnitState::Unavailable | UnitState::NotFound) {
+            return;
+        }
+        let unit = self.state.unit.clone();
+        let running = !self.state.state.is_on();
+        self.pending = true;
+        self.error = None;
+        cx.notify();
+
+        let (result_tx, result_rx) = oneshot::channel();
+        gpui_tokio::Tokio::spawn(cx, async move {
+            // polkit may ask for a password for system units
+            let result = systemd::set_running(&connection, &unit, running).await;
+            let _ = result_tx.send(result.map_err(|err| err.to_string()));
+        })
+        .detach();
+
+        cx.spawn(async move |this, cx| {
+            let result = result_rx.await.unwrap_or_else(|_| Err("cancelled".to_string()));
+            let _ = this.update(cx, |srv, cx| {
+                srv.pending = false;
+                if let Err(error) = result {
+                    log::warn!("Cannot toggle {}: {error}", srv.state.unit);
+                    srv.error = Some(error);
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+}
@@ -0,0 +1,144 @@
+//! `org.freedesktop.systemd1` client following the active state of one unit.
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use tokio::sync::watch;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{proxy, Connection};
+
+use crate::{SshState, SshTarget, UnitState};
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+trait Manager {
+    fn load_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(allow_interactive_auth)]
+    fn start_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(allow_interactive_auth)]
+    fn stop_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+
+    /// Without a subscriber, systemd does not emit unit changes.
+    fn subscribe(&self) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.freedesktop.systemd1.Unit", default_service = "org.freedesktop.systemd1")]
+trait Unit {
+    #[zbus(property)]
+    fn active_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn load_state(&self) -> zbus::Result<String>;
+}
+
+/// From the `LoadState` and `ActiveState` properties of a unit.
+pub fn parse_unit_state(load_state: &str, active_state: &str) -> UnitState {
+    if load_state == "not-found" {
+        return UnitState::NotFound;
+    }
+    match active_state {
+        "active" | "reloading" | "refreshing" => UnitState::Active,
+        "activating" => UnitState::Activating,
+        "deactivating" => UnitState::Deactivating,
+        "failed" => UnitState::Failed,
+        _ => UnitState::Inactive,
+    }
+}
+
+async fn bus(user: bool) -> zbus::Result<Connection> {
+    if user {
+        Connection::session().await
+    } else {
+        Connection::system().await
+    }
+}
+
+async fn unit_state(unit: &UnitProxy<'_>) -> UnitState {
+    let load_state = unit.load_state().await.unwrap_or_default();
+    let active_state = unit.active_state().await.unwrap_or_default();
+    parse_unit_state(&load_state, &active_state)
+}
+
+/// Follows one target until it changes or its bus goes away.
+async fn follow(
+    connection: &Connection,
+    target: &SshTarget,
+    targets: &mut watch::Receiver<SshTarget>,
+    tx: &mpsc::UnboundedSender<SshState>,
+) -> zbus::Result<()> {
+    let manager = ManagerProxy::new(connection).await?;
+    // Fails when this connection already subscribed, which is fine
+    let _ = manager.subscribe().await;
+    let path = manager.load_unit(&target.unit).await?;
+    let unit = UnitProxy::builder(connection).path(path)?.build().await?;
+    let mut changes = unit.receive_active_state_changed().await;
+
+    let mut last_state = None;
+    loop {
+        let state = unit_state(&unit).await;
+        if last_state != Some(state) {
+            last_state = Some(state);
+            let state = SshState {
+                unit: target.unit.clone(),
+                state,
+            };
+            if tx.unbounded_send(state).is_err() {
+                return Ok(());
+            }
+        }
+        tokio::select! {
+            change = changes.next() => {
+                if change.is_none() {
+                    return Ok(());
+                }
+            }
+            _ = targets.changed() => return Ok(()),
+        }
+    }
+}
+
+/// Sends the state of the configured unit after every change, following
+/// `targets` when the configuration changes.
+pub(crate) async fn watch(
+    mut targets: watch::Receiver<SshTarget>,
+    tx: mpsc::UnboundedSender<SshState>,
+    connection_tx: mpsc::UnboundedSender<Connection>,
+) {
+    loop {
+        let target = targets.borrow_and_update().clone();
+        match bus(target.user).await {
+            Ok(connection) => {
+                let _ = connection_tx.unbounded_send(connection.clone());
+                if let Err(err) = follow(&connection, &target, &mut targets, &tx).await {
+                    log::warn!("Cannot follow {}: {err}", target.unit);
+                }
+            }
+            Err(err) => log::warn!("SSH toggle disabled, no bus for {}: {err}", target.unit),
+        }
+        if *targets.borrow() != target {
+            continue;
+        }
+        let unavailable = SshState {
+            unit: target.unit.clone(),
+            state: UnitState::Unavailable,
+        };
+        // Nothing to follow until the configuration changes
+        if tx.unbounded_send(unavailable).is_err() || targets.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+pub(crate) async fn set_running(connection: &Connection, unit: &str, running: bool) -> zbus::Result<()> {
+    let manager = ManagerProxy::new(connection).await?;
+    if running {
+        manager.start_unit(unit, "replace").await?;
+    } else {
+        manager.stop_unit(unit, "replace").await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! Mount usage through statvfs and disk throughput from `/proc/diskstats`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+use nix::sys::statvfs::statvfs;
+
+use crate::DiskStats;
+
+/// `/proc/diskstats` counts 512-byte sectors whatever the device.
+const SECTOR_SIZE: u64 = 512;
+
+/// Sector counters of one block device since boot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCounters {
+    /// Kernel name, e.g. `nvme0n1p2` or `dm-0`.
+    pub name: String,
+    pub sectors_read: u64,
+    pub sectors_written: u64,
+}
+
+/// Parses `/proc/diskstats`.
+pub fn parse_diskstats(content: &str) -> Vec<DiskCounters> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // major minor name reads merged sectors ms writes merged sectors ...
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            Some(DiskCounters {
+                name: fields[2].to_string(),
+                sectors_read: fields[5].parse().ok()?,
+                sectors_written: fields[9].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Source device of each mount point from `/proc/self/mounts`, as written there.
+pub fn parse_mounts(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            // Spaces are escaped as \040
+            let mount_point = fields.next()?.replace("\\040", " ");
+            Some((mount_point, device.to_string()))
+        })
+        .collect()
+}
+
+/// Kernel name of a `/dev` path, following `/dev/mapper` and `/dev/disk/by-*` links.
+pub fn device_name(device: &str) -> Option<String> {
+    if !device.starts_with("/dev/") {
+        return None;
+    }
+    let path = std::fs::canonicalize(device).unwrap_or_else(|_| Path::new(device).to_path_buf());
+    path.file_name().map(|name| name.to_string_lossy().into_owned())
+}
+
+/// `(total, used, available)` bytes of the filesystem mounted at `mount`, as `df` counts them.
+pub fn usage(mount: &str) -> Option<(u64, u64, u64)> {
+    let stat = statvfs(mount).ok()?;
+    let block = stat.fragment_size() as u64;
+    let total = stat.blocks() as u64 * block;
+    let used = total.saturating_sub(stat.blocks_free() as u64 * block);
+    Some((total, used, stat.blocks_available() as u64 * block))
+}
+
+/// Turns successive `/proc/diskstats` readings into read/write rates.
+#[derive(Default)]
+pub struct DiskSampler {
+    last: Option<(Instant, HashMap<String, (u64, u64)>)>,
+}
+
+impl DiskSampler {
+    /// Bytes per second read and written by each device since the previous call.
+    pub fn sample(&mut self, counters: Vec<DiskCounters>, now: Instant) -> HashMap<String, (u64, u64)> {
+        let previous = self.last.take();
+        let elapsed = previous
+            .as_ref()
+            .map(|(at, _)| now.saturating_duration_since(*at).as_secs_f64())
+            .unwrap_or(0.0);
+
+        let rate = |now: u64, before: u64| {
+            if elapsed > 0.0 {
+                (now.saturating_sub(before) as f64 * SECTOR_SIZE as f64 / elapsed).round() as u64
+            } else {
+                0
+            }
+        };
+
+        let current: HashMap<String, (u64, u64)> = counters
+            .into_iter()
+            .map(|counter| (counter.name, (counter.sectors_read, counter.sectors_written)))
+            .collect();
+        let rates = current
+            .iter()
+            .map(|(name, &(read, written))| {
+                let before = previous.as_ref().and_then(|(_, last)| last.get(name));
+                let rates = match before {
+                    Some(&(read_before, written_before)) => (rate(read, read_before), rate(written, written_before)),
+                    None => (0, 0),
+                };
+                (name.clone(), rates)
+            })
+            .collect();
+        self.last = Some((now, current));
+        rates
+    }
+}
+
+/// Usage and throughput of the configured mounts; mounts that are not mounted are skipped.
+pub fn disk_stats(
+    mounts: &[String],
+    mount_table: &HashMap<String, String>,
+    rates: &HashMap<String, (u64, u64)>,
+) -> Vec<DiskStats> {
+    mounts
+        .iter()
+        .filter_map(|mount| {
+            let device = mount_table.get(mount)?;
+            let (total, used, available) = usage(mount)?;
+            let (read, write) = device_name(device)
+                .and_then(|name| rates.get(&name).copied())
+                .unwrap_or_default();
+            Some(DiskStats {
+                mount: mount.clone(),
+                total,
+                used,
+                available,
+                read,
+                write,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Kernel 5.5+ layout (20 fields), a 4.x one (14 fields) and a short line.
+    const DISKSTATS: &str = "\
+ 259       0 nvme0n1 293470 61291 22360382 54061 1037622 425931 61180648 1418446 0 634456 1494170 0 0 0 0 88014 21662
+ 259       2 nvme0n1p2 292970 61291 22339934 53990 1037622 425931 61180648 1418446 0 634428 1472436 0 0 0 0 0 0
+   8       0 sda 1024 0 8192 120 512 0 4096 80 0 200 200
+ 254       0 dm-0
+";
+
+    const MOUNTS: &str = "\
+/dev/mapper/root / btrfs rw,relatime,ssd,space_cache=v2,subvol=/@ 0 0
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+/dev/nvme0n1p1 /boot vfat rw,relatime,fmask=0022,dmask=0022 0 0
+/dev/sdb1 /run/media/nia/My\\040Disk ext4 rw,nosuid,nodev,relatime 0 0
+";
+
+    fn counters(name: &str, sectors_read: u64, sectors_written: u64) -> DiskCounters {
+        DiskCounters {
+            name: name.to_string(),
+            sectors_read,
+            sectors_written,
+        }
+    }
+
+    #[test]
+    fn parses_diskstats() {
+        assert_eq!(
+            parse_diskstats(DISKSTATS),
+            [
+                counters("nvme0n1", 22360382, 61180648),
+                counters("nvme0n1p2", 22339934, 61180648),
+                counters("sda", 8192, 4096),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_mounts() {
+        let mounts = parse_mounts(MOUNTS);
+        let cases = [
+            ("/", Some("/dev/mapper/root")),
+            ("/proc", Some("proc")),
+            ("/boot", Some("/dev/nvme0n1p1")),
+            ("/run/media/nia/My Disk", Some("/dev/sdb1")),
+            ("/run/media/nia/My\\040Disk", None),
+            ("/home", None),
+        ];
+        for (mount, device) in cases {
+            assert_eq!(mounts.get(mount).map(String::as_str), device, "{mount}");
+        }
+    }
+
+    #[test]
+    fn samples_rates() {
+        let start = Instant::now();
+        let mut sampler = DiskSampler::default();
+
+        // (seconds since start, counters, expected (read, write) bytes per second)
+        let steps: [(u64, Vec<DiskCounters>, Vec<(&str, (u64, u64))>); 5] = [
+            // Nothing to compare with yet
+            (0, vec![counters("sda", 1000, 2000)], vec![("sda", (0, 0))]),
+            (2, vec![counters("sda", 1008, 4000)], vec![("sda", (2048, 512_000))]),
+            // Counters back to zero (device removed and added again), a new device
+            (3, vec![counters("sda", 10, 20), counters("sdb", 50, 50)], vec![("sda", (0, 0)), ("sdb", (0, 0))]),
+            // Rounded to the byte
+            (6, vec![counters("sda", 11, 20), counters("sdb", 50, 50)], vec![("sda", (171, 0)), ("sdb", (0, 0))]),
+            // No time elapsed
+            (6, vec![counters("sda", 5000, 5000)], vec![("sda", (0, 0))]),
+        ];
+        for (secs, counters, expected) in steps {
+            let rates = sampler.sample(counters, start + Duration::from_secs(secs));
+            let expected: HashMap<String, (u64, u64)> =
+                expected.into_iter().map(|(name, rates)| (name.to_string(), rates)).collect();
+            assert_eq!(rates, expected, "at {secs} s");
+        }
+    }
+
+    #[test]
+    fn usage_rounds_up_like_df() {
+        let disk = |total, used, available| DiskStats {
+            mount: "/".to_string(),
+            total,
+            used,
+            available,
+            read: 0,
+            write: 0,
+        };
+        // (total, used, available, percent)
+        let cases = [
+            (0, 0, 0, 0),
+            (1000, 0, 1000, 0),
+            // Any use shows, like df
+            (1000, 1, 999, 1),
+            (1000, 500, 500, 50),
+            (1000, 501, 499, 51),
+            // Root's reserved blocks are left out: 900 of 950 usable bytes
+            (1000, 900, 50, 95),
+            // Only root can write: full
+            (1000, 960, 0, 100),
+        ];
+        for (total, used, available, percent) in cases {
+            assert_eq!(disk(total, used, available).usage(), percent, "{used} used, {available} available");
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Throwaway directory trees standing in for `/` in tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory under the system temp dir, removed on drop.
+pub struct FakeRoot {
+    path: PathBuf,
+}
+
+impl FakeRoot {
+    pub fn new() -> Self {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("nwidgets-sysmon-{}-{n}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `content` to `relative`, creating the parent directories.
+    pub fn file(&self, relative: &str, content: &str) -> &Self {
+        let path = self.path.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        self
+    }
+
+    pub fn dir(&self, relative: &str) -> &Self {
+        fs::create_dir_all(self.path.join(relative)).unwrap();
+        self
+    }
+
+    /// Symlink at `relative` pointing to `target`, as sysfs links devices and drivers.
+    pub fn link(&self, relative: &str, target: &str) -> &Self {
+        let path = self.path.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(target, path).unwrap();
+        self
+    }
+}
+
+impl Drop for FakeRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
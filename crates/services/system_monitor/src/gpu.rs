@@ -0,0 +1,300 @@
+//! GPU backends: amdgpu and Intel (i915/xe) through sysfs, NVIDIA through
+//! `nvidia-smi`. Every sysfs path is relative to a root so that a fake tree
+//! can stand in for `/`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::hwmon::{pick_temperature, read_trimmed, read_u64, temperatures};
+use crate::{GpuStats, GpuVendor};
+
+const VENDOR_AMD: &str = "0x1002";
+const VENDOR_INTEL: &str = "0x8086";
+const VENDOR_NVIDIA: &str = "0x10de";
+
+/// Labels of the amdgpu sensors, by preference.
+const AMD_LABELS: [&str; 2] = ["edge", "junction"];
+
+pub trait GpuBackend: Send {
+    /// Current readings; `None` values are not reported by the driver.
+    fn sample(&mut self, now: Instant) -> Vec<GpuStats>;
+}
+
+fn hwmon_temperature(device: &Path, labels: &[&str]) -> Option<f32> {
+    let hwmon = fs::read_dir(device.join("hwmon")).ok()?.flatten().next()?.path();
+    pick_temperature(&temperatures(&hwmon), labels)
+}
+
+fn round_temp(temp: Option<f32>) -> Option<u8> {
+    temp.map(|temp| temp.round().clamp(0.0, 255.0) as u8)
+}
+
+/// `/sys/class/drm/cardN/device` of an amdgpu card.
+pub struct AmdGpu {
+    device: PathBuf,
+}
+
+impl AmdGpu {
+    pub fn new(device: PathBuf) -> Self {
+        Self { device }
+    }
+}
+
+impl GpuBackend for AmdGpu {
+    fn sample(&mut self, _now: Instant) -> Vec<GpuStats> {
+        vec![GpuStats {
+            vendor: GpuVendor::Amd,
+            usage: read_u64(&self.device.join("gpu_busy_percent")).map(|busy| busy.min(100) as u8),
+            vram_used: read_u64(&self.device.join("mem_info_vram_used")),
+            vram_total: read_u64(&self.device.join("mem_info_vram_total")),
+            temp: round_temp(hwmon_temperature(&self.device, &AMD_LABELS)),
+        }]
+    }
+}
+
+/// Intel cards do not report their load: it is derived from the time spent in RC6 (idle).
+pub struct IntelGpu {
+    card: PathBuf,
+    last_rc6: Option<(Instant, u64)>,
+}
+
+impl IntelGpu {
+    pub fn new(card: PathBuf) -> Self {
+        Self { card, last_rc6: None }
+    }
+
+    /// Milliseconds spent idle since boot (i915, older i915, xe).
+    fn rc6_residency_ms(&self) -> Option<u64> {
+        ["gt/gt0/rc6_residency_ms", "power/rc6_residency_ms", "device/tile0/gt0/gtidle/idle_residency_ms"]
+            .iter()
+            .find_map(|path| read_u64(&self.card.join(path)))
+    }
+}
+
+/// Busy percentage from two idle residency readings.
+pub fn rc6_usage(previous: (Instant, u64), now: (Instant, u64)) -> Option<u8> {
+    let elapsed_ms = now.0.saturating_duration_since(previous.0).as_millis() as f64;
+    if elapsed_ms <= 0.0 {
+        return None;
+    }
+    let idle_ms = now.1.saturating_sub(previous.1) as f64;
+    Some(((1.0 - idle_ms / elapsed_ms).clamp(0.0, 1.0) * 100.0).round() as u8)
+}
+
+impl GpuBackend for IntelGpu {
+    fn sample(&mut self, now: Instant) -> Vec<GpuStats> {
+        let rc6 = self.rc6_residency_ms().map(|ms| (now, ms));
+        let usage = match (self.last_rc6, rc6) {
+            (Some(previous), Some(current)) => rc6_usage(previous, current),
+            _ => None,
+        };
+        self.last_rc6 = rc6;
+
+        let device = self.card.join("device");
+        vec![GpuStats {
+            vendor: GpuVendor::Intel,
+            usage,
+            // Integrated GPUs share system memory; xe reports its VRAM like amdgpu
+            vram_used: read_u64(&device.join("mem_info_vram_used")),
+            vram_total: read_u64(&device.join("mem_info_vram_total")),
+            temp: round_temp(hwmon_temperature(&device, &["pkg"])),
+        }]
+    }
+}
+
+/// Every NVIDIA GPU, as listed by `nvidia-smi`.
+#[derive(Default)]
+pub struct NvidiaGpus;
+
+/// Parses `nvidia-smi --query-gpu=utilization.gpu,memory.used,memory.total,temperature.gpu
+/// --format=csv,noheader,nounits`, memory being in MiB.
+pub fn parse_nvidia_smi(output: &str) -> Vec<GpuStats> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            // Unsupported fields read `[N/A]`
+            let fields: Vec<Option<u64>> = line.split(',').map(|field| field.trim().parse().ok()).collect();
+            let field = |i: usize| fields.get(i).copied().flatten();
+            GpuStats {
+                vendor: GpuVendor::Nvidia,
+                usage: field(0).map(|usage| usage.min(100) as u8),
+                vram_used: field(1).map(|mib| mib * 1024 * 1024),
+                vram_total: field(2).map(|mib| mib * 1024 * 1024),
+                temp: field(3).map(|temp| temp.min(255) as u8),
+            }
+        })
+        .collect()
+}
+
+impl GpuBackend for NvidiaGpus {
+    fn sample(&mut self, _now: Instant) -> Vec<GpuStats> {
+        let output = Command::new("nvidia-smi")
+            .args([
+                "--query-gpu=utilization.gpu,memory.used,memory.total,temperature.gpu",
+                "--format=csv,noheader,nounits",
+            ])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout)),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Backends for the cards of `<root>/sys/class/drm`.
+pub fn detect(root: &Path) -> Vec<Box<dyn GpuBackend>> {
+    let Ok(entries) = fs::read_dir(root.join("sys/class/drm")) else {
+        return Vec::new();
+    };
+    // cardN only, not its connectors (card0-DP-1)
+    let mut cards: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_prefix("card").is_some_and(|n| n.parse::<u32>().is_ok())
+        })
+        .map(|entry| entry.path())
+        .collect();
+    cards.sort();
+
+    let mut backends: Vec<Box<dyn GpuBackend>> = Vec::new();
+    let mut nvidia = root.join("proc/driver/nvidia/gpus").is_dir();
+    for card in cards {
+        let device = card.join("device");
+        let driver = fs::read_link(device.join("driver"))
+            .ok()
+            .and_then(|driver| driver.file_name().map(|name| name.to_string_lossy().into_owned()));
+        match read_trimmed(&device.join("vendor")).as_deref() {
+            Some(VENDOR_AMD) if driver.as_deref() != Some("radeon") => backends.push(Box::new(AmdGpu::new(device))),
+            Some(VENDOR_INTEL) => backends.push(Box::new(IntelGpu::new(card))),
+            Some(VENDOR_NVIDIA) => nvidia = true,
+            _ => {}
+        }
+    }
+    if nvidia {
+        backends.push(Box::new(NvidiaGpus));
+    }
+    backends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeRoot;
+    use std::time::Duration;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    /// `/sys/class/drm/<card>` of a PCI card, linked to its device like sysfs does.
+    fn card(root: &FakeRoot, card: &str, pci: &str, vendor: &str, driver: &str) {
+        let device = format!("sys/devices/pci0000:00/{pci}");
+        root.file(&format!("{device}/vendor"), &format!("{vendor}\n"))
+            .dir(&format!("sys/bus/pci/drivers/{driver}"))
+            .link(&format!("{device}/driver"), &format!("../../../bus/pci/drivers/{driver}"))
+            .dir(&format!("{device}/drm/{card}"))
+            .link(&format!("sys/class/drm/{card}"), &format!("../../devices/pci0000:00/{pci}/drm/{card}"))
+            .link(&format!("{device}/drm/{card}/device"), "../..");
+    }
+
+    fn amdgpu(root: &FakeRoot) {
+        card(root, "card1", "0000:03:00.0", "0x1002", "amdgpu");
+        let device = "sys/devices/pci0000:00/0000:03:00.0";
+        root.file(&format!("{device}/gpu_busy_percent"), "42\n")
+            .file(&format!("{device}/mem_info_vram_used"), &format!("{}\n", 2 * GIB))
+            .file(&format!("{device}/mem_info_vram_total"), &format!("{}\n", 16 * GIB))
+            .file(&format!("{device}/hwmon/hwmon3/name"), "amdgpu\n")
+            .file(&format!("{device}/hwmon/hwmon3/temp1_input"), "51000\n")
+            .file(&format!("{device}/hwmon/hwmon3/temp1_label"), "edge\n")
+            .file(&format!("{device}/hwmon/hwmon3/temp2_input"), "68000\n")
+            .file(&format!("{device}/hwmon/hwmon3/temp2_label"), "junction\n")
+            .file(&format!("{device}/hwmon/hwmon3/temp3_input"), "60000\n")
+            .file(&format!("{device}/hwmon/hwmon3/temp3_label"), "mem\n");
+    }
+
+    fn sample_all(root: &FakeRoot, now: Instant) -> Vec<GpuStats> {
+        detect(root.path()).iter_mut().flat_map(|backend| backend.sample(now)).collect()
+    }
+
+    #[test]
+    fn amdgpu_card() {
+        let root = FakeRoot::new();
+        amdgpu(&root);
+        assert_eq!(
+            sample_all(&root, Instant::now()),
+            vec![GpuStats {
+                vendor: GpuVendor::Amd,
+                usage: Some(42),
+                vram_used: Some(2 * GIB),
+                vram_total: Some(16 * GIB),
+                temp: Some(51),
+            }]
+        );
+    }
+
+    #[test]
+    fn amdgpu_junction_without_edge() {
+        let root = FakeRoot::new();
+        amdgpu(&root);
+        let hwmon = "sys/devices/pci0000:00/0000:03:00.0/hwmon/hwmon3";
+        fs::remove_file(root.path().join(hwmon).join("temp1_input")).unwrap();
+        fs::remove_file(root.path().join(hwmon).join("temp1_label")).unwrap();
+        assert_eq!(sample_all(&root, Instant::now())[0].temp, Some(68));
+    }
+
+    #[test]
+    fn i915_usage_from_rc6() {
+        let root = FakeRoot::new();
+        card(&root, "card0", "0000:00:02.0", "0x8086", "i915");
+        let rc6 = "sys/devices/pci0000:00/0000:00:02.0/drm/card0/gt/gt0/rc6_residency_ms";
+        root.file(rc6, "10000\n");
+
+        let mut backends = detect(root.path());
+        assert_eq!(backends.len(), 1);
+        let start = Instant::now();
+        let first = backends[0].sample(start);
+        assert_eq!(first[0].vendor, GpuVendor::Intel);
+        // Nothing to compare with yet
+        assert_eq!(first[0].usage, None);
+        assert_eq!(first[0].vram_total, None);
+
+        // 250 ms idle over one second
+        root.file(rc6, "10250\n");
+        let second = backends[0].sample(start + Duration::from_secs(1));
+        assert_eq!(second[0].usage, Some(75));
+    }
+
+    #[test]
+    fn radeon_card_is_skipped() {
+        let root = FakeRoot::new();
+        card(&root, "card0", "0000:01:00.0", "0x1002", "radeon");
+        root.file("sys/devices/pci0000:00/0000:01:00.0/gpu_busy_percent", "10\n");
+        assert!(detect(root.path()).is_empty());
+    }
+
+    #[test]
+    fn connectors_are_ignored() {
+        let root = FakeRoot::new();
+        amdgpu(&root);
+        let device = "sys/devices/pci0000:00/0000:03:00.0";
+        // Connectors have no vendor file but resolve into the card's device
+        root.dir(&format!("{device}/drm/card1/card1-DP-1"))
+            .link("sys/class/drm/card1-DP-1", "../../devices/pci0000:00/0000:03:00.0/drm/card1/card1-DP-1")
+            .link(&format!("{device}/drm/card1/card1-DP-1/device"), "../../..")
+            .dir("sys/class/drm/renderD128")
+            .file("sys/class/drm/version", "drm 1.1.0 20060810\n");
+        assert_eq!(detect(root.path()).len(), 1);
+    }
+
+    #[test]
+    fn nvidia_smi_output() {
+        let stats = parse_nvidia_smi("37, 1024, 8192, 55\n[N/A], [N/A], 4096, 40\n");
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].usage, Some(37));
+        assert_eq!(stats[0].vram_used, Some(GIB));
+        assert_eq!(stats[1].usage, None);
+        assert_eq!(stats[1].temp, Some(40));
+    }
+}
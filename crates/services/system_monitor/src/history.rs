@@ -0,0 +1,83 @@
+//! Fixed-size time series of the samples, for graphs.
+
+use std::collections::VecDeque;
+
+use crate::{NetRate, SystemStats};
+
+/// The last `capacity` values pushed, oldest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History<T> {
+    samples: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T: Copy> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Appends `value`, dropping the oldest one when full.
+    pub fn push(&mut self, value: T) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = T> + ExactSizeIterator + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn latest(&self) -> Option<T> {
+        self.samples.back().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Series of [`SystemStats`], one entry per sample. Missing readings are kept
+/// as `None` so that every series stays aligned in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsHistory {
+    pub cpu: History<u8>,
+    pub gpu: History<Option<u8>>,
+    pub ram: History<u8>,
+    pub cpu_temp: History<Option<u8>>,
+    pub gpu_temp: History<Option<u8>>,
+    pub net: History<NetRate>,
+}
+
+impl StatsHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cpu: History::new(capacity),
+            gpu: History::new(capacity),
+            ram: History::new(capacity),
+            cpu_temp: History::new(capacity),
+            gpu_temp: History::new(capacity),
+            net: History::new(capacity),
+        }
+    }
+
+    pub fn push(&mut self, stats: &SystemStats) {
+        self.cpu.push(stats.cpu);
+        self.gpu.push(stats.gpu);
+        self.ram.push(stats.ram);
+        self.cpu_temp.push(stats.cpu_temp);
+        self.gpu_temp.push(stats.gpu_temp);
+        self.net.push(stats.net);
+    }
+}
@@ -0,0 +1,127 @@
+//! Temperatures from the hwmon sysfs class.
+
+use std::fs;
+use std::path::Path;
+
+/// Drivers of CPU sensors, by preference.
+const CPU_DRIVERS: [&str; 4] = ["k10temp", "zenpower", "coretemp", "cpu_thermal"];
+/// Labels of the whole-package sensor, by preference.
+const CPU_LABELS: [&str; 3] = ["Tdie", "Tctl", "Package id 0"];
+
+pub(crate) fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|content| content.trim().to_string())
+}
+
+pub(crate) fn read_u64(path: &Path) -> Option<u64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+/// `(label, °C)` of every `temp*_input` of one hwmon directory. Unlabelled
+/// sensors get their file name, e.g. `temp1`.
+pub fn temperatures(hwmon: &Path) -> Vec<(String, f32)> {
+    let Ok(entries) = fs::read_dir(hwmon) else {
+        return Vec::new();
+    };
+    let mut temps: Vec<(String, f32)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file = entry.file_name().to_string_lossy().into_owned();
+            let sensor = file.strip_suffix("_input").filter(|name| name.starts_with("temp"))?;
+            let millidegrees = read_trimmed(&entry.path())?.parse::<i64>().ok()?;
+            let label = read_trimmed(&hwmon.join(format!("{sensor}_label"))).unwrap_or_else(|| sensor.to_string());
+            Some((label, millidegrees as f32 / 1000.0))
+        })
+        .collect();
+    temps.sort_by(|a, b| a.0.cmp(&b.0));
+    temps
+}
+
+/// Preferred sensor among `temps`: the first of `labels` present, else `temp1`, else any.
+pub fn pick_temperature(temps: &[(String, f32)], labels: &[&str]) -> Option<f32> {
+    labels
+        .iter()
+        .find_map(|label| temps.iter().find(|(name, _)| name == label))
+        .or_else(|| temps.iter().find(|(name, _)| name == "temp1"))
+        .or(temps.first())
+        .map(|(_, temp)| *temp)
+}
+
+/// Package temperature of the CPU, from `<root>/sys/class/hwmon`.
+pub fn cpu_temperature(root: &Path) -> Option<f32> {
+    let entries = fs::read_dir(root.join("sys/class/hwmon")).ok()?;
+    let sensors: Vec<(String, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| Some((read_trimmed(&entry.path().join("name"))?, entry.path())))
+        .collect();
+    CPU_DRIVERS.iter().find_map(|driver| {
+        let (_, path) = sensors.iter().find(|(name, _)| name == driver)?;
+        pick_temperature(&temperatures(path), &CPU_LABELS)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FakeRoot;
+
+    fn sensor(root: &FakeRoot, hwmon: &str, name: &str, temps: &[(&str, Option<&str>, &str)]) {
+        let dir = format!("sys/class/hwmon/{hwmon}");
+        root.file(&format!("{dir}/name"), &format!("{name}\n"));
+        for (sensor, label, millidegrees) in temps {
+            root.file(&format!("{dir}/{sensor}_input"), &format!("{millidegrees}\n"));
+            if let Some(label) = label {
+                root.file(&format!("{dir}/{sensor}_label"), &format!("{label}\n"));
+            }
+        }
+    }
+
+    #[test]
+    fn k10temp_prefers_tctl_over_ccds() {
+        let root = FakeRoot::new();
+        sensor(&root, "hwmon0", "nvme", &[("temp1", Some("Composite"), "38850")]);
+        sensor(
+            &root,
+            "hwmon1",
+            "k10temp",
+            &[("temp1", Some("Tctl"), "54250"), ("temp3", Some("Tccd1"), "48000")],
+        );
+        sensor(&root, "hwmon2", "amdgpu", &[("temp1", Some("edge"), "45000")]);
+        assert_eq!(cpu_temperature(root.path()), Some(54.25));
+    }
+
+    #[test]
+    fn coretemp_package() {
+        let root = FakeRoot::new();
+        sensor(&root, "hwmon0", "acpitz", &[("temp1", None, "27800")]);
+        sensor(
+            &root,
+            "hwmon4",
+            "coretemp",
+            &[("temp2", Some("Core 0"), "61000"), ("temp1", Some("Package id 0"), "64000")],
+        );
+        assert_eq!(cpu_temperature(root.path()), Some(64.0));
+    }
+
+    #[test]
+    fn k10temp_before_coretemp() {
+        let root = FakeRoot::new();
+        sensor(&root, "hwmon0", "coretemp", &[("temp1", Some("Package id 0"), "70000")]);
+        sensor(&root, "hwmon1", "k10temp", &[("temp1", Some("Tctl"), "50000")]);
+        assert_eq!(cpu_temperature(root.path()), Some(50.0));
+    }
+
+    #[test]
+    fn no_cpu_sensor() {
+        let root = FakeRoot::new();
+        sensor(&root, "hwmon0", "acpitz", &[("temp1", None, "27800")]);
+        assert_eq!(cpu_temperature(root.path()), None);
+        assert_eq!(cpu_temperature(&root.path().join("missing")), None);
+    }
+
+    #[test]
+    fn unlabelled_sensors() {
+        let temps = vec![("temp2".to_string(), 40.0), ("temp1".to_string(), 30.0)];
+        assert_eq!(pick_temperature(&temps, &["edge"]), Some(30.0));
+        assert_eq!(pick_temperature(&[], &["edge"]), None);
+    }
+}
@@ -1,16 +1,182 @@
 use futures::channel::mpsc;
 use futures::StreamExt;
 use gpui::{App, AppContext, AsyncApp, Context, Entity, EventEmitter, Global};
-use std::time::Duration;
+use nwidgets_demand::{next_sample, Demand, Merge};
+use nwidgets_service_config::{ConfigChanged, ConfigService, SystemMonitorConfig};
+use nwidgets_service_notification::NotificationService;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use tokio::fs;
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+mod disk;
+#[cfg(test)]
+mod fixture;
+mod gpu;
+mod history;
+mod hwmon;
+mod net;
+mod proc;
+
+pub use nwidgets_demand::{DemandGuard, Rate};
+pub use disk::{device_name, parse_diskstats, parse_mounts, DiskCounters, DiskSampler};
+pub use gpu::{detect as detect_gpus, parse_nvidia_smi, rc6_usage, AmdGpu, GpuBackend, IntelGpu, NvidiaGpus};
+pub use history::{History, StatsHistory};
+pub use hwmon::{cpu_temperature, pick_temperature, temperatures};
+pub use net::{format_bytes, format_rate, parse_net_dev, InterfaceCounters, NetSampler};
+pub use proc::{
+    core_stats, parse_cpuinfo_mhz, parse_loadavg, parse_meminfo, parse_proc_stat, CpuSampler, CpuTimes, ProcStat,
+};
+
+/// Samples kept in [`SystemMonitorService::history`], 5 minutes at the default 2 s interval.
+const HISTORY_LEN: usize = 150;
+/// Points of free space a mount must regain before a new low space notification.
+const DISK_SPACE_HYSTERESIS: u8 = 5;
+
+/// Throughput in bytes per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NetRate {
+    pub rx: u64,
+    pub tx: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceStats {
+    /// Interface name, e.g. `wlan0`.
+    pub name: String,
+    pub rate: NetRate,
+    /// Bytes since boot.
+    pub rx_total: u64,
+    pub tx_total: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoreStats {
+    pub usage: u8,
+    pub mhz: Option<u32>,
+}
+
+/// Run queue length averaged over 1, 5 and 15 minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LoadAverage {
+    pub one: f32,
+    pub five: f32,
+    pub fifteen: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuVendor {
+    Amd,
+    Intel,
+    Nvidia,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuStats {
+    pub vendor: GpuVendor,
+    pub usage: Option<u8>,
+    /// Bytes of dedicated memory.
+    pub vram_used: Option<u64>,
+    pub vram_total: Option<u64>,
+    /// °C
+    pub temp: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskStats {
+    /// Mount point, e.g. `/home`.
+    pub mount: String,
+    /// Bytes; blocks reserved for root count in neither `used` nor `available`.
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+    /// Bytes per second read from and written to the underlying device.
+    pub read: u64,
+    pub write: u64,
+}
+
+impl DiskStats {
+    /// Used space in percent of what non-root users can fill, as `df` shows it.
+    pub fn usage(&self) -> u8 {
+        let usable = self.used + self.available;
+        if usable == 0 {
+            return 0;
+        }
+        (self.used as f64 * 100.0 / usable as f64).ceil().min(100.0) as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct SystemStats {
+    /// Usage since the previous sample, in percent.
     pub cpu: u8,
-    pub gpu: u8,
+    pub cores: Vec<CoreStats>,
+    pub load: LoadAverage,
+    /// Average frequency of the cores.
+    pub cpu_mhz: Option<u32>,
+    /// Usage of the main GPU, see [`SystemStats::gpus`].
+    pub gpu: Option<u8>,
     pub ram: u8,
     pub cpu_temp: Option<u8>,
     pub gpu_temp: Option<u8>,
+    /// Cards with dedicated memory first, the main GPU being the first one.
+    pub gpus: Vec<GpuStats>,
+    /// Every interface but loopback, by name.
+    pub interfaces: Vec<InterfaceStats>,
+    /// Sum of the interfaces.
+    pub net: NetRate,
+    /// Configured mounts that are currently mounted, in configuration order.
+    pub disks: Vec<DiskStats>,
+}
+
+/// What a view needs from the collector, see [`SystemMonitorService::request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MonitorDemand {
+    /// CPU, memory and network are sampled at any rate but [`Rate::Off`].
+    pub rate: Rate,
+    /// GPU readings and temperature sensors (sysfs, `nvidia-smi`).
+    pub gpu: bool,
+    /// Mount usage and disk throughput.
+    pub disks: bool,
+}
+
+impl Merge for MonitorDemand {
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            rate: self.rate.merge(&other.rate),
+            gpu: self.gpu || other.gpu,
+            disks: self.disks || other.disks,
+        }
+    }
+}
+
+/// What the collector needs from the configuration.
+#[derive(Debug, Clone, PartialEq)]
+struct CollectorSettings {
+    interval: Duration,
+    idle_interval: Duration,
+    mounts: Vec<String>,
+}
+
+impl CollectorSettings {
+    /// Time between two samples, `None` when nothing is requested.
+    fn interval(&self, rate: Rate) -> Option<Duration> {
+        match rate {
+            Rate::Off => None,
+            Rate::Slow => Some(self.idle_interval),
+            Rate::Fast => Some(self.interval),
+        }
+    }
+}
+
+impl From<&SystemMonitorConfig> for CollectorSettings {
+    fn from(config: &SystemMonitorConfig) -> Self {
+        Self {
+            interval: Duration::from_secs(config.interval_secs),
+            idle_interval: Duration::from_secs(config.idle_interval_secs),
+            mounts: config.mounts.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +184,13 @@ pub struct SystemStatsChanged;
 
 pub struct SystemMonitorService {
     pub stats: SystemStats,
+    /// The last samples taken at [`Rate::Fast`], oldest first.
+    pub history: StatsHistory,
+    /// Mounts already notified as low on space.
+    low_disk_space: HashSet<String>,
+    demand: Demand<MonitorDemand>,
+    /// Keeps the disks sampled for the low space notification.
+    disk_watch: Option<DemandGuard>,
 }
 
 impl EventEmitter<SystemStatsChanged> for SystemMonitorService {}
@@ -31,59 +204,116 @@ impl SystemMonitorService {
     }
 
     pub fn init(cx: &mut App) -> Entity<Self> {
-        let service = cx.new(|_cx| Self {
-            stats: SystemStats::default(),
+        let (demand, mut demand_rx) = Demand::new();
+        let service = cx.new(|cx| {
+            let mut service = Self {
+                stats: SystemStats::default(),
+                history: StatsHistory::new(HISTORY_LEN),
+                low_disk_space: HashSet::new(),
+                demand,
+                disk_watch: None,
+            };
+            service.update_disk_watch(cx);
+            service
         });
 
         cx.set_global(GlobalSystemMonitorService(service.clone()));
 
-        let (tx, mut rx) = mpsc::unbounded::<SystemStats>();
+        let (tx, mut rx) = mpsc::unbounded::<(SystemStats, Rate)>();
+
+        // Sampling intervals and mounts, re-read by the collector after each sample
+        let settings = CollectorSettings::from(&ConfigService::config(cx).system_monitor);
+        let (settings_tx, settings_rx) = tokio::sync::watch::channel(settings);
+        let service_entity = service.clone();
+        cx.subscribe(&ConfigService::global(cx), move |config, _: &ConfigChanged, cx| {
+            let new_settings = CollectorSettings::from(&config.read(cx).config.system_monitor);
+            settings_tx.send_if_modified(|settings| {
+                let changed = *settings != new_settings;
+                *settings = new_settings;
+                changed
+            });
+            service_entity.update(cx, |srv, cx| srv.update_disk_watch(cx));
+        })
+        .detach();
 
         // Background system metrics collector thread
         gpui_tokio::Tokio::spawn(cx, async move {
+            let mut cpu_sampler = CpuSampler::default();
+            let mut gpu_backends = Some(gpu::detect(Path::new("/")));
+            let mut net_sampler = NetSampler::default();
+            let mut disk_sampler = DiskSampler::default();
             loop {
+                let demand = *demand_rx.borrow_and_update();
+                let settings = settings_rx.borrow().clone();
+                let Some(interval) = settings.interval(demand.rate) else {
+                    // No view needs the stats: sleep until one does
+                    next_sample(&mut demand_rx, &demand, None).await;
+                    continue;
+                };
                 let mut stats = SystemStats::default();
 
-                // Read MemTotal & MemAvailable from /proc/meminfo
                 if let Ok(meminfo) = fs::read_to_string("/proc/meminfo").await {
-                    let mut total = 0u64;
-                    let mut avail = 0u64;
-                    for line in meminfo.lines() {
-                        if line.starts_with("MemTotal:") {
-                            total = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                        } else if line.starts_with("MemAvailable:") {
-                            avail = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-                        }
-                    }
-                    if total > 0 {
-                        let used = total.saturating_sub(avail);
-                        stats.ram = ((used as f32 / total as f32) * 100.0).round() as u8;
-                    }
+                    stats.ram = parse_meminfo(&meminfo).unwrap_or(0);
                 }
 
-                // Read CPU load estimation from /proc/stat
-                if let Ok(stat) = fs::read_to_string("/proc/stat").await {
-                    if let Some(first_line) = stat.lines().next() {
-                        let parts: Vec<u64> = first_line
-                            .split_whitespace()
-                            .skip(1)
-                            .filter_map(|s| s.parse().ok())
-                            .collect();
-                        if parts.len() >= 4 {
-                            let idle = parts[3];
-                            let total: u64 = parts.iter().sum();
-                            let non_idle = total.saturating_sub(idle);
-                            if total > 0 {
-                                stats.cpu = ((non_idle as f32 / total as f32) * 100.0).round() as u8;
-                            }
-                        }
+                // Usage from the jiffies elapsed since the previous sample
+                if let Some(stat) = fs::read_to_string("/proc/stat").await.ok().and_then(|stat| parse_proc_stat(&stat)) {
+                    let (cpu, cores) = cpu_sampler.sample(stat);
+                    let mhz = match fs::read_to_string("/proc/cpuinfo").await {
+                        Ok(cpuinfo) => parse_cpuinfo_mhz(&cpuinfo),
+                        Err(_) => Vec::new(),
+                    };
+                    stats.cpu = cpu;
+                    stats.cpu_mhz = (!mhz.is_empty()).then(|| mhz.iter().sum::<u32>() / mhz.len() as u32);
+                    stats.cores = core_stats(cores, &mhz);
+                }
+
+                if let Some(load) = fs::read_to_string("/proc/loadavg").await.ok().and_then(|load| parse_loadavg(&load)) {
+                    stats.load = load;
+                }
+
+                // sysfs reads and nvidia-smi block, the backends come back with the readings
+                let backends = if demand.gpu { gpu_backends.take() } else { None };
+                if let Some(mut backends) = backends {
+                    let sample = tokio::task::spawn_blocking(move || {
+                        let now = Instant::now();
+                        let gpus: Vec<GpuStats> = backends.iter_mut().flat_map(|backend| backend.sample(now)).collect();
+                        let cpu_temp = hwmon::cpu_temperature(Path::new("/"));
+                        (backends, gpus, cpu_temp)
+                    })
+                    .await;
+                    if let Ok((backends, mut gpus, cpu_temp)) = sample {
+                        gpus.sort_by_key(|gpu| gpu.vram_total.is_none());
+                        stats.gpu = gpus.first().and_then(|gpu| gpu.usage);
+                        stats.gpu_temp = gpus.first().and_then(|gpu| gpu.temp);
+                        stats.gpus = gpus;
+                        stats.cpu_temp = cpu_temp.map(|temp| temp.round().clamp(0.0, 255.0) as u8);
+                        gpu_backends = Some(backends);
                     }
                 }
 
-                stats.gpu = (stats.cpu / 2).max(10); // GPU metric placeholder
+                if let Ok(net_dev) = fs::read_to_string("/proc/net/dev").await {
+                    stats.interfaces = net_sampler.sample(parse_net_dev(&net_dev), Instant::now());
+                    stats.net = stats.interfaces.iter().fold(NetRate::default(), |total, interface| NetRate {
+                        rx: total.rx + interface.rate.rx,
+                        tx: total.tx + interface.rate.tx,
+                    });
+                }
+
+                let diskstats = if demand.disks { fs::read_to_string("/proc/diskstats").await.ok() } else { None };
+                if let Some(diskstats) = diskstats {
+                    let rates = disk_sampler.sample(parse_diskstats(&diskstats), Instant::now());
+                    let mount_table = fs::read_to_string("/proc/self/mounts").await.unwrap_or_default();
+                    // statvfs blocks on unreachable network filesystems
+                    let disks = tokio::task::spawn_blocking(move || {
+                        disk::disk_stats(&settings.mounts, &parse_mounts(&mount_table), &rates)
+                    })
+                    .await;
+                    stats.disks = disks.unwrap_or_default();
+                }
 
-                let _ = tx.unbounded_send(stats);
-                tokio::time::sleep(Duration::from_secs(2)).await;
+                let _ = tx.unbounded_send((stats, demand.rate));
+                next_sample(&mut demand_rx, &demand, Some(interval)).await;
             }
         })
         .detach();
@@ -93,14 +323,20 @@ impl SystemMonitorService {
         cx.spawn(|cx: &mut AsyncApp| {
             let cx = cx.clone();
             async move {
-                while let Some(new_stats) = rx.next().await {
+                while let Some((new_stats, rate)) = rx.next().await {
                     let _ = cx.update(|cx| {
                         service_entity.update(cx, |srv, cx| {
+                            // The graphs move on even when the readings are the same, at
+                            // the view interval: background samples would stretch them
+                            if rate == Rate::Fast {
+                                srv.history.push(&new_stats);
+                            }
                             if srv.stats != new_stats {
                                 srv.stats = new_stats;
-                                cx.emit(SystemStatsChanged);
-                                cx.notify();
+                                srv.check_disk_space(cx);
                             }
+                            cx.emit(SystemStatsChanged);
+                            cx.notify();
                         });
                     });
                 }
@@ -110,4 +346,57 @@ impl SystemMonitorService {
 
         service
     }
+
+    /// Keeps the collectors of `demand` running until the guard is dropped.
+    pub fn request(&self, demand: MonitorDemand) -> DemandGuard {
+        self.demand.request(demand)
+    }
+
+    fn update_disk_watch(&mut self, cx: &mut Context<Self>) {
+        let enabled = ConfigService::config(cx).system_monitor.low_disk_space > 0;
+        if enabled != self.disk_watch.is_some() {
+            self.disk_watch = enabled.then(|| {
+                self.demand.request(MonitorDemand {
+                    rate: Rate::Slow,
+                    disks: true,
+                    ..Default::default()
+                })
+            });
+        }
+    }
+
+    fn check_disk_space(&mut self, cx: &mut Context<Self>) {
+        let threshold = ConfigService::config(cx).system_monitor.low_disk_space;
+        let mut low = Vec::new();
+        for disk in &self.stats.disks {
+            let free = 100 - disk.usage();
+            if free < threshold {
+                if self.low_disk_space.insert(disk.mount.clone()) {
+                    low.push((disk.mount.clone(), disk.available));
+                }
+            } else if free >= threshold.saturating_add(DISK_SPACE_HYSTERESIS) {
+                // Space was freed: warn on the next drop
+                self.low_disk_space.remove(&disk.mount);
+            }
+        }
+        // Forget unmounted filesystems
+        let disks = &self.stats.disks;
+        self.low_disk_space.retain(|mount| disks.iter().any(|disk| &disk.mount == mount));
+
+        if low.is_empty() {
+            return;
+        }
+        let notifications = NotificationService::init(cx);
+        for (mount, available) in low {
+            notifications.update(cx, |notifications, cx| {
+                notifications.notify(
+                    format!("{mount}: low disk space"),
+                    format!("{} left", format_bytes(available)),
+                    "drive-harddisk",
+                    1,
+                    cx,
+                );
+            });
+        }
+    }
 }
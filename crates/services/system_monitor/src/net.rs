@@ -0,0 +1,204 @@
+//! Network throughput from the byte counters of `/proc/net/dev`.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::{InterfaceStats, NetRate};
+
+/// Byte counters of one interface since boot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// Parses `/proc/net/dev`. The loopback interface is left out.
+pub fn parse_net_dev(content: &str) -> Vec<InterfaceCounters> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let name = name.trim();
+            if name == "lo" {
+                return None;
+            }
+            // rx: bytes packets errs drop fifo frame compressed multicast, then tx: bytes ...
+            let fields: Vec<u64> = counters.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+            if fields.len() < 16 {
+                return None;
+            }
+            Some(InterfaceCounters {
+                name: name.to_string(),
+                rx_bytes: fields[0],
+                tx_bytes: fields[8],
+            })
+        })
+        .collect()
+}
+
+/// Turns successive counter readings into rates.
+#[derive(Default)]
+pub struct NetSampler {
+    last: Option<(Instant, HashMap<String, (u64, u64)>)>,
+}
+
+impl NetSampler {
+    /// Rates since the previous call, zero on the first one. Interfaces are sorted by name.
+    pub fn sample(&mut self, counters: Vec<InterfaceCounters>, now: Instant) -> Vec<InterfaceStats> {
+        let previous = self.last.take();
+        let elapsed = previous
+            .as_ref()
+            .map(|(at, _)| now.saturating_duration_since(*at).as_secs_f64())
+            .unwrap_or(0.0);
+
+        let mut interfaces: Vec<InterfaceStats> = counters
+            .iter()
+            .map(|counter| {
+                let before = previous.as_ref().and_then(|(_, last)| last.get(&counter.name));
+                // Counters restart when an interface comes back up
+                let rate = |now: u64, before: u64| {
+                    if elapsed > 0.0 {
+                        (now.saturating_sub(before) as f64 / elapsed).round() as u64
+                    } else {
+                        0
+                    }
+                };
+                InterfaceStats {
+                    name: counter.name.clone(),
+                    rate: match before {
+                        Some(&(rx, tx)) => NetRate {
+                            rx: rate(counter.rx_bytes, rx),
+                            tx: rate(counter.tx_bytes, tx),
+                        },
+                        None => NetRate::default(),
+                    },
+                    rx_total: counter.rx_bytes,
+                    tx_total: counter.tx_bytes,
+                }
+            })
+            .collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let last = counters
+            .into_iter()
+            .map(|counter| (counter.name, (counter.rx_bytes, counter.tx_bytes)))
+            .collect();
+        self.last = Some((now, last));
+        interfaces
+    }
+}
+
+/// Human readable size, e.g. `1.2 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 || value >= 100.0 {
+        format!("{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Human readable rate, e.g. `1.2 MB/s`.
+pub fn format_rate(bytes_per_sec: u64) -> String {
+    format!("{}/s", format_bytes(bytes_per_sec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 8843270   61447    0    0    0     0          0         0  8843270   61447    0    0    0     0       0          0
+enp3s0:       0       0    0    0    0     0          0         0        0       0    0    0    0     0       0          0
+ wlan0: 3065125412 2315847    0  137    0     0          0         0 192354803  943117    0    0    0     0       0          0
+docker0: truncated line
+";
+
+    fn counters(name: &str, rx_bytes: u64, tx_bytes: u64) -> InterfaceCounters {
+        InterfaceCounters {
+            name: name.to_string(),
+            rx_bytes,
+            tx_bytes,
+        }
+    }
+
+    #[test]
+    fn parses_net_dev() {
+        assert_eq!(
+            parse_net_dev(NET_DEV),
+            [counters("enp3s0", 0, 0), counters("wlan0", 3065125412, 192354803)]
+        );
+    }
+
+    /// (rx, tx) rate of each interface, by name.
+    fn rates(stats: &[InterfaceStats]) -> Vec<(&str, u64, u64)> {
+        stats.iter().map(|stats| (stats.name.as_str(), stats.rate.rx, stats.rate.tx)).collect()
+    }
+
+    #[test]
+    fn samples_rates() {
+        let start = Instant::now();
+        let mut sampler = NetSampler::default();
+
+        // (seconds since start, counters, expected rates)
+        let steps: [(u64, Vec<InterfaceCounters>, Vec<(&str, u64, u64)>); 5] = [
+            // Nothing to compare with yet
+            (0, vec![counters("wlan0", 1000, 500)], vec![("wlan0", 0, 0)]),
+            (2, vec![counters("wlan0", 5000, 1500)], vec![("wlan0", 2000, 500)]),
+            // Counters back to zero when the interface went down and up, an
+            // interface showing up; sorted by name
+            (
+                3,
+                vec![counters("wlan0", 300, 100), counters("enp3s0", 800, 0)],
+                vec![("enp3s0", 0, 0), ("wlan0", 0, 0)],
+            ),
+            // Rounded to the byte
+            (
+                6,
+                vec![counters("wlan0", 1300, 101), counters("enp3s0", 800, 0)],
+                vec![("enp3s0", 0, 0), ("wlan0", 333, 0)],
+            ),
+            // No time elapsed
+            (6, vec![counters("wlan0", 9000, 900)], vec![("wlan0", 0, 0)]),
+        ];
+        for (secs, counters, expected) in steps {
+            let stats = sampler.sample(counters, start + Duration::from_secs(secs));
+            assert_eq!(rates(&stats), expected, "at {secs} s");
+        }
+    }
+
+    #[test]
+    fn keeps_totals() {
+        let mut sampler = NetSampler::default();
+        let stats = sampler.sample(parse_net_dev(NET_DEV), Instant::now());
+        assert_eq!((stats[1].rx_total, stats[1].tx_total), (3065125412, 192354803));
+    }
+
+    #[test]
+    fn formats_sizes() {
+        let cases = [
+            (0, "0 B"),
+            (999, "999 B"),
+            (1000, "1.0 kB"),
+            (1234, "1.2 kB"),
+            (99_949, "99.9 kB"),
+            (150_000, "150 kB"),
+            (3_065_125_412, "3.1 GB"),
+            (u64::MAX, "18446744 TB"),
+        ];
+        for (bytes, text) in cases {
+            assert_eq!(format_bytes(bytes), text, "{bytes}");
+        }
+        assert_eq!(format_rate(1_500_000), "1.5 MB/s");
+    }
+}
@@ -0,0 +1,260 @@
+//! Parsers for `/proc/stat`, `/proc/loadavg`, `/proc/cpuinfo` and `/proc/meminfo`.
+
+use crate::{CoreStats, LoadAverage};
+
+/// Jiffies spent by one CPU (or all of them) since boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuTimes {
+    /// idle + iowait
+    pub idle: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Busy percentage between `previous` and `self`.
+    pub fn usage_since(&self, previous: &CpuTimes) -> u8 {
+        let total = self.total.saturating_sub(previous.total);
+        let idle = self.idle.saturating_sub(previous.idle);
+        if total == 0 {
+            return 0;
+        }
+        (total.saturating_sub(idle) as f64 * 100.0 / total as f64).round().min(100.0) as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProcStat {
+    pub total: CpuTimes,
+    /// `cpu0`, `cpu1`... in order.
+    pub cores: Vec<CpuTimes>,
+}
+
+fn parse_cpu_line(fields: &str) -> Option<CpuTimes> {
+    let values: Vec<u64> = fields.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+    if values.len() < 4 {
+        return None;
+    }
+    // user nice system idle iowait irq softirq steal; guest time is already in user
+    let total = values.iter().take(8).sum();
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    Some(CpuTimes { idle, total })
+}
+
+/// Parses the `cpu` lines of `/proc/stat`.
+pub fn parse_proc_stat(content: &str) -> Option<ProcStat> {
+    let mut stat = ProcStat::default();
+    let mut found = false;
+    for line in content.lines() {
+        let Some((name, fields)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        if name == "cpu" {
+            stat.total = parse_cpu_line(fields)?;
+            found = true;
+        } else if name.strip_prefix("cpu").is_some_and(|n| n.parse::<u32>().is_ok()) {
+            stat.cores.push(parse_cpu_line(fields)?);
+        }
+    }
+    found.then_some(stat)
+}
+
+/// Turns successive `/proc/stat` readings into current usage.
+#[derive(Default)]
+pub struct CpuSampler {
+    last: Option<ProcStat>,
+}
+
+impl CpuSampler {
+    /// Total and per core usage since the previous call; the first call
+    /// gives the average since boot.
+    pub fn sample(&mut self, stat: ProcStat) -> (u8, Vec<u8>) {
+        let previous = self.last.take().unwrap_or_default();
+        let total = stat.total.usage_since(&previous.total);
+        let cores = stat
+            .cores
+            .iter()
+            .enumerate()
+            .map(|(i, core)| core.usage_since(&previous.cores.get(i).copied().unwrap_or_default()))
+            .collect();
+        self.last = Some(stat);
+        (total, cores)
+    }
+}
+
+/// Parses `/proc/loadavg`.
+pub fn parse_loadavg(content: &str) -> Option<LoadAverage> {
+    let mut fields = content.split_whitespace().map(|s| s.parse::<f32>().ok());
+    Some(LoadAverage {
+        one: fields.next()??,
+        five: fields.next()??,
+        fifteen: fields.next()??,
+    })
+}
+
+/// Current frequency of each core from the `cpu MHz` lines of `/proc/cpuinfo`.
+pub fn parse_cpuinfo_mhz(content: &str) -> Vec<u32> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim() != "cpu MHz" {
+                return None;
+            }
+            value.trim().parse::<f32>().ok().map(|mhz| mhz.round() as u32)
+        })
+        .collect()
+}
+
+/// Used memory percentage from `/proc/meminfo`.
+pub fn parse_meminfo(content: &str) -> Option<u8> {
+    let field = |name: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+    };
+    let total = field("MemTotal")?;
+    // Kernels before 3.14 have no MemAvailable
+    let available = match field("MemAvailable") {
+        Some(available) => available,
+        None => field("MemFree")? + field("Buffers").unwrap_or(0) + field("Cached").unwrap_or(0),
+    };
+    if total == 0 {
+        return None;
+    }
+    Some((total.saturating_sub(available) as f64 * 100.0 / total as f64).round() as u8)
+}
+
+/// Pairs per core usage with the frequencies of `/proc/cpuinfo`.
+pub fn core_stats(usage: Vec<u8>, mhz: &[u32]) -> Vec<CoreStats> {
+    usage
+        .into_iter()
+        .enumerate()
+        .map(|(i, usage)| CoreStats {
+            usage,
+            mhz: mhz.get(i).copied(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAT_1: &str = "\
+cpu  1000 0 500 8000 500 0 0 0 0 0
+cpu0 600 0 200 3900 300 0 0 0 0 0
+cpu1 400 0 300 4100 200 0 0 0 0 0
+intr 12345 0 0
+ctxt 67890
+btime 1700000000
+";
+
+    // 1000 more jiffies per core: cpu0 fully busy, cpu1 half busy
+    const STAT_2: &str = "\
+cpu  2500 0 500 8500 500 0 0 0 0 0
+cpu0 1600 0 200 3900 300 0 0 0 0 0
+cpu1 900 0 300 4600 200 0 0 0 0 0
+";
+
+    const MEMINFO: &str = "\
+MemTotal:       16000000 kB
+MemFree:         2000000 kB
+MemAvailable:    4000000 kB
+Buffers:          500000 kB
+Cached:          3000000 kB
+";
+
+    const CPUINFO: &str = "\
+processor\t: 0
+model name\t: AMD Ryzen 7 5800X 8-Core Processor
+cpu MHz\t\t: 3800.000
+cache size\t: 512 KB
+
+processor\t: 1
+model name\t: AMD Ryzen 7 5800X 8-Core Processor
+cpu MHz\t\t: 2199.540
+cache size\t: 512 KB
+";
+
+    #[test]
+    fn parses_proc_stat() {
+        let stat = parse_proc_stat(STAT_1).unwrap();
+        assert_eq!(stat.total, CpuTimes { idle: 8500, total: 10000 });
+        assert_eq!(stat.cores, vec![CpuTimes { idle: 4200, total: 5000 }, CpuTimes { idle: 4300, total: 5000 }]);
+        assert_eq!(parse_proc_stat("intr 12345\nctxt 67890\n"), None);
+    }
+
+    #[test]
+    fn usage_from_deltas() {
+        let mut sampler = CpuSampler::default();
+        // Since boot
+        assert_eq!(sampler.sample(parse_proc_stat(STAT_1).unwrap()), (15, vec![16, 14]));
+        assert_eq!(sampler.sample(parse_proc_stat(STAT_2).unwrap()), (75, vec![100, 50]));
+    }
+
+    #[test]
+    fn counters_going_backwards_saturate() {
+        let mut sampler = CpuSampler::default();
+        sampler.sample(parse_proc_stat(STAT_2).unwrap());
+        // e.g. a CPU taken offline and back, its counters restarting
+        assert_eq!(sampler.sample(parse_proc_stat(STAT_1).unwrap()), (0, vec![0, 0]));
+
+        let previous = CpuTimes { idle: 100, total: 1000 };
+        assert_eq!(CpuTimes { idle: 50, total: 1100 }.usage_since(&previous), 100);
+        assert_eq!(CpuTimes { idle: 200, total: 900 }.usage_since(&previous), 0);
+    }
+
+    #[test]
+    fn parses_meminfo() {
+        assert_eq!(parse_meminfo(MEMINFO), Some(75));
+    }
+
+    #[test]
+    fn meminfo_without_mem_available() {
+        let old_kernel: String = MEMINFO
+            .lines()
+            .filter(|line| !line.starts_with("MemAvailable"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        // free + buffers + cached = 5500000
+        assert_eq!(parse_meminfo(&old_kernel), Some(66));
+        assert_eq!(parse_meminfo("MemTotal: 16000000 kB\n"), None);
+        assert_eq!(parse_meminfo("MemTotal: 0 kB\nMemAvailable: 0 kB\n"), None);
+    }
+
+    #[test]
+    fn parses_loadavg() {
+        assert_eq!(
+            parse_loadavg("0.52 0.58 0.59 2/1234 56789\n"),
+            Some(LoadAverage { one: 0.52, five: 0.58, fifteen: 0.59 })
+        );
+    }
+
+    #[test]
+    fn malformed_loadavg() {
+        assert_eq!(parse_loadavg(""), None);
+        assert_eq!(parse_loadavg("0.52 0.58"), None);
+        assert_eq!(parse_loadavg("0.52 abc 0.59 2/1234 56789"), None);
+    }
+
+    #[test]
+    fn parses_cpuinfo_mhz() {
+        assert_eq!(parse_cpuinfo_mhz(CPUINFO), vec![3800, 2200]);
+        // No frequency on some ARM and virtualized CPUs
+        assert_eq!(parse_cpuinfo_mhz("processor\t: 0\nBogoMIPS\t: 48.00\n"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn cores_without_frequency() {
+        let mhz = parse_cpuinfo_mhz(CPUINFO);
+        assert_eq!(
+            core_stats(vec![10, 20, 30], &mhz),
+            vec![
+                CoreStats { usage: 10, mhz: Some(3800) },
+                CoreStats { usage: 20, mhz: Some(2200) },
+                CoreStats { usage: 30, mhz: None },
+            ]
+        );
+    }
+}
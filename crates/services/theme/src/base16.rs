@@ -0,0 +1,41 @@
+use gpui::Hsla;
+use std::collections::HashMap;
+
+use crate::palette::{parse_hex, Palette};
+
+/// Imports a base16 scheme file.
+///
+/// Both the classic flat layout (`base00: "2e3440"`) and the tinted-theming
+/// layout nesting the colors under `palette:` are accepted, so only the
+/// `baseXX` lines are looked at instead of pulling a YAML parser.
+pub fn parse(content: &str) -> Result<Palette, String> {
+    let mut colors: HashMap<String, Hsla> = HashMap::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.split(" #").next().unwrap_or(line).trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        if !is_base16_key(key) {
+            continue;
+        }
+
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        let color = parse_hex(value).map_err(|err| format!("line {}: {key}: {err}", line_no + 1))?;
+        colors.insert(normalize_key(key), color);
+    }
+
+    Palette::from_base16(&colors)
+}
+
+fn is_base16_key(key: &str) -> bool {
+    key.len() == 6
+        && key.starts_with("base")
+        && key[4..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// `base0d` and `base0D` both exist in the wild.
+fn normalize_key(key: &str) -> String {
+    format!("base{}", key[4..].to_ascii_uppercase())
+}
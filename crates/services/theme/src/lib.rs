@@ -0,0 +1,179 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+use gpui::{App, AppContext, AsyncApp, Entity, EventEmitter, Global, SharedString};
+use nwidgets_service_config::{config_dir, ConfigChanged, ConfigService, DirWatcher};
+use std::path::{Path, PathBuf};
+
+mod base16;
+mod palette;
+
+pub use palette::{parse_hex, Palette, ThemeFile};
+
+/// `<config_dir>/themes`, holding `<name>.toml` and base16 `<name>.yaml` files.
+pub fn themes_dir() -> PathBuf {
+    config_dir().join("themes")
+}
+
+/// Resolves a theme name: files from `dir` first, then the built-in themes.
+pub fn load_theme(dir: &Path, name: &str) -> Result<Palette, String> {
+    let toml_path = dir.join(format!("{name}.toml"));
+    if toml_path.exists() {
+        let content = std::fs::read_to_string(&toml_path).map_err(|err| format!("{}: {err}", toml_path.display()))?;
+        let file: ThemeFile = toml::from_str(&content).map_err(|err| format!("{}: {err}", toml_path.display()))?;
+        return file
+            .into_palette()
+            .map_err(|err| format!("{}: invalid theme\n  - {err}", toml_path.display()));
+    }
+
+    for extension in ["yaml", "yml"] {
+        let yaml_path = dir.join(format!("{name}.{extension}"));
+        if yaml_path.exists() {
+            let content = std::fs::read_to_string(&yaml_path).map_err(|err| format!("{}: {err}", yaml_path.display()))?;
+            return base16::parse(&content).map_err(|err| format!("{}: invalid base16 scheme: {err}", yaml_path.display()));
+        }
+    }
+
+    Palette::builtin(name).ok_or_else(|| format!("unknown theme `{name}` (looked in {})", dir.display()))
+}
+
+#[derive(Debug, Clone)]
+pub struct ThemeChanged;
+
+pub struct ThemeService {
+    pub palette: Palette,
+    pub name: SharedString,
+    /// Last load error, the previous palette stays active meanwhile.
+    pub error: Option<SharedString>,
+}
+
+impl EventEmitter<ThemeChanged> for ThemeService {}
+
+struct GlobalThemeService(Entity<ThemeService>);
+impl Global for GlobalThemeService {}
+
+impl ThemeService {
+    pub fn global(cx: &App) -> Entity<Self> {
+        cx.global::<GlobalThemeService>().0.clone()
+    }
+
+    /// Shortcut for reading the active palette.
+    pub fn palette(cx: &App) -> &Palette {
+        &cx.global::<GlobalThemeService>().0.read(cx).palette
+    }
+
+    pub fn init(cx: &mut App) -> Entity<Self> {
+        let name = ConfigService::config(cx).theme.name.clone();
+        let (palette, error) = match load_theme(&themes_dir(), &name) {
+            Ok(palette) => (palette, None),
+            Err(err) => {
+                log::error!("{err}");
+                (Palette::nord_dark(), Some(SharedString::from(err)))
+            }
+        };
+
+        let service = cx.new(|_cx| Self {
+            palette,
+            name: name.into(),
+            error,
+        });
+
+        cx.set_global(GlobalThemeService(service.clone()));
+        sync_component_theme(&palette, cx);
+
+        // A different theme name in config.toml
+        let service_entity = service.clone();
+        cx.subscribe(&ConfigService::global(cx), move |config, _: &ConfigChanged, cx| {
+            let name = config.read(cx).config.theme.name.clone();
+            if service_entity.read(cx).name.as_ref() != name {
+                reload(&service_entity, name, cx);
+            }
+        })
+        .detach();
+
+        let (tx, mut rx) = mpsc::unbounded::<()>();
+
+        // Edits to the theme files themselves
+        gpui_tokio::Tokio::spawn(cx, async move {
+            let dir = themes_dir();
+            if let Err(err) = std::fs::create_dir_all(&dir) {
+                log::warn!("Cannot create {}: {err}, theme hot reload disabled", dir.display());
+                return;
+            }
+
+            let mut watcher = match DirWatcher::new(&dir, |_| true) {
+                Ok(watcher) => watcher,
+                Err(err) => {
+                    log::warn!("Cannot watch {}: {err}", dir.display());
+                    return;
+                }
+            };
+
+            while watcher.changed().await {
+                if tx.unbounded_send(()).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        // UI Thread listener
+        let service_entity = service.clone();
+        cx.spawn(|cx: &mut AsyncApp| {
+            let cx = cx.clone();
+            async move {
+                while rx.next().await.is_some() {
+                    let _ = cx.update(|cx| {
+                        let name = service_entity.read(cx).name.to_string();
+                        reload(&service_entity, name, cx);
+                    });
+                }
+            }
+        })
+        .detach();
+
+        service
+    }
+}
+
+fn reload(service: &Entity<ThemeService>, name: String, cx: &mut App) {
+    let result = load_theme(&themes_dir(), &name);
+
+    let changed = service.update(cx, |srv, cx| {
+        srv.name = name.into();
+        let changed = match result {
+            Ok(palette) => {
+                srv.error = None;
+                let changed = srv.palette != palette;
+                srv.palette = palette;
+                changed
+            }
+            Err(err) => {
+                log::error!("{err}");
+                srv.error = Some(err.into());
+                false
+            }
+        };
+        if changed {
+            log::info!("Applied theme `{}`", srv.name);
+            cx.emit(ThemeChanged);
+        }
+        cx.notify();
+        changed
+    });
+
+    if changed {
+        let palette = *ThemeService::palette(cx);
+        sync_component_theme(&palette, cx);
+        cx.refresh_windows();
+    }
+}
+
+/// Keeps the gpui-component colors used by the shared widgets (text, lists,
+/// inputs...) in line with the palette. The transparent window background
+/// set up at startup is left alone.
+fn sync_component_theme(palette: &Palette, cx: &mut App) {
+    let mut theme = gpui_component::Theme::global(cx).clone();
+    theme.colors.foreground = palette.foreground;
+    theme.colors.muted_foreground = palette.foreground_dim;
+    cx.set_global(theme);
+}
@@ -0,0 +1,169 @@
+use gpui::{rgb, Hsla};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Semantic colors shared by every view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Surface backgrounds (bar, panel, launcher...).
+    pub background: Hsla,
+    /// Cards and rows drawn on top of `background`.
+    pub surface: Hsla,
+    /// Selected rows and hovered surfaces.
+    pub surface_alt: Hsla,
+    /// Disabled text, separators and subtle borders.
+    pub muted: Hsla,
+    pub foreground: Hsla,
+    /// Secondary text, slightly dimmer than `foreground`.
+    pub foreground_dim: Hsla,
+    pub accent: Hsla,
+    pub danger: Hsla,
+    pub warning: Hsla,
+    pub success: Hsla,
+    pub highlight: Hsla,
+    /// Outline of the surfaces and their concave corners.
+    pub border: Hsla,
+}
+
+impl Palette {
+    pub fn nord_dark() -> Self {
+        Self {
+            background: rgb(0x2e3440).into(),
+            surface: rgb(0x3b4252).into(),
+            surface_alt: rgb(0x434c5e).into(),
+            muted: rgb(0x4c566a).into(),
+            foreground: rgb(0xe5e9f0).into(),
+            foreground_dim: rgb(0xd8dee9).into(),
+            accent: rgb(0x88c0d0).into(),
+            danger: rgb(0xbf616a).into(),
+            warning: rgb(0xebcb8b).into(),
+            success: rgb(0xa3be8c).into(),
+            highlight: rgb(0xb48ead).into(),
+            border: Hsla::from(rgb(0x88c0d0)).opacity(0.3),
+        }
+    }
+
+    pub fn nord_light() -> Self {
+        Self {
+            background: rgb(0xeceff4).into(),
+            surface: rgb(0xe5e9f0).into(),
+            surface_alt: rgb(0xd8dee9).into(),
+            muted: rgb(0x7b88a1).into(),
+            foreground: rgb(0x2e3440).into(),
+            foreground_dim: rgb(0x3b4252).into(),
+            accent: rgb(0x5e81ac).into(),
+            danger: rgb(0xbf616a).into(),
+            warning: rgb(0xd08770).into(),
+            success: rgb(0x8fa876).into(),
+            highlight: rgb(0xb48ead).into(),
+            border: Hsla::from(rgb(0x5e81ac)).opacity(0.3),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "nord-dark" | "nord" => Some(Self::nord_dark()),
+            "nord-light" => Some(Self::nord_light()),
+            _ => None,
+        }
+    }
+
+    /// Builds a palette from a base16 scheme (`base00`..`base0F`).
+    pub fn from_base16(colors: &HashMap<String, Hsla>) -> Result<Self, String> {
+        let get = |key: &str| colors.get(key).copied().ok_or_else(|| format!("missing `{key}`"));
+        let accent = get("base0D")?;
+        Ok(Self {
+            background: get("base00")?,
+            surface: get("base01")?,
+            surface_alt: get("base02")?,
+            muted: get("base03")?,
+            foreground: get("base05")?,
+            foreground_dim: get("base04")?,
+            accent,
+            danger: get("base08")?,
+            warning: get("base0A")?,
+            success: get("base0B")?,
+            highlight: get("base0E")?,
+            border: accent.opacity(0.3),
+        })
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa`, the leading `#` being optional.
+pub fn parse_hex(value: &str) -> Result<Hsla, String> {
+    let hex = value.trim().trim_start_matches('#');
+    let parsed = u32::from_str_radix(hex, 16).map_err(|_| format!("`{value}` is not a hex color"));
+    match hex.len() {
+        6 => Ok(rgb(parsed?).into()),
+        8 => Ok(gpui::rgba(parsed?).into()),
+        _ => Err(format!("`{value}` is not a hex color")),
+    }
+}
+
+/// Theme file stored as `themes/<name>.toml`. Missing colors come from `inherits`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeFile {
+    pub inherits: Option<String>,
+    pub colors: ThemeFileColors,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeFileColors {
+    pub background: Option<String>,
+    pub surface: Option<String>,
+    pub surface_alt: Option<String>,
+    pub muted: Option<String>,
+    pub foreground: Option<String>,
+    pub foreground_dim: Option<String>,
+    pub accent: Option<String>,
+    pub danger: Option<String>,
+    pub warning: Option<String>,
+    pub success: Option<String>,
+    pub highlight: Option<String>,
+    pub border: Option<String>,
+}
+
+impl ThemeFile {
+    pub fn into_palette(self) -> Result<Palette, String> {
+        let base_name = self.inherits.as_deref().unwrap_or("nord-dark");
+        let mut palette = Palette::builtin(base_name).ok_or_else(|| format!("unknown base theme `{base_name}`"))?;
+        let colors = self.colors;
+
+        let mut errors = Vec::new();
+        let mut apply = |slot: &mut Hsla, key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                match parse_hex(&value) {
+                    Ok(color) => *slot = color,
+                    Err(err) => errors.push(format!("colors.{key}: {err}")),
+                }
+            }
+        };
+
+        let accent_overridden = colors.accent.is_some();
+        apply(&mut palette.background, "background", colors.background);
+        apply(&mut palette.surface, "surface", colors.surface);
+        apply(&mut palette.surface_alt, "surface_alt", colors.surface_alt);
+        apply(&mut palette.muted, "muted", colors.muted);
+        apply(&mut palette.foreground, "foreground", colors.foreground);
+        apply(&mut palette.foreground_dim, "foreground_dim", colors.foreground_dim);
+        apply(&mut palette.accent, "accent", colors.accent);
+        apply(&mut palette.danger, "danger", colors.danger);
+        apply(&mut palette.warning, "warning", colors.warning);
+        apply(&mut palette.success, "success", colors.success);
+        apply(&mut palette.highlight, "highlight", colors.highlight);
+
+        // The border follows the accent unless given explicitly
+        if colors.border.is_none() && accent_overridden {
+            palette.border = palette.accent.opacity(0.3);
+        }
+        apply(&mut palette.border, "border", colors.border);
+
+        if errors.is_empty() {
+            Ok(palette)
+        } else {
+            Err(errors.join("\n  - "))
+        }
+    }
+}
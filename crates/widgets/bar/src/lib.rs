@@ -3,11 +3,17 @@ use gpui::*;
 
 /// Rayon des coins arrondis sous la barre.
 pub const CORNER_RADIUS: f32 = 12.0;
-pub const BAR_HEIGHT: f32 = 50.0;
 
 /// Ouvre la fenêtre layer shell pour le panneau/bar.
+///
+/// `width` et `height` viennent de la configuration : la hauteur sert aussi de zone exclusive,
+/// donc un changement de taille impose de rouvrir la surface.
+/// `display_id` fixe l'écran de la surface ; `None` laisse le compositeur choisir.
 pub fn open<T: gpui::Render + 'static>(
     cx: &mut App,
+    display_id: Option<DisplayId>,
+    width: f32,
+    height: f32,
     build_view: impl FnOnce(&mut Window, &mut App) -> Entity<T>,
 ) -> anyhow::Result<WindowHandle<T>> {
     let window = cx.open_window(
@@ -15,17 +21,18 @@ pub fn open<T: gpui::Render + 'static>(
             window_bounds: Some(WindowBounds::Windowed(Bounds {
                 origin: Point { x: px(0.0), y: px(0.0) },
                 size: Size {
-                    width: px(3440.0),
+                    width: px(width),
                     height: px(350.0), // Surface height expanded for GPUI popovers & context menus
                 },
             })),
             titlebar: None,
+            display_id,
             window_background: WindowBackgroundAppearance::Transparent,
             kind: WindowKind::LayerShell(LayerShellOptions {
                 namespace: "nwidgets-panel".to_string(),
                 layer: Layer::Top,
                 anchor: Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
-                exclusive_zone: Some(px(BAR_HEIGHT)),
+                exclusive_zone: Some(px(height)),
                 margin: None,
                 keyboard_interactivity: KeyboardInteractivity::None,
                 ..Default::default()
@@ -33,11 +40,11 @@ pub fn open<T: gpui::Render + 'static>(
             ..Default::default()
         },
         |window, cx| {
-            // Restreindre la zone de clic à la barre uniquement (50px + 12px de coins = 62px)
+            // Restreindre la zone de clic à la barre uniquement (hauteur de barre + 12px de coins)
             // afin que la zone transparente sous la barre ne bloque pas les clics sur les autres applications !
             window.set_input_region(Some(&[Bounds {
                 origin: point(px(0.0), px(0.0)),
-                size: size(px(3440.0), px(BAR_HEIGHT)),
+                size: size(px(width), px(height)),
             }]));
             build_view(window, cx)
         },
@@ -5,8 +5,10 @@ pub const BAR_HEIGHT: f32 = 50.0;
 pub const CHAT_WIDTH: f32 = 600.0;
 
 /// Ouvre la fenêtre layer shell pour le chat (masquée par défaut).
+/// Une surface layer shell ne change pas d'écran : la rouvrir pour la déplacer.
 pub fn open<T: gpui::Render + 'static>(
     cx: &mut App,
+    display_id: Option<DisplayId>,
     build_view: impl FnOnce(&mut Window, &mut App) -> Entity<T>,
 ) -> anyhow::Result<WindowHandle<T>> {
     let window = cx.open_window(
@@ -19,6 +21,7 @@ pub fn open<T: gpui::Render + 'static>(
                 },
             })),
             titlebar: None,
+            display_id,
             window_background: WindowBackgroundAppearance::Transparent,
             kind: WindowKind::LayerShell(LayerShellOptions {
                 namespace: "nwidgets-chat".to_string(),
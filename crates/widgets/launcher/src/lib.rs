@@ -2,12 +2,12 @@ use gpui::layer_shell::{Anchor, KeyboardInteractivity, Layer, LayerShellOptions}
 use gpui::*;
 
 pub const BAR_HEIGHT: f32 = 50.0;
-pub const LAUNCHER_WIDTH: f32 = 700.0;
-pub const LAUNCHER_HEIGHT: f32 = 482.0;
 
 /// Ouvre la fenêtre layer shell pour le lanceur d'applications (masquée par défaut).
+/// Une surface layer shell ne change pas d'écran : la rouvrir pour la déplacer.
 pub fn open<T: gpui::Render + 'static>(
     cx: &mut App,
+    display_id: Option<DisplayId>,
     build_view: impl FnOnce(&mut Window, &mut App) -> Entity<T>,
 ) -> anyhow::Result<WindowHandle<T>> {
     let window = cx.open_window(
@@ -17,6 +17,7 @@ pub fn open<T: gpui::Render + 'static>(
                 size: Size { width: px(1.0), height: px(1.0) },
             })),
             titlebar: None,
+            display_id,
             window_background: WindowBackgroundAppearance::Transparent,
             kind: WindowKind::LayerShell(LayerShellOptions {
                 namespace: "nwidgets-launcher".to_string(),
@@ -38,10 +39,12 @@ pub fn open<T: gpui::Render + 'static>(
     Ok(window)
 }
 
-/// Bascule la visibilité de la fenêtre du lanceur.
+/// Bascule la visibilité de la fenêtre du lanceur, dimensionnée selon la configuration.
 pub fn set_visible<T: 'static>(
     handle: &WindowHandle<T>,
     visible: bool,
+    width: f32,
+    height: f32,
     focus_handle: Option<&gpui::FocusHandle>,
     cx: &mut App,
 ) {
@@ -50,10 +53,10 @@ pub fn set_visible<T: 'static>(
             window.set_layer(Layer::Overlay);
             window.set_input_region(Some(&[Bounds {
                 origin: point(px(0.0), px(0.0)),
-                size: size(px(LAUNCHER_WIDTH), px(LAUNCHER_HEIGHT)),
+                size: size(px(width), px(height)),
             }]));
             window.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
-            window.resize(size(px(LAUNCHER_WIDTH), px(LAUNCHER_HEIGHT)));
+            window.resize(size(px(width), px(height)));
             if let Some(fh) = focus_handle {
                 window.focus(fh, cx);
             }
@@ -3,6 +3,7 @@ use gpui::*;
 
 pub fn open<T: gpui::Render + 'static>(
     cx: &mut App,
+    display_id: Option<DisplayId>,
     build_view: impl FnOnce(&mut Window, &mut App) -> Entity<T>,
 ) -> Option<WindowHandle<T>> {
     cx.open_window(
@@ -12,6 +13,7 @@ pub fn open<T: gpui::Render + 'static>(
                 size: Size { width: px(1.0), height: px(1.0) },
             })),
             titlebar: None,
+            display_id,
             window_background: WindowBackgroundAppearance::Transparent,
             kind: WindowKind::LayerShell(LayerShellOptions {
                 namespace: "nwidgets-notifications".to_string(),
@@ -6,8 +6,10 @@ pub const BAR_HEIGHT: f32 = 50.0;
 pub const PANEL_WIDTH: f32 = 600.0;
 
 /// Ouvre la fenêtre layer shell pour le panneau latéral (masquée par défaut).
+/// Une surface layer shell ne change pas d'écran : la rouvrir pour la déplacer.
 pub fn open<T: gpui::Render + 'static>(
     cx: &mut App,
+    display_id: Option<DisplayId>,
     build_view: impl FnOnce(&mut Window, &mut App) -> Entity<T>,
 ) -> anyhow::Result<WindowHandle<T>> {
     let window = cx.open_window(
@@ -20,6 +22,7 @@ pub fn open<T: gpui::Render + 'static>(
                 },
             })),
             titlebar: None,
+            display_id,
             window_background: WindowBackgroundAppearance::Transparent,
             kind: WindowKind::LayerShell(LayerShellOptions {
                 namespace: "nwidgets-panel".to_string(),
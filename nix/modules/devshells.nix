@@ -23,6 +23,8 @@
           rustToolchain
           cargo-nextest
           cargo-machete
+          # dbus-daemon, for the D-Bus service tests
+          dbus
         ];
 
         env = builtins.removeAttrs baseEnv [